@@ -47,7 +47,7 @@ fn print_ascii_art() {
 }
 
 fn status_info(config: &Parseable, scheme: &str, id: Uid) {
-    let address = format!(
+    let mut address = format!(
         "\"{}://{}\" ({}), \":{}\" (livetail), \":{}\" (flight protocol)",
         scheme,
         config.options.address,
@@ -55,6 +55,9 @@ fn status_info(config: &Parseable, scheme: &str, id: Uid) {
         config.options.grpc_port,
         config.options.flight_port
     );
+    if let Some(port) = config.options.otel_grpc_port {
+        address.push_str(&format!(", \":{port}\" (otlp grpc)"));
+    }
 
     let mut credentials =
         String::from("\"As set in P_USERNAME and P_PASSWORD environment variables\"");
@@ -28,6 +28,7 @@ use crate::{
     oidc::{self, OpenidConfig},
     option::{Compression, Mode, validation},
     storage::{AzureBlobConfig, FSConfig, GcsConfig, S3Config},
+    syslog::SyslogListener,
 };
 
 /// Default username and password for Parseable server, used by default for local mode.
@@ -295,6 +296,38 @@ pub struct Options {
     )]
     pub flight_port: u16,
 
+    #[arg(
+        long,
+        env = "P_OTEL_GRPC_PORT",
+        help = "Port for the OTLP gRPC receiver for logs, metrics and traces, started when set, usually 4317"
+    )]
+    pub otel_grpc_port: Option<u16>,
+
+    #[arg(
+        long,
+        env = "P_SYSLOG_LISTENERS",
+        value_delimiter = ',',
+        value_parser = validation::syslog_listener,
+        help = "Comma separated syslog listeners to start in ingest mode, given as <tcp|udp>:<port>=<stream>, e.g. udp:514=network"
+    )]
+    pub syslog_listeners: Vec<SyslogListener>,
+
+    #[arg(
+        long,
+        env = "P_FLUENT_FORWARD_PORT",
+        help = "Port for the Fluent Forward protocol listener, started in ingest mode when set"
+    )]
+    pub fluent_forward_port: Option<u16>,
+
+    #[arg(
+        long = "geoip-db",
+        env = "P_GEOIP_DB",
+        value_delimiter = ',',
+        value_parser = validation::canonicalize_path,
+        help = "Comma separated paths of MaxMind format (mmdb) databases, e.g. City and ASN, used to enrich IP fields of streams. Databases are reloaded when they change"
+    )]
+    pub geoip_db: Vec<PathBuf>,
+
     // Performance settings
     #[arg(
         long,
@@ -81,10 +81,13 @@ impl ConnectorError {
 
 #[derive(ValueEnum, Default, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum BadData {
+    /// Fail the batch the record is part of
     #[default]
     Fail,
+    /// Skip the record
     Drop,
-    Dlt, //TODO: Implement Dead Letter Topic support when needed
+    /// Produce the record to the dead letter topic
+    Dlt,
 }
 
 impl FromStr for BadData {
@@ -17,11 +17,14 @@
  */
 
 use crate::connectors::common::BadData;
+use crate::option::validation;
 use clap::{Args, Parser, ValueEnum};
 use rdkafka::{ClientConfig, Offset};
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 use std::time::Duration;
+use url::Url;
 
 #[derive(Debug, Clone, Parser)]
 pub struct KafkaConfig {
@@ -69,9 +72,18 @@ pub struct KafkaConfig {
         required = false,
         default_value_t = BadData::Fail,
         env = "P_CONNECTOR_BAD_DATA_POLICY",
-        help = "Policy for handling bad data"
+        help = "Policy for records that can't be decoded or ingested: fail the batch, drop the record or produce it to the dead letter topic"
     )]
     pub bad_data: BadData,
+
+    #[arg(
+        long = "dlt-topic",
+        env = "P_KAFKA_DLT_TOPIC",
+        value_name = "topic",
+        required = false,
+        help = "Dead letter topic for bad records, required with the dlt bad data policy"
+    )]
+    pub dlt_topic: Option<String>,
 }
 
 #[derive(Debug, Clone, Args)]
@@ -281,6 +293,80 @@ pub struct ConsumerConfig {
         help = "Statistics interval in milliseconds"
     )]
     pub stats_interval_ms: u64,
+
+    #[arg(
+        value_enum,
+        long = "consumer-value-decoder",
+        env = "P_KAFKA_CONSUMER_VALUE_DECODER",
+        required = false,
+        default_value_t = ValueDecoder::Json,
+        help = "Decoder of record values"
+    )]
+    pub value_decoder: ValueDecoder,
+
+    #[arg(
+        long = "schema-registry-url",
+        env = "P_KAFKA_SCHEMA_REGISTRY_URL",
+        value_name = "url",
+        required = false,
+        value_parser = validation::url,
+        help = "Confluent compatible schema registry URL, required with the avro and protobuf decoders"
+    )]
+    pub schema_registry_url: Option<Url>,
+
+    #[arg(
+        long = "schema-registry-username",
+        env = "P_KAFKA_SCHEMA_REGISTRY_USERNAME",
+        required = false,
+        help = "Schema registry basic auth username"
+    )]
+    pub schema_registry_username: Option<String>,
+
+    #[arg(
+        long = "schema-registry-password",
+        env = "P_KAFKA_SCHEMA_REGISTRY_PASSWORD",
+        required = false,
+        help = "Schema registry basic auth password"
+    )]
+    pub schema_registry_password: Option<String>,
+
+    #[arg(
+        long = "consumer-stream-routes",
+        env = "P_KAFKA_CONSUMER_STREAM_ROUTES",
+        value_name = "regex=template",
+        required = false,
+        value_delimiter = ',',
+        help = "Comma-separated list of routes of topics to streams, as a topic regex and a stream name template referring to capture groups as $1 or ${name}. Topics matching no route go to the stream of their name"
+    )]
+    pub stream_routes: Vec<TopicRoute>,
+
+    #[arg(
+        long = "consumer-stream-route-field",
+        env = "P_KAFKA_CONSUMER_STREAM_ROUTE_FIELD",
+        value_name = "field",
+        required = false,
+        help = "Top level field of record values naming the stream to ingest them in, over topic routes"
+    )]
+    pub stream_route_field: Option<String>,
+
+    #[arg(
+        long = "consumer-include-metadata",
+        env = "P_KAFKA_CONSUMER_INCLUDE_METADATA",
+        required = false,
+        default_value_t = false,
+        help = "Add the key, partition, offset and timestamp of records as the p_kafka_key, p_kafka_partition, p_kafka_offset and p_kafka_timestamp columns"
+    )]
+    pub include_metadata: bool,
+
+    #[arg(
+        long = "consumer-header-columns",
+        env = "P_KAFKA_CONSUMER_HEADER_COLUMNS",
+        value_name = "headers",
+        required = false,
+        value_delimiter = ',',
+        help = "Comma-separated list of record headers to add as p_kafka_header_<name> columns"
+    )]
+    pub header_columns: Vec<String>,
 }
 
 #[derive(Debug, Clone, Args)]
@@ -624,6 +710,19 @@ impl KafkaConfig {
             security.validate()?;
         }
 
+        if self.bad_data == BadData::Dlt {
+            let Some(dlt_topic) = self.dlt_topic.as_ref().filter(|topic| !topic.is_empty()) else {
+                anyhow::bail!("Dead letter topic must be set with the dlt bad data policy");
+            };
+            if self
+                .consumer
+                .as_ref()
+                .is_some_and(|consumer| consumer.topics.contains(dlt_topic))
+            {
+                anyhow::bail!("Dead letter topic must not be one of the consumer topics");
+            }
+        }
+
         Ok(())
     }
 }
@@ -636,6 +735,21 @@ impl ConsumerConfig {
         if self.topics.is_empty() {
             anyhow::bail!("At least one topic must be specified");
         }
+        if matches!(
+            self.value_decoder,
+            ValueDecoder::Avro | ValueDecoder::Protobuf
+        ) && self.schema_registry_url.is_none()
+        {
+            anyhow::bail!(
+                "Schema registry URL is required with the {} decoder",
+                self.value_decoder
+            );
+        }
+        if let Some(url) = &self.schema_registry_url
+            && !matches!(url.scheme(), "http" | "https")
+        {
+            anyhow::bail!("Schema registry URL must be an http or https URL");
+        }
         Ok(())
     }
 
@@ -884,6 +998,7 @@ impl Default for KafkaConfig {
             // Security configuration with plaintext protocol
             security: Some(SecurityConfig::default()),
             bad_data: BadData::default(),
+            dlt_topic: None,
         }
     }
 }
@@ -951,6 +1066,14 @@ impl Default for ConsumerConfig {
             isolation_level: "read_committed".to_string(),
             fetch_message_max_bytes: 1048576,
             stats_interval_ms: 10000,
+            value_decoder: ValueDecoder::Json,
+            schema_registry_url: None,
+            schema_registry_username: None,
+            schema_registry_password: None,
+            stream_routes: vec![],
+            stream_route_field: None,
+            include_metadata: false,
+            header_columns: vec![],
         }
     }
 }
@@ -1008,6 +1131,53 @@ impl std::str::FromStr for Acks {
     }
 }
 
+/// How record values are decoded. Avro and Protobuf values are expected in the
+/// Confluent wire format, prefixed with the id of their schema in the schema registry.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueDecoder {
+    Json,
+    Avro,
+    Protobuf,
+    /// Each value is a UTF-8 string, stored as is in the `message` column
+    Raw,
+}
+
+impl std::fmt::Display for ValueDecoder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ValueDecoder::Json => write!(f, "json"),
+            ValueDecoder::Avro => write!(f, "avro"),
+            ValueDecoder::Protobuf => write!(f, "protobuf"),
+            ValueDecoder::Raw => write!(f, "raw"),
+        }
+    }
+}
+
+/// Route of the records of the topics matching a regex to the stream named by a template
+#[derive(Debug, Clone)]
+pub struct TopicRoute {
+    pub pattern: Regex,
+    pub template: String,
+}
+
+impl std::str::FromStr for TopicRoute {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some((pattern, template)) = s.rsplit_once('=').filter(|(_, t)| !t.is_empty()) else {
+            return Err(format!("Route {s} must be given as regex=template"));
+        };
+        // topics must match as a whole
+        let pattern = Regex::new(&format!("^(?:{pattern})$"))
+            .map_err(|e| format!("Invalid route regex {pattern}: {e}"))?;
+
+        Ok(Self {
+            pattern,
+            template: template.to_owned(),
+        })
+    }
+}
+
 #[derive(ValueEnum, Debug, Clone)]
 pub enum SourceOffset {
     Earliest,
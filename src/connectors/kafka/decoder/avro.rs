@@ -0,0 +1,728 @@
+/*
+ * Parseable Server (C) 2022 - 2025 Parseable, Inc.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ */
+
+//! Decoding of Avro binary encoded values with their writer schema
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anyhow::{Context, bail};
+use base64::{Engine, prelude::BASE64_STANDARD};
+use chrono::{DateTime, SecondsFormat};
+use serde_json::{Map, Number, Value};
+
+use super::{ColumnType, DecodedValue, Layout, Row, Scalar, VALUE_KEY};
+
+/// Values nested deeper than this are rejected, recursive schemas allowing any depth
+const MAX_DEPTH: usize = 64;
+
+/// Items take at least a byte but for nulls and empty records, of which arrays and maps
+/// can't hold more than this beyond the bytes left in the value
+const MAX_EMPTY_ITEMS: i64 = 1024;
+
+#[derive(Debug, Clone, Copy)]
+enum Precision {
+    Millis,
+    Micros,
+    Nanos,
+}
+
+impl Precision {
+    fn to_millis(self, value: i64) -> i64 {
+        match self {
+            Precision::Millis => value,
+            Precision::Micros => value.div_euclid(1_000),
+            Precision::Nanos => value.div_euclid(1_000_000),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Type {
+    Null,
+    Boolean,
+    Int,
+    Long,
+    Float,
+    Double,
+    Bytes,
+    String,
+    Date,
+    TimeMillis,
+    TimeMicros,
+    Timestamp(Precision),
+    /// Decimal of the given scale, encoded as bytes
+    Decimal(u32),
+    Named(usize),
+    Array(Box<Type>),
+    Map(Box<Type>),
+    Union(Vec<Type>),
+}
+
+#[derive(Debug)]
+enum Named {
+    Record(Vec<(String, Type)>),
+    Enum(Vec<String>),
+    Fixed {
+        size: usize,
+        decimal_scale: Option<u32>,
+    },
+}
+
+/// The writer schema of Avro values
+#[derive(Debug)]
+pub struct AvroSchema {
+    root: Type,
+    named: Vec<Named>,
+    layout: Option<Arc<Layout>>,
+}
+
+impl AvroSchema {
+    /// Parses a schema given the schemas it references, which come before the
+    /// schemas they depend on themselves
+    pub fn parse(schema: &str, references: &[&str]) -> anyhow::Result<Self> {
+        let mut parser = Parser::default();
+        for reference in references {
+            parser.parse(&serde_json::from_str(reference)?, None)?;
+        }
+        let root = parser.parse(&serde_json::from_str(schema)?, None)?;
+
+        let mut schema = Self {
+            root,
+            named: parser.named,
+            layout: None,
+        };
+        schema.layout = schema.layout().map(Arc::new);
+
+        Ok(schema)
+    }
+
+    pub fn decode(&self, payload: &[u8]) -> anyhow::Result<DecodedValue> {
+        let mut reader = Reader(payload);
+        let decoded = match &self.layout {
+            Some(layout) => {
+                let mut row = Vec::with_capacity(layout.len());
+                self.row(&self.root, &mut reader, &mut row)?;
+                DecodedValue::Row(layout.clone(), row)
+            }
+            None => match self.json(&self.root, &mut reader, 0)? {
+                json @ Value::Object(_) => DecodedValue::Json(json),
+                value => DecodedValue::Json(Value::Object(Map::from_iter([(
+                    VALUE_KEY.to_owned(),
+                    value,
+                )]))),
+            },
+        };
+        if !reader.0.is_empty() {
+            bail!("{} bytes left after the value", reader.0.len());
+        }
+
+        Ok(decoded)
+    }
+
+    /// The columns of the values, if the schema is a record of scalars and nested records
+    fn layout(&self) -> Option<Layout> {
+        let Type::Named(index) = &self.root else {
+            return None;
+        };
+        let Named::Record(fields) = &self.named[*index] else {
+            return None;
+        };
+
+        let mut layout = Layout::default();
+        let mut path = vec![*index];
+        for (name, ty) in fields {
+            self.columns(ty, name.clone(), &mut layout, &mut path)?;
+        }
+
+        Some(layout)
+    }
+
+    fn columns(
+        &self,
+        ty: &Type,
+        name: String,
+        layout: &mut Layout,
+        path: &mut Vec<usize>,
+    ) -> Option<()> {
+        let column_type = match ty {
+            Type::Null => return Some(()),
+            Type::Boolean => ColumnType::Boolean,
+            Type::Int | Type::Long | Type::TimeMillis | Type::TimeMicros => ColumnType::Int64,
+            Type::Float | Type::Double | Type::Decimal(_) => ColumnType::Float64,
+            Type::Bytes | Type::String => ColumnType::Utf8,
+            Type::Date => ColumnType::Date32,
+            Type::Timestamp(_) => ColumnType::Timestamp,
+            Type::Named(index) => match &self.named[*index] {
+                Named::Record(fields) => {
+                    // recursive records can't be flattened
+                    if path.contains(index) {
+                        return None;
+                    }
+                    path.push(*index);
+                    for (field, ty) in fields {
+                        self.columns(ty, format!("{name}_{field}"), layout, path)?;
+                    }
+                    path.pop();
+                    return Some(());
+                }
+                Named::Enum(_) => ColumnType::Utf8,
+                Named::Fixed {
+                    decimal_scale: Some(_),
+                    ..
+                } => ColumnType::Float64,
+                Named::Fixed { .. } => ColumnType::Utf8,
+            },
+            Type::Union(branches) => return self.columns(nullable(branches)?, name, layout, path),
+            Type::Array(_) | Type::Map(_) => return None,
+        };
+
+        layout.push(name, column_type).then_some(())
+    }
+
+    /// Number of columns of a type
+    fn width(&self, ty: &Type) -> usize {
+        match ty {
+            Type::Null => 0,
+            Type::Named(index) => match &self.named[*index] {
+                Named::Record(fields) => fields.iter().map(|(_, ty)| self.width(ty)).sum(),
+                _ => 1,
+            },
+            Type::Union(branches) => nullable(branches).map_or(0, |ty| self.width(ty)),
+            _ => 1,
+        }
+    }
+
+    fn row(&self, ty: &Type, reader: &mut Reader, row: &mut Row) -> anyhow::Result<()> {
+        let scalar = match ty {
+            Type::Null => return Ok(()),
+            Type::Boolean => Scalar::Boolean(reader.boolean()?),
+            Type::Int | Type::Long | Type::TimeMillis | Type::TimeMicros => {
+                Scalar::Int64(reader.long()?)
+            }
+            Type::Float => Scalar::Float64(f64::from(reader.float()?)),
+            Type::Double => Scalar::Float64(reader.double()?),
+            Type::Decimal(scale) => Scalar::Float64(decimal(reader.bytes()?, *scale)),
+            Type::Bytes => Scalar::Utf8(BASE64_STANDARD.encode(reader.bytes()?)),
+            Type::String => Scalar::Utf8(reader.string()?.to_owned()),
+            Type::Date => Scalar::Date32(i32::try_from(reader.long()?)?),
+            Type::Timestamp(precision) => Scalar::Timestamp(precision.to_millis(reader.long()?)),
+            Type::Named(index) => match &self.named[*index] {
+                Named::Record(fields) => {
+                    for (_, ty) in fields {
+                        self.row(ty, reader, row)?;
+                    }
+                    return Ok(());
+                }
+                Named::Enum(symbols) => Scalar::Utf8(pick(symbols, reader)?.clone()),
+                Named::Fixed {
+                    size,
+                    decimal_scale: Some(scale),
+                } => Scalar::Float64(decimal(reader.take(*size)?, *scale)),
+                Named::Fixed { size, .. } => {
+                    Scalar::Utf8(BASE64_STANDARD.encode(reader.take(*size)?))
+                }
+            },
+            Type::Union(branches) => {
+                let branch = pick(branches, reader)?;
+                if matches!(branch, Type::Null) {
+                    let width = nullable(branches).map_or(0, |ty| self.width(ty));
+                    row.extend(std::iter::repeat_n(None, width));
+                    return Ok(());
+                }
+                return self.row(branch, reader, row);
+            }
+            Type::Array(_) | Type::Map(_) => bail!("arrays and maps aren't decoded to columns"),
+        };
+        row.push(Some(scalar));
+
+        Ok(())
+    }
+
+    fn json(&self, ty: &Type, reader: &mut Reader, depth: usize) -> anyhow::Result<Value> {
+        if depth > MAX_DEPTH {
+            bail!("value is nested deeper than {MAX_DEPTH} levels");
+        }
+
+        Ok(match ty {
+            Type::Null => Value::Null,
+            Type::Boolean => Value::Bool(reader.boolean()?),
+            Type::Int | Type::Long | Type::TimeMillis | Type::TimeMicros => {
+                Value::from(reader.long()?)
+            }
+            Type::Float => float(f64::from(reader.float()?)),
+            Type::Double => float(reader.double()?),
+            Type::Decimal(scale) => float(decimal(reader.bytes()?, *scale)),
+            Type::Bytes => Value::String(BASE64_STANDARD.encode(reader.bytes()?)),
+            Type::String => Value::String(reader.string()?.to_owned()),
+            Type::Date => {
+                let days = reader.long()?;
+                let date = DateTime::from_timestamp(days.saturating_mul(86_400), 0)
+                    .context("date out of range")?;
+                Value::String(date.date_naive().to_string())
+            }
+            Type::Timestamp(precision) => {
+                let millis = precision.to_millis(reader.long()?);
+                let timestamp =
+                    DateTime::from_timestamp_millis(millis).context("timestamp out of range")?;
+                Value::String(timestamp.to_rfc3339_opts(SecondsFormat::AutoSi, true))
+            }
+            Type::Named(index) => match &self.named[*index] {
+                Named::Record(fields) => {
+                    let mut object = Map::with_capacity(fields.len());
+                    for (name, ty) in fields {
+                        object.insert(name.clone(), self.json(ty, reader, depth + 1)?);
+                    }
+                    Value::Object(object)
+                }
+                Named::Enum(symbols) => Value::String(pick(symbols, reader)?.clone()),
+                Named::Fixed {
+                    size,
+                    decimal_scale: Some(scale),
+                } => float(decimal(reader.take(*size)?, *scale)),
+                Named::Fixed { size, .. } => {
+                    Value::String(BASE64_STANDARD.encode(reader.take(*size)?))
+                }
+            },
+            Type::Array(items) => {
+                let mut values = vec![];
+                reader.blocks(|reader| {
+                    values.push(self.json(items, reader, depth + 1)?);
+                    Ok(())
+                })?;
+                Value::Array(values)
+            }
+            Type::Map(values) => {
+                let mut object = Map::new();
+                reader.blocks(|reader| {
+                    let key = reader.string()?.to_owned();
+                    object.insert(key, self.json(values, reader, depth + 1)?);
+                    Ok(())
+                })?;
+                Value::Object(object)
+            }
+            Type::Union(branches) => self.json(pick(branches, reader)?, reader, depth + 1)?,
+        })
+    }
+}
+
+/// The other branch of unions of null and another type, or of a single type
+fn nullable(branches: &[Type]) -> Option<&Type> {
+    match branches {
+        [ty] | [Type::Null, ty] | [ty, Type::Null] => Some(ty),
+        _ => None,
+    }
+}
+
+/// Reads an index, of an enum symbol or union branch
+fn pick<'a, T>(items: &'a [T], reader: &mut Reader) -> anyhow::Result<&'a T> {
+    let index = reader.long()?;
+    usize::try_from(index)
+        .ok()
+        .and_then(|index| items.get(index))
+        .with_context(|| format!("index {index} out of range"))
+}
+
+/// Converts the two's complement big-endian unscaled value of a decimal
+fn decimal(bytes: &[u8], scale: u32) -> f64 {
+    let unscaled = bytes
+        .iter()
+        .fold(0f64, |unscaled, byte| unscaled * 256.0 + f64::from(*byte));
+    let unscaled = match bytes.first() {
+        Some(byte) if byte & 0x80 != 0 => unscaled - 2f64.powi(8 * bytes.len() as i32),
+        _ => unscaled,
+    };
+
+    unscaled / 10f64.powi(scale as i32)
+}
+
+fn float(value: f64) -> Value {
+    Number::from_f64(value).map_or(Value::Null, Value::Number)
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> anyhow::Result<&'a [u8]> {
+        if self.0.len() < len {
+            bail!("value ends unexpectedly");
+        }
+        let (head, rest) = self.0.split_at(len);
+        self.0 = rest;
+
+        Ok(head)
+    }
+
+    /// Reads a zigzag encoded variable length integer, as ints and longs are
+    fn long(&mut self) -> anyhow::Result<i64> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.take(1)?[0];
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok((value >> 1) as i64 ^ -((value & 1) as i64));
+            }
+        }
+
+        bail!("variable length integer is too long")
+    }
+
+    fn boolean(&mut self) -> anyhow::Result<bool> {
+        match self.take(1)?[0] {
+            0 => Ok(false),
+            1 => Ok(true),
+            byte => bail!("invalid boolean {byte}"),
+        }
+    }
+
+    fn float(&mut self) -> anyhow::Result<f32> {
+        Ok(f32::from_le_bytes(self.take(4)?.try_into()?))
+    }
+
+    fn double(&mut self) -> anyhow::Result<f64> {
+        Ok(f64::from_le_bytes(self.take(8)?.try_into()?))
+    }
+
+    fn bytes(&mut self) -> anyhow::Result<&'a [u8]> {
+        let len = self.long()?;
+        self.take(usize::try_from(len).with_context(|| format!("invalid length {len}"))?)
+    }
+
+    fn string(&mut self) -> anyhow::Result<&'a str> {
+        Ok(std::str::from_utf8(self.bytes()?)?)
+    }
+
+    /// Reads the blocks of items of arrays and maps
+    fn blocks(
+        &mut self,
+        mut item: impl FnMut(&mut Self) -> anyhow::Result<()>,
+    ) -> anyhow::Result<()> {
+        loop {
+            let count = match self.long()? {
+                0 => return Ok(()),
+                // negative counts are followed by the size of the block in bytes
+                count if count < 0 => {
+                    self.long()?;
+                    count.checked_neg().context("invalid block count")?
+                }
+                count => count,
+            };
+            if count > self.0.len() as i64 + MAX_EMPTY_ITEMS {
+                bail!("block of {count} items is larger than the value");
+            }
+            for _ in 0..count {
+                item(self)?;
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+struct Parser {
+    names: HashMap<String, usize>,
+    named: Vec<Named>,
+}
+
+impl Parser {
+    fn parse(&mut self, schema: &Value, namespace: Option<&str>) -> anyhow::Result<Type> {
+        match schema {
+            Value::String(name) => self.reference(name, namespace),
+            Value::Array(branches) => Ok(Type::Union(
+                branches
+                    .iter()
+                    .map(|branch| self.parse(branch, namespace))
+                    .collect::<anyhow::Result<_>>()?,
+            )),
+            Value::Object(object) => self.parse_object(object, namespace),
+            _ => bail!("invalid schema {schema}"),
+        }
+    }
+
+    /// A primitive type or a named type defined before
+    fn reference(&self, name: &str, namespace: Option<&str>) -> anyhow::Result<Type> {
+        Ok(match name {
+            "null" => Type::Null,
+            "boolean" => Type::Boolean,
+            "int" => Type::Int,
+            "long" => Type::Long,
+            "float" => Type::Float,
+            "double" => Type::Double,
+            "bytes" => Type::Bytes,
+            "string" => Type::String,
+            _ => {
+                let index = self
+                    .names
+                    .get(&fullname(name, namespace))
+                    .or_else(|| self.names.get(name))
+                    .with_context(|| format!("unknown type {name}"))?;
+                Type::Named(*index)
+            }
+        })
+    }
+
+    fn parse_object(
+        &mut self,
+        object: &Map<String, Value>,
+        namespace: Option<&str>,
+    ) -> anyhow::Result<Type> {
+        let type_name = match object.get("type") {
+            Some(Value::String(type_name)) => type_name.as_str(),
+            Some(schema) => return self.parse(schema, namespace),
+            None => bail!("schema without type"),
+        };
+        let logical_type = object.get("logicalType").and_then(Value::as_str);
+        let scale = || {
+            object
+                .get("scale")
+                .and_then(Value::as_u64)
+                .and_then(|scale| u32::try_from(scale).ok())
+                .unwrap_or_default()
+        };
+
+        match type_name {
+            "record" | "error" => {
+                let (fullname, namespace) = name(object, namespace)?;
+                // defined before its fields, which may refer to it
+                let index = self.define(fullname, Named::Record(vec![]))?;
+                let fields = object
+                    .get("fields")
+                    .and_then(Value::as_array)
+                    .context("record without fields")?
+                    .iter()
+                    .map(|field| {
+                        let name = field
+                            .get("name")
+                            .and_then(Value::as_str)
+                            .context("field without name")?;
+                        let schema = field
+                            .get("type")
+                            .with_context(|| format!("field {name} without type"))?;
+                        Ok((name.to_owned(), self.parse(schema, namespace.as_deref())?))
+                    })
+                    .collect::<anyhow::Result<_>>()?;
+                self.named[index] = Named::Record(fields);
+
+                Ok(Type::Named(index))
+            }
+            "enum" => {
+                let (fullname, _) = name(object, namespace)?;
+                let symbols = object
+                    .get("symbols")
+                    .and_then(Value::as_array)
+                    .context("enum without symbols")?
+                    .iter()
+                    .map(|symbol| symbol.as_str().map(str::to_owned).context("invalid symbol"))
+                    .collect::<anyhow::Result<_>>()?;
+
+                Ok(Type::Named(self.define(fullname, Named::Enum(symbols))?))
+            }
+            "fixed" => {
+                let (fullname, _) = name(object, namespace)?;
+                let size = object
+                    .get("size")
+                    .and_then(Value::as_u64)
+                    .context("fixed without size")?;
+                let fixed = Named::Fixed {
+                    size: usize::try_from(size)?,
+                    decimal_scale: (logical_type == Some("decimal")).then(scale),
+                };
+
+                Ok(Type::Named(self.define(fullname, fixed)?))
+            }
+            "array" => {
+                let items = object.get("items").context("array without items")?;
+                Ok(Type::Array(Box::new(self.parse(items, namespace)?)))
+            }
+            "map" => {
+                let values = object.get("values").context("map without values")?;
+                Ok(Type::Map(Box::new(self.parse(values, namespace)?)))
+            }
+            // unknown logical types are ignored, as per the specification
+            primitive => Ok(
+                match (self.reference(primitive, namespace)?, logical_type) {
+                    (Type::Int, Some("date")) => Type::Date,
+                    (Type::Int, Some("time-millis")) => Type::TimeMillis,
+                    (Type::Long, Some("time-micros")) => Type::TimeMicros,
+                    (Type::Long, Some("timestamp-millis" | "local-timestamp-millis")) => {
+                        Type::Timestamp(Precision::Millis)
+                    }
+                    (Type::Long, Some("timestamp-micros" | "local-timestamp-micros")) => {
+                        Type::Timestamp(Precision::Micros)
+                    }
+                    (Type::Long, Some("timestamp-nanos" | "local-timestamp-nanos")) => {
+                        Type::Timestamp(Precision::Nanos)
+                    }
+                    (Type::Bytes, Some("decimal")) => Type::Decimal(scale()),
+                    (ty, _) => ty,
+                },
+            ),
+        }
+    }
+
+    fn define(&mut self, fullname: String, named: Named) -> anyhow::Result<usize> {
+        if self.names.contains_key(&fullname) {
+            bail!("type {fullname} is defined more than once");
+        }
+        self.named.push(named);
+        self.names.insert(fullname, self.named.len() - 1);
+
+        Ok(self.named.len() - 1)
+    }
+}
+
+/// The full name of a named type, and the namespace of the types defined within it
+fn name(
+    object: &Map<String, Value>,
+    namespace: Option<&str>,
+) -> anyhow::Result<(String, Option<String>)> {
+    let name = object
+        .get("name")
+        .and_then(Value::as_str)
+        .context("named type without name")?;
+    if let Some((namespace, _)) = name.rsplit_once('.') {
+        return Ok((name.to_owned(), Some(namespace.to_owned())));
+    }
+    let namespace = object
+        .get("namespace")
+        .and_then(Value::as_str)
+        .or(namespace)
+        .filter(|namespace| !namespace.is_empty());
+
+    Ok((fullname(name, namespace), namespace.map(str::to_owned)))
+}
+
+fn fullname(name: &str, namespace: Option<&str>) -> String {
+    match namespace {
+        Some(namespace) if !name.contains('.') => format!("{namespace}.{name}"),
+        _ => name.to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn long(value: i64) -> Vec<u8> {
+        let mut zigzag = ((value << 1) ^ (value >> 63)) as u64;
+        let mut bytes = vec![];
+        loop {
+            let byte = (zigzag & 0x7f) as u8;
+            zigzag >>= 7;
+            if zigzag == 0 {
+                bytes.push(byte);
+                return bytes;
+            }
+            bytes.push(byte | 0x80);
+        }
+    }
+
+    fn string(value: &str) -> Vec<u8> {
+        [long(value.len() as i64), value.as_bytes().to_vec()].concat()
+    }
+
+    #[test]
+    fn decode_flat_record_to_row() {
+        let schema = json!({
+            "type": "record",
+            "name": "Log",
+            "namespace": "com.example",
+            "fields": [
+                {"name": "id", "type": "long"},
+                {"name": "level", "type": {"type": "enum", "name": "Level", "symbols": ["INFO", "ERROR"]}},
+                {"name": "host", "type": {"type": "record", "name": "Host", "fields": [
+                    {"name": "name", "type": ["null", "string"]},
+                    {"name": "ip", "type": ["null", "string"]}
+                ]}},
+                {"name": "ts", "type": {"type": "long", "logicalType": "timestamp-micros"}}
+            ]
+        });
+        let schema = AvroSchema::parse(&schema.to_string(), &[]).unwrap();
+        let payload = [
+            long(42),
+            long(1),
+            long(1),
+            string("web-1"),
+            long(0),
+            long(1_700_000_000_123_456),
+        ]
+        .concat();
+
+        let DecodedValue::Row(layout, row) = schema.decode(&payload).unwrap() else {
+            panic!("flat records are decoded to rows");
+        };
+        assert_eq!(
+            layout
+                .columns
+                .iter()
+                .map(|(name, _)| name.as_str())
+                .collect::<Vec<_>>(),
+            ["id", "level", "host_name", "host_ip", "ts"]
+        );
+        assert_eq!(
+            row,
+            vec![
+                Some(Scalar::Int64(42)),
+                Some(Scalar::Utf8("ERROR".to_owned())),
+                Some(Scalar::Utf8("web-1".to_owned())),
+                None,
+                Some(Scalar::Timestamp(1_700_000_000_123)),
+            ]
+        );
+        assert_eq!(layout.to_batch(&[row]).unwrap().num_rows(), 1);
+        assert!(schema.decode(&payload[..3]).is_err());
+    }
+
+    #[test]
+    fn decode_nested_collections_to_json() {
+        let schema = json!({
+            "type": "record",
+            "name": "Event",
+            "fields": [
+                {"name": "tags", "type": {"type": "array", "items": "string"}},
+                {"name": "attributes", "type": {"type": "map", "values": ["null", "long"]}},
+                {"name": "next", "type": ["null", "Event"]}
+            ]
+        });
+        let schema = AvroSchema::parse(&schema.to_string(), &[]).unwrap();
+        let payload = [
+            long(2),
+            string("a"),
+            string("b"),
+            long(0),
+            // a block of one entry, given with its size
+            long(-1),
+            long(4),
+            string("n"),
+            long(1),
+            long(7),
+            long(0),
+            long(0),
+        ]
+        .concat();
+
+        let DecodedValue::Json(json) = schema.decode(&payload).unwrap() else {
+            panic!("records with collections are decoded to json");
+        };
+        assert_eq!(
+            json,
+            json!({"tags": ["a", "b"], "attributes": {"n": 7}, "next": null})
+        );
+    }
+}
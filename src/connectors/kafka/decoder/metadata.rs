@@ -0,0 +1,242 @@
+/*
+ * Parseable Server (C) 2022 - 2025 Parseable, Inc.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ */
+
+//! Columns of the Kafka metadata of records, to trace events back to their source offsets
+
+use std::sync::Arc;
+
+use serde_json::Value;
+
+use super::{ColumnType, Layout, Scalar, Values};
+use crate::connectors::kafka::{ConsumerRecord, config::ConsumerConfig};
+
+pub const KAFKA_KEY_KEY: &str = "p_kafka_key";
+pub const KAFKA_PARTITION_KEY: &str = "p_kafka_partition";
+pub const KAFKA_OFFSET_KEY: &str = "p_kafka_offset";
+pub const KAFKA_TIMESTAMP_KEY: &str = "p_kafka_timestamp";
+pub const KAFKA_HEADER_KEY_PREFIX: &str = "p_kafka_header_";
+
+#[derive(Debug)]
+enum Source {
+    Key,
+    Partition,
+    Offset,
+    Timestamp,
+    Header(String),
+}
+
+impl Source {
+    fn value(&self, record: &ConsumerRecord) -> Option<Scalar> {
+        match self {
+            Source::Key => record
+                .key
+                .as_deref()
+                .map(|key| Scalar::Utf8(String::from_utf8_lossy(key).into_owned())),
+            Source::Partition => Some(Scalar::Int64(record.partition.into())),
+            Source::Offset => Some(Scalar::Int64(record.offset)),
+            Source::Timestamp => record.timestamp.map(Scalar::Timestamp),
+            Source::Header(name) => record
+                .headers
+                .iter()
+                .find(|(key, _)| key == name)
+                .and_then(|(_, value)| value.clone())
+                .map(Scalar::Utf8),
+        }
+    }
+}
+
+/// The metadata columns added to decoded values
+#[derive(Debug, Default)]
+pub struct RecordMetadata {
+    columns: Vec<(String, ColumnType, Source)>,
+}
+
+impl RecordMetadata {
+    pub fn new(config: &ConsumerConfig) -> Self {
+        let mut columns = vec![];
+        if config.include_metadata {
+            columns.extend(
+                [
+                    (KAFKA_KEY_KEY, ColumnType::Utf8, Source::Key),
+                    (KAFKA_PARTITION_KEY, ColumnType::Int64, Source::Partition),
+                    (KAFKA_OFFSET_KEY, ColumnType::Int64, Source::Offset),
+                    (
+                        KAFKA_TIMESTAMP_KEY,
+                        ColumnType::Timestamp,
+                        Source::Timestamp,
+                    ),
+                ]
+                .map(|(name, column_type, source)| (name.to_owned(), column_type, source)),
+            );
+        }
+        columns.extend(config.header_columns.iter().map(|header| {
+            (
+                header_column(header),
+                ColumnType::Utf8,
+                Source::Header(header.clone()),
+            )
+        }));
+
+        Self { columns }
+    }
+
+    /// Adds the metadata of the records to their values, in the same order
+    pub fn apply(&self, values: Values, records: &[&ConsumerRecord]) -> Values {
+        if self.columns.is_empty() {
+            return values;
+        }
+
+        match values {
+            Values::Rows(layout, rows) => match self.extend(&layout) {
+                Some(extended) => Values::Rows(
+                    Arc::new(extended),
+                    rows.into_iter()
+                        .zip(records)
+                        .map(|(mut row, record)| {
+                            row.extend(
+                                self.columns
+                                    .iter()
+                                    .map(|(_, _, source)| source.value(record)),
+                            );
+                            row
+                        })
+                        .collect(),
+                ),
+                // fields named as metadata columns are overwritten, as in JSON
+                None => self.apply_json(Values::Rows(layout, rows), records),
+            },
+            values => self.apply_json(values, records),
+        }
+    }
+
+    /// The layout along with the metadata columns, if none of them is taken already
+    fn extend(&self, layout: &Layout) -> Option<Layout> {
+        let mut extended = Layout {
+            columns: layout.columns.clone(),
+        };
+        self.columns
+            .iter()
+            .all(|(name, column_type, _)| extended.push(name.clone(), *column_type))
+            .then_some(extended)
+    }
+
+    fn apply_json(&self, values: Values, records: &[&ConsumerRecord]) -> Values {
+        let values = values
+            .into_json()
+            .into_iter()
+            .zip(records)
+            .map(|(mut value, record)| {
+                if let Value::Object(object) = &mut value {
+                    for (name, _, source) in &self.columns {
+                        match source.value(record) {
+                            Some(scalar) => object.insert(name.clone(), scalar.into_json()),
+                            None => object.remove(name),
+                        };
+                    }
+                }
+                value
+            })
+            .collect();
+
+        Values::Json(values)
+    }
+}
+
+/// Name of the column of a header, of which characters other than ASCII letters,
+/// digits and `_` are replaced
+fn header_column(header: &str) -> String {
+    let name: String = header
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+
+    format!("{KAFKA_HEADER_KEY_PREFIX}{name}")
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn record() -> ConsumerRecord {
+        ConsumerRecord {
+            payload: Some(b"{}".to_vec()),
+            key: Some(b"user-1".to_vec()),
+            topic: "logs".to_owned(),
+            partition: 3,
+            offset: 42,
+            timestamp: None,
+            headers: vec![("trace-id".to_owned(), Some("abc".to_owned()))],
+        }
+    }
+
+    fn metadata() -> RecordMetadata {
+        RecordMetadata::new(&ConsumerConfig {
+            include_metadata: true,
+            header_columns: vec!["trace-id".to_owned(), "tenant".to_owned()],
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn metadata_extends_rows() {
+        let mut layout = Layout::default();
+        layout.push("message".to_owned(), ColumnType::Utf8);
+        let row = vec![Some(Scalar::Utf8("hello".to_owned()))];
+        let record = record();
+
+        let Values::Rows(layout, rows) =
+            metadata().apply(Values::Rows(Arc::new(layout), vec![row]), &[&record])
+        else {
+            panic!("rows stay rows when no column is taken");
+        };
+        assert_eq!(layout.position("p_kafka_header_trace_id"), Some(5));
+        assert_eq!(
+            rows[0],
+            vec![
+                Some(Scalar::Utf8("hello".to_owned())),
+                Some(Scalar::Utf8("user-1".to_owned())),
+                Some(Scalar::Int64(3)),
+                Some(Scalar::Int64(42)),
+                None,
+                Some(Scalar::Utf8("abc".to_owned())),
+                None,
+            ]
+        );
+    }
+
+    #[test]
+    fn metadata_overwrites_json_fields() {
+        let record = record();
+        let values = Values::Json(vec![json!({"p_kafka_offset": "x", "p_kafka_timestamp": 1})]);
+
+        let Values::Json(values) = metadata().apply(values, &[&record]) else {
+            panic!("json stays json");
+        };
+        assert_eq!(
+            values[0],
+            json!({
+                "p_kafka_key": "user-1",
+                "p_kafka_partition": 3,
+                "p_kafka_offset": 42,
+                "p_kafka_header_trace_id": "abc"
+            })
+        );
+    }
+}
@@ -0,0 +1,454 @@
+/*
+ * Parseable Server (C) 2022 - 2025 Parseable, Inc.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ */
+
+//! Decoding of record values. Values of a known schema made of scalars and nested records
+//! are decoded straight to Arrow, nested records flattened with `_` as JSON is on ingestion,
+//! the others are decoded to JSON.
+
+pub mod avro;
+pub mod metadata;
+pub mod protobuf;
+
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use arrow_array::{
+    ArrayRef, BooleanArray, Date32Array, Float64Array, Int64Array, RecordBatch, RecordBatchOptions,
+    StringArray, TimestampMillisecondArray,
+};
+use arrow_schema::{ArrowError, DataType, Field, Schema, TimeUnit};
+use chrono::{DateTime, SecondsFormat};
+use once_cell::sync::Lazy;
+use serde_json::Value;
+
+use super::{
+    ConsumerRecord,
+    config::{ConsumerConfig, ValueDecoder},
+    schema_registry::{RegisteredSchema, RegistryError, SchemaRegistry},
+};
+
+/// The first byte of values in the Confluent wire format
+const MAGIC_BYTE: u8 = 0;
+
+/// Column of the values decoded with the raw decoder
+pub const RAW_MESSAGE_KEY: &str = "message";
+
+/// Key of values that aren't records once decoded to JSON
+pub const VALUE_KEY: &str = "value";
+
+static RAW_LAYOUT: Lazy<Arc<Layout>> = Lazy::new(|| {
+    let mut layout = Layout::default();
+    layout.push(RAW_MESSAGE_KEY.to_owned(), ColumnType::Utf8);
+    Arc::new(layout)
+});
+
+/// Arrow type of a column of a [`Layout`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnType {
+    Boolean,
+    Int64,
+    Float64,
+    Utf8,
+    Date32,
+    /// Milliseconds since the epoch, as timestamps are stored
+    Timestamp,
+}
+
+/// A value of a [`Row`], of the type of its column
+#[derive(Debug, Clone, PartialEq)]
+pub enum Scalar {
+    Boolean(bool),
+    Int64(i64),
+    Float64(f64),
+    Utf8(String),
+    Date32(i32),
+    Timestamp(i64),
+}
+
+/// The values of a record, in the order of the columns of its layout
+pub type Row = Vec<Option<Scalar>>;
+
+impl ColumnType {
+    fn data_type(&self) -> DataType {
+        match self {
+            ColumnType::Boolean => DataType::Boolean,
+            ColumnType::Int64 => DataType::Int64,
+            ColumnType::Float64 => DataType::Float64,
+            ColumnType::Utf8 => DataType::Utf8,
+            ColumnType::Date32 => DataType::Date32,
+            ColumnType::Timestamp => DataType::Timestamp(TimeUnit::Millisecond, None),
+        }
+    }
+
+    fn array<'a>(&self, values: impl Iterator<Item = Option<&'a Scalar>>) -> ArrayRef {
+        match self {
+            ColumnType::Boolean => Arc::new(
+                values
+                    .map(|value| match value {
+                        Some(Scalar::Boolean(value)) => Some(*value),
+                        _ => None,
+                    })
+                    .collect::<BooleanArray>(),
+            ),
+            ColumnType::Int64 => Arc::new(
+                values
+                    .map(|value| match value {
+                        Some(Scalar::Int64(value)) => Some(*value),
+                        _ => None,
+                    })
+                    .collect::<Int64Array>(),
+            ),
+            ColumnType::Float64 => Arc::new(
+                values
+                    .map(|value| match value {
+                        Some(Scalar::Float64(value)) => Some(*value),
+                        _ => None,
+                    })
+                    .collect::<Float64Array>(),
+            ),
+            ColumnType::Utf8 => Arc::new(
+                values
+                    .map(|value| match value {
+                        Some(Scalar::Utf8(value)) => Some(value.as_str()),
+                        _ => None,
+                    })
+                    .collect::<StringArray>(),
+            ),
+            ColumnType::Date32 => Arc::new(
+                values
+                    .map(|value| match value {
+                        Some(Scalar::Date32(value)) => Some(*value),
+                        _ => None,
+                    })
+                    .collect::<Date32Array>(),
+            ),
+            ColumnType::Timestamp => Arc::new(
+                values
+                    .map(|value| match value {
+                        Some(Scalar::Timestamp(value)) => Some(*value),
+                        _ => None,
+                    })
+                    .collect::<TimestampMillisecondArray>(),
+            ),
+        }
+    }
+}
+
+/// The columns the values of a schema are decoded into
+#[derive(Debug, Default)]
+pub struct Layout {
+    columns: Vec<(String, ColumnType)>,
+}
+
+impl Layout {
+    /// Adds a column, returns `false` if there is one of the same name already
+    fn push(&mut self, name: String, column_type: ColumnType) -> bool {
+        if self.columns.iter().any(|(column, _)| column == &name) {
+            return false;
+        }
+        self.columns.push((name, column_type));
+
+        true
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.columns.iter().position(|(column, _)| column == name)
+    }
+
+    pub fn len(&self) -> usize {
+        self.columns.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.columns.is_empty()
+    }
+
+    pub fn to_batch(&self, rows: &[Row]) -> Result<RecordBatch, ArrowError> {
+        let schema = Schema::new(
+            self.columns
+                .iter()
+                .map(|(name, column_type)| Field::new(name, column_type.data_type(), true))
+                .collect::<Vec<_>>(),
+        );
+        let columns = self
+            .columns
+            .iter()
+            .enumerate()
+            .map(|(i, (_, column_type))| {
+                column_type.array(rows.iter().map(|row| row.get(i).and_then(Option::as_ref)))
+            })
+            .collect();
+
+        RecordBatch::try_new_with_options(
+            Arc::new(schema),
+            columns,
+            &RecordBatchOptions::new().with_row_count(Some(rows.len())),
+        )
+    }
+}
+
+/// A decoded value
+#[derive(Debug)]
+pub enum DecodedValue {
+    Json(Value),
+    Row(Arc<Layout>, Row),
+}
+
+impl DecodedValue {
+    /// The string value of a top level field, or of a column
+    pub fn get_str(&self, name: &str) -> Option<&str> {
+        match self {
+            DecodedValue::Json(value) => value.get(name)?.as_str(),
+            DecodedValue::Row(layout, row) => match row.get(layout.position(name)?)? {
+                Some(Scalar::Utf8(value)) => Some(value),
+                _ => None,
+            },
+        }
+    }
+}
+
+/// Decoded values of the same layout, or JSON values
+#[derive(Debug)]
+pub enum Values {
+    Json(Vec<Value>),
+    Rows(Arc<Layout>, Vec<Row>),
+}
+
+impl Values {
+    /// Converts rows to JSON, for streams which partitions are derived from JSON values
+    pub fn into_json(self) -> Vec<Value> {
+        match self {
+            Values::Json(values) => values,
+            Values::Rows(layout, rows) => rows
+                .into_iter()
+                .map(|row| {
+                    let object = layout
+                        .columns
+                        .iter()
+                        .zip(row)
+                        .filter_map(|((name, _), value)| Some((name.clone(), value?.into_json())))
+                        .collect();
+                    Value::Object(object)
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<DecodedValue> for Values {
+    fn from(value: DecodedValue) -> Self {
+        match value {
+            DecodedValue::Json(value) => Values::Json(vec![value]),
+            DecodedValue::Row(layout, row) => Values::Rows(layout, vec![row]),
+        }
+    }
+}
+
+impl Scalar {
+    fn into_json(self) -> Value {
+        match self {
+            Scalar::Boolean(value) => value.into(),
+            Scalar::Int64(value) => value.into(),
+            Scalar::Float64(value) => value.into(),
+            Scalar::Utf8(value) => value.into(),
+            Scalar::Date32(days) => DateTime::from_timestamp(i64::from(days) * 86_400, 0)
+                .map_or(Value::Null, |date| date.date_naive().to_string().into()),
+            Scalar::Timestamp(millis) => {
+                DateTime::from_timestamp_millis(millis).map_or(Value::Null, |timestamp| {
+                    timestamp
+                        .to_rfc3339_opts(SecondsFormat::AutoSi, true)
+                        .into()
+                })
+            }
+        }
+    }
+}
+
+/// Records decoded alike and routed to the same stream, to be ingested as a single event
+#[derive(Debug)]
+pub struct Chunk<'a> {
+    pub stream_name: String,
+    pub records: Vec<&'a ConsumerRecord>,
+    pub payload_size: u64,
+    pub values: Values,
+}
+
+impl<'a> Chunk<'a> {
+    /// Groups decoded records by stream and by the way they were decoded
+    pub fn group(
+        decoded: impl IntoIterator<Item = (String, &'a ConsumerRecord, DecodedValue)>,
+    ) -> Vec<Self> {
+        let mut chunks: Vec<Self> = vec![];
+        for (stream_name, record, value) in decoded {
+            let position = chunks.iter().position(|chunk| {
+                chunk.stream_name == stream_name
+                    && match (&chunk.values, &value) {
+                        (Values::Json(_), DecodedValue::Json(_)) => true,
+                        (Values::Rows(layout, _), DecodedValue::Row(other, _)) => {
+                            Arc::ptr_eq(layout, other)
+                        }
+                        _ => false,
+                    }
+            });
+            let index = position.unwrap_or_else(|| {
+                let values = match &value {
+                    DecodedValue::Json(_) => Values::Json(vec![]),
+                    DecodedValue::Row(layout, _) => Values::Rows(layout.clone(), vec![]),
+                };
+                chunks.push(Chunk {
+                    stream_name,
+                    records: vec![],
+                    payload_size: 0,
+                    values,
+                });
+                chunks.len() - 1
+            });
+
+            let chunk = &mut chunks[index];
+            chunk.records.push(record);
+            chunk.payload_size += record.payload.as_ref().map_or(0, Vec::len) as u64;
+            match (&mut chunk.values, value) {
+                (Values::Json(values), DecodedValue::Json(value)) => values.push(value),
+                (Values::Rows(_, rows), DecodedValue::Row(_, row)) => rows.push(row),
+                _ => unreachable!("chunks hold values decoded alike"),
+            }
+        }
+
+        chunks
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct DecodedBatch<'a> {
+    pub decoded: Vec<(&'a ConsumerRecord, DecodedValue)>,
+    /// Records that can't be decoded, with the reason why
+    pub failed: Vec<(&'a ConsumerRecord, anyhow::Error)>,
+}
+
+enum DecodeError {
+    /// The value can't be decoded
+    Invalid(anyhow::Error),
+    /// The schema of the value can't be fetched for now
+    Registry(RegistryError),
+}
+
+impl From<anyhow::Error> for DecodeError {
+    fn from(error: anyhow::Error) -> Self {
+        DecodeError::Invalid(error)
+    }
+}
+
+#[derive(Debug)]
+pub struct Decoder {
+    kind: ValueDecoder,
+    registry: Option<SchemaRegistry>,
+}
+
+impl Default for Decoder {
+    fn default() -> Self {
+        Self {
+            kind: ValueDecoder::Json,
+            registry: None,
+        }
+    }
+}
+
+impl Decoder {
+    pub fn new(config: &ConsumerConfig) -> Self {
+        let registry = config.schema_registry_url.clone().map(|url| {
+            SchemaRegistry::new(
+                url,
+                config.schema_registry_username.clone(),
+                config.schema_registry_password.clone(),
+            )
+        });
+
+        Self {
+            kind: config.value_decoder,
+            registry,
+        }
+    }
+
+    /// Decodes the values of the records. Records without value are tombstones, which are skipped. Fails only if the
+    /// schema registry can't be reached, such that the records may be retried.
+    pub async fn decode<'a>(
+        &self,
+        records: impl IntoIterator<Item = &'a ConsumerRecord>,
+    ) -> Result<DecodedBatch<'a>, RegistryError> {
+        let mut batch = DecodedBatch::default();
+        for (record, value) in records
+            .into_iter()
+            .filter_map(|record| record.payload.as_deref().map(|value| (record, value)))
+        {
+            match self.decode_value(value).await {
+                Ok(decoded) => batch.decoded.push((record, decoded)),
+                Err(DecodeError::Invalid(e)) => batch.failed.push((record, e)),
+                Err(DecodeError::Registry(e)) => return Err(e),
+            }
+        }
+
+        Ok(batch)
+    }
+
+    async fn decode_value(&self, value: &[u8]) -> Result<DecodedValue, DecodeError> {
+        match self.kind {
+            ValueDecoder::Json => {
+                match serde_json::from_slice(value).map_err(anyhow::Error::from)? {
+                    json @ Value::Object(_) => Ok(DecodedValue::Json(json)),
+                    _ => Err(anyhow!("value is not a JSON object").into()),
+                }
+            }
+            ValueDecoder::Raw => {
+                let message = std::str::from_utf8(value).map_err(anyhow::Error::from)?;
+                Ok(DecodedValue::Row(
+                    RAW_LAYOUT.clone(),
+                    vec![Some(Scalar::Utf8(message.to_owned()))],
+                ))
+            }
+            ValueDecoder::Avro | ValueDecoder::Protobuf => {
+                let (id, payload) = split_schema_id(value)?;
+                let registry = self
+                    .registry
+                    .as_ref()
+                    .ok_or_else(|| anyhow!("schema registry is not configured"))?;
+                let schema = registry.get(id).await.map_err(|e| match e {
+                    RegistryError::Request(_) => DecodeError::Registry(e),
+                    e => DecodeError::Invalid(e.into()),
+                })?;
+
+                match (self.kind, schema.as_ref()) {
+                    (ValueDecoder::Avro, RegisteredSchema::Avro(schema)) => {
+                        Ok(schema.decode(payload)?)
+                    }
+                    (ValueDecoder::Protobuf, RegisteredSchema::Protobuf(schema)) => {
+                        Ok(schema.decode(payload)?)
+                    }
+                    _ => Err(anyhow!("schema {id} is not a {} schema", self.kind).into()),
+                }
+            }
+        }
+    }
+}
+
+/// Splits a value in the Confluent wire format into the id of its schema and the rest
+fn split_schema_id(value: &[u8]) -> anyhow::Result<(u32, &[u8])> {
+    match value {
+        [MAGIC_BYTE, a, b, c, d, rest @ ..] => Ok((u32::from_be_bytes([*a, *b, *c, *d]), rest)),
+        _ => Err(anyhow!("value is not in the Confluent wire format")),
+    }
+}
@@ -0,0 +1,488 @@
+/*
+ * Parseable Server (C) 2022 - 2025 Parseable, Inc.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ */
+
+//! Decoding of Protobuf encoded values with the schema they were produced with
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anyhow::{Context, bail};
+use base64::{Engine, prelude::BASE64_STANDARD};
+use chrono::{DateTime, SecondsFormat};
+use protobuf::MessageDyn;
+use protobuf::reflect::{
+    FieldDescriptor, FileDescriptor, MessageDescriptor, ReflectValueRef, RuntimeFieldType,
+    RuntimeType, Syntax,
+};
+use serde_json::{Map, Number, Value};
+
+use super::{ColumnType, DecodedValue, Layout, Row, Scalar};
+
+/// Name the schema is parsed under, which references can't take as they are `.proto` files
+const ROOT_FILE: &str = "schema.proto.root";
+
+const TIMESTAMP: &str = "google.protobuf.Timestamp";
+
+/// A Protobuf schema, of which values may be of any message
+#[derive(Debug)]
+pub struct ProtoSchema {
+    file: FileDescriptor,
+    /// The columns of the messages made of scalars and nested messages, by full name
+    layouts: HashMap<String, Arc<Layout>>,
+}
+
+impl ProtoSchema {
+    /// Parses a schema given the files it imports, by their name
+    pub fn parse(schema: &str, references: &[(String, String)]) -> anyhow::Result<Self> {
+        let dir = tempfile::tempdir()?;
+        for (name, content) in references {
+            let path = dir.path().join(name);
+            if !path.starts_with(dir.path()) || name.contains("..") {
+                bail!("invalid reference {name}");
+            }
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::write(path, content)?;
+        }
+        let root = dir.path().join(ROOT_FILE);
+        std::fs::write(&root, schema)?;
+
+        let parsed = protobuf_parse::Parser::new()
+            .pure()
+            .include(dir.path())
+            .input(&root)
+            .parse_and_typecheck()?;
+        let file = FileDescriptor::new_dynamic_fds(parsed.file_descriptors, &[])?
+            .into_iter()
+            .find(|file| file.proto().name() == ROOT_FILE)
+            .context("schema is missing once parsed")?;
+
+        let mut layouts = HashMap::new();
+        let mut messages: Vec<_> = file.messages().collect();
+        while let Some(message) = messages.pop() {
+            if let Some(layout) = layout(&message) {
+                layouts.insert(message.full_name().to_owned(), Arc::new(layout));
+            }
+            messages.extend(message.nested_messages());
+        }
+
+        Ok(Self { file, layouts })
+    }
+
+    pub fn decode(&self, payload: &[u8]) -> anyhow::Result<DecodedValue> {
+        let mut payload = payload;
+        let descriptor = self.message(&message_indexes(&mut payload)?)?;
+        let message = descriptor.parse_from_bytes(payload)?;
+
+        Ok(match self.layouts.get(descriptor.full_name()) {
+            Some(layout) => {
+                let mut row = Vec::with_capacity(layout.len());
+                push_row(&*message, &descriptor, &mut row)?;
+                DecodedValue::Row(layout.clone(), row)
+            }
+            None => DecodedValue::Json(to_json(&*message, &descriptor)?),
+        })
+    }
+
+    /// The message at the given indexes, of the top level message then of nested ones
+    fn message(&self, indexes: &[usize]) -> anyhow::Result<MessageDescriptor> {
+        let not_found = || format!("no message at indexes {indexes:?}");
+        let (first, nested) = indexes.split_first().with_context(not_found)?;
+        let mut message = self.file.messages().nth(*first).with_context(not_found)?;
+        for index in nested {
+            let nested = message.nested_messages().nth(*index);
+            message = nested.with_context(not_found)?;
+        }
+
+        Ok(message)
+    }
+}
+
+/// Reads the indexes of the message of a value, which a single `0` stands for the first
+/// top level message of
+fn message_indexes(payload: &mut &[u8]) -> anyhow::Result<Vec<usize>> {
+    let count = varint(payload)?;
+    if count == 0 {
+        return Ok(vec![0]);
+    }
+    if count < 0 || count as usize > payload.len() {
+        bail!("invalid count of message indexes {count}");
+    }
+
+    (0..count)
+        .map(|_| {
+            let index = varint(payload)?;
+            usize::try_from(index).with_context(|| format!("invalid message index {index}"))
+        })
+        .collect()
+}
+
+/// Reads a zigzag encoded variable length integer
+fn varint(payload: &mut &[u8]) -> anyhow::Result<i64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let (byte, rest) = payload.split_first().context("value ends unexpectedly")?;
+        *payload = rest;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok((value >> 1) as i64 ^ -((value & 1) as i64));
+        }
+    }
+
+    bail!("variable length integer is too long")
+}
+
+fn layout(message: &MessageDescriptor) -> Option<Layout> {
+    let mut layout = Layout::default();
+    let mut path = vec![message.full_name().to_owned()];
+    columns(message, None, &mut layout, &mut path)?;
+
+    Some(layout)
+}
+
+fn columns(
+    message: &MessageDescriptor,
+    prefix: Option<&str>,
+    layout: &mut Layout,
+    path: &mut Vec<String>,
+) -> Option<()> {
+    for field in message.fields() {
+        let name = match prefix {
+            Some(prefix) => format!("{prefix}_{}", field.name()),
+            None => field.name().to_owned(),
+        };
+        // repeated and map fields can't be flattened
+        let RuntimeFieldType::Singular(runtime_type) = field.runtime_field_type() else {
+            return None;
+        };
+        let column_type = match runtime_type {
+            RuntimeType::I32 | RuntimeType::I64 | RuntimeType::U32 => ColumnType::Int64,
+            RuntimeType::U64 | RuntimeType::F32 | RuntimeType::F64 => ColumnType::Float64,
+            RuntimeType::Bool => ColumnType::Boolean,
+            RuntimeType::String | RuntimeType::VecU8 | RuntimeType::Enum(_) => ColumnType::Utf8,
+            RuntimeType::Message(nested) if nested.full_name() == TIMESTAMP => {
+                ColumnType::Timestamp
+            }
+            RuntimeType::Message(nested) => {
+                // neither can recursive messages
+                if path.iter().any(|name| name == nested.full_name()) {
+                    return None;
+                }
+                path.push(nested.full_name().to_owned());
+                columns(&nested, Some(&name), layout, path)?;
+                path.pop();
+                continue;
+            }
+        };
+        if !layout.push(name, column_type) {
+            return None;
+        }
+    }
+
+    Some(())
+}
+
+/// Number of columns of a message with a layout
+fn width(message: &MessageDescriptor) -> usize {
+    message
+        .fields()
+        .map(|field| match field.runtime_field_type() {
+            RuntimeFieldType::Singular(RuntimeType::Message(nested))
+                if nested.full_name() != TIMESTAMP =>
+            {
+                width(&nested)
+            }
+            _ => 1,
+        })
+        .sum()
+}
+
+/// The value of a singular field. Scalars without presence, which aren't
+/// encoded when of their default value, are given it.
+fn singular<'a>(
+    message: &'a dyn MessageDyn,
+    field: &FieldDescriptor,
+) -> Option<ReflectValueRef<'a>> {
+    let implicit = field.containing_oneof_including_synthetic().is_none()
+        && field.containing_message().file_descriptor().syntax() == Syntax::Proto3
+        && !matches!(
+            field.runtime_field_type(),
+            RuntimeFieldType::Singular(RuntimeType::Message(_))
+        );
+    if implicit {
+        Some(field.get_singular_field_or_default(message))
+    } else {
+        field.get_singular(message)
+    }
+}
+
+fn push_row(
+    message: &dyn MessageDyn,
+    descriptor: &MessageDescriptor,
+    row: &mut Row,
+) -> anyhow::Result<()> {
+    for field in descriptor.fields() {
+        let value = singular(message, &field);
+        match field.runtime_field_type() {
+            RuntimeFieldType::Singular(RuntimeType::Message(nested))
+                if nested.full_name() != TIMESTAMP =>
+            {
+                match value.as_ref().and_then(ReflectValueRef::to_message) {
+                    Some(value) => push_row(&*value, &nested, row)?,
+                    None => row.extend(std::iter::repeat_n(None, width(&nested))),
+                }
+            }
+            _ => row.push(value.map(to_scalar).transpose()?),
+        }
+    }
+
+    Ok(())
+}
+
+fn to_scalar(value: ReflectValueRef) -> anyhow::Result<Scalar> {
+    Ok(match value {
+        ReflectValueRef::U32(value) => Scalar::Int64(value.into()),
+        ReflectValueRef::U64(value) => Scalar::Float64(value as f64),
+        ReflectValueRef::I32(value) => Scalar::Int64(value.into()),
+        ReflectValueRef::I64(value) => Scalar::Int64(value),
+        ReflectValueRef::F32(value) => Scalar::Float64(value.into()),
+        ReflectValueRef::F64(value) => Scalar::Float64(value),
+        ReflectValueRef::Bool(value) => Scalar::Boolean(value),
+        ReflectValueRef::String(value) => Scalar::Utf8(value.to_owned()),
+        ReflectValueRef::Bytes(value) => Scalar::Utf8(BASE64_STANDARD.encode(value)),
+        ReflectValueRef::Enum(descriptor, number) => Scalar::Utf8(enum_name(&descriptor, number)),
+        ReflectValueRef::Message(message) => {
+            Scalar::Timestamp(timestamp(&*message)?.timestamp_millis())
+        }
+    })
+}
+
+fn enum_name(descriptor: &protobuf::reflect::EnumDescriptor, number: i32) -> String {
+    descriptor
+        .value_by_number(number)
+        .map_or_else(|| number.to_string(), |value| value.name().to_owned())
+}
+
+/// Reads a `google.protobuf.Timestamp`
+fn timestamp(message: &dyn MessageDyn) -> anyhow::Result<DateTime<chrono::Utc>> {
+    let descriptor = message.descriptor_dyn();
+    let field = |name: &str| {
+        descriptor
+            .field_by_name(name)
+            .map(|field| field.get_singular_field_or_default(message))
+    };
+    let seconds = field("seconds").and_then(|value| value.to_i64());
+    let nanos = field("nanos").and_then(|value| value.to_i32());
+    match (seconds, nanos) {
+        (Some(seconds), Some(nanos)) => u32::try_from(nanos)
+            .ok()
+            .and_then(|nanos| DateTime::from_timestamp(seconds, nanos))
+            .context("timestamp out of range"),
+        _ => bail!("invalid timestamp"),
+    }
+}
+
+fn to_json(message: &dyn MessageDyn, descriptor: &MessageDescriptor) -> anyhow::Result<Value> {
+    let mut object = Map::new();
+    for field in descriptor.fields() {
+        let value = match field.runtime_field_type() {
+            RuntimeFieldType::Singular(_) => match singular(message, &field) {
+                Some(value) => value_to_json(value)?,
+                None => continue,
+            },
+            RuntimeFieldType::Repeated(_) => {
+                let values = field.get_repeated(message);
+                if values.is_empty() {
+                    continue;
+                }
+                Value::Array(
+                    values
+                        .into_iter()
+                        .map(value_to_json)
+                        .collect::<anyhow::Result<_>>()?,
+                )
+            }
+            RuntimeFieldType::Map(..) => {
+                let entries = field.get_map(message);
+                if entries.is_empty() {
+                    continue;
+                }
+                let mut map = Map::new();
+                for (key, value) in &entries {
+                    let key = match key {
+                        ReflectValueRef::String(key) => key.to_owned(),
+                        key => key.to_string(),
+                    };
+                    map.insert(key, value_to_json(value)?);
+                }
+                Value::Object(map)
+            }
+        };
+        object.insert(field.name().to_owned(), value);
+    }
+
+    Ok(Value::Object(object))
+}
+
+fn value_to_json(value: ReflectValueRef) -> anyhow::Result<Value> {
+    Ok(match value {
+        ReflectValueRef::U32(value) => value.into(),
+        ReflectValueRef::U64(value) => value.into(),
+        ReflectValueRef::I32(value) => value.into(),
+        ReflectValueRef::I64(value) => value.into(),
+        ReflectValueRef::F32(value) => float(value.into()),
+        ReflectValueRef::F64(value) => float(value),
+        ReflectValueRef::Bool(value) => value.into(),
+        ReflectValueRef::String(value) => value.into(),
+        ReflectValueRef::Bytes(value) => BASE64_STANDARD.encode(value).into(),
+        ReflectValueRef::Enum(descriptor, number) => enum_name(&descriptor, number).into(),
+        ReflectValueRef::Message(message) => {
+            let descriptor = message.descriptor_dyn();
+            if descriptor.full_name() == TIMESTAMP {
+                timestamp(&*message)?
+                    .to_rfc3339_opts(SecondsFormat::AutoSi, true)
+                    .into()
+            } else {
+                to_json(&*message, &descriptor)?
+            }
+        }
+    })
+}
+
+fn float(value: f64) -> Value {
+    Number::from_f64(value).map_or(Value::Null, Value::Number)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    const SCHEMA: &str = r#"
+        syntax = "proto3";
+        package example;
+
+        import "google/protobuf/timestamp.proto";
+        import "common/host.proto";
+
+        message Log {
+            int64 id = 1;
+            string message = 2;
+            Level level = 3;
+            common.Host host = 4;
+            google.protobuf.Timestamp time = 5;
+            optional string trace_id = 6;
+
+            enum Level {
+                INFO = 0;
+                ERROR = 1;
+            }
+        }
+
+        message Batch {
+            repeated Log logs = 1;
+            map<string, string> labels = 2;
+        }
+    "#;
+
+    const HOST: &str = r#"
+        syntax = "proto3";
+        package common;
+
+        message Host {
+            string name = 1;
+            uint32 port = 2;
+        }
+    "#;
+
+    fn schema() -> ProtoSchema {
+        ProtoSchema::parse(SCHEMA, &[("common/host.proto".to_owned(), HOST.to_owned())]).unwrap()
+    }
+
+    #[test]
+    fn decode_flat_message_to_row() {
+        // a single 0 for the first message, then id 7, level ERROR, host {name "web"}
+        // and time {seconds 1700000000, nanos 5000000}
+        let payload = [
+            &[0x00, 0x08, 0x07, 0x18, 0x01, 0x22, 0x05, 0x0a, 0x03][..],
+            b"web",
+            &[
+                0x2a, 0x0b, 0x08, 0x80, 0xe2, 0xcf, 0xaa, 0x06, 0x10, 0xc0, 0x96, 0xb1, 0x02,
+            ],
+        ]
+        .concat();
+
+        let DecodedValue::Row(layout, row) = schema().decode(&payload).unwrap() else {
+            panic!("flat messages are decoded to rows");
+        };
+        assert_eq!(
+            layout
+                .columns
+                .iter()
+                .map(|(name, _)| name.as_str())
+                .collect::<Vec<_>>(),
+            [
+                "id",
+                "message",
+                "level",
+                "host_name",
+                "host_port",
+                "time",
+                "trace_id"
+            ]
+        );
+        assert_eq!(
+            row,
+            vec![
+                Some(Scalar::Int64(7)),
+                Some(Scalar::Utf8(String::new())),
+                Some(Scalar::Utf8("ERROR".to_owned())),
+                Some(Scalar::Utf8("web".to_owned())),
+                Some(Scalar::Int64(0)),
+                Some(Scalar::Timestamp(1_700_000_000_005)),
+                None,
+            ]
+        );
+    }
+
+    #[test]
+    fn decode_message_with_collections_to_json() {
+        // indexes [1] for the second message, then a log of id 1 and a label
+        let payload = [
+            &[0x02, 0x02, 0x0a, 0x02, 0x08, 0x01, 0x12, 0x06, 0x0a, 0x01][..],
+            b"k",
+            &[0x12, 0x01],
+            b"v",
+        ]
+        .concat();
+
+        let DecodedValue::Json(json) = schema().decode(&payload).unwrap() else {
+            panic!("messages with collections are decoded to json");
+        };
+        assert_eq!(
+            json,
+            json!({
+                "logs": [{"id": 1, "message": "", "level": "INFO"}],
+                "labels": {"k": "v"}
+            })
+        );
+        assert!(schema().decode(&[0x02, 0x04]).is_err());
+    }
+}
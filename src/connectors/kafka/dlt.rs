@@ -0,0 +1,150 @@
+/*
+ * Parseable Server (C) 2022 - 2025 Parseable, Inc.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ */
+
+use std::fmt;
+use std::time::Duration;
+
+use rdkafka::message::{Header, OwnedHeaders};
+use rdkafka::producer::{FutureProducer, FutureRecord};
+use tracing::{debug, info};
+
+use super::{ConsumerRecord, config::KafkaConfig, metrics::DltMetrics};
+
+pub const ERROR_HEADER: &str = "parseable.error.message";
+pub const REASON_HEADER: &str = "parseable.error.reason";
+pub const SOURCE_TOPIC_HEADER: &str = "parseable.source.topic";
+pub const SOURCE_PARTITION_HEADER: &str = "parseable.source.partition";
+pub const SOURCE_OFFSET_HEADER: &str = "parseable.source.offset";
+
+/// How long a record may wait for room in the producer queue
+const QUEUE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Why a record was sent to the dead letter topic
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeadLetterReason {
+    /// The payload couldn't be decoded with the configured [`ValueDecoder`], e.g. invalid JSON,
+    /// Avro or Protobuf not matching its schema, or a raw value that isn't UTF-8
+    ///
+    /// [`ValueDecoder`]: super::config::ValueDecoder
+    Undecodable,
+    /// The payload was decoded but couldn't be turned into an event of the stream
+    Rejected,
+}
+
+impl DeadLetterReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeadLetterReason::Undecodable => "undecodable",
+            DeadLetterReason::Rejected => "rejected",
+        }
+    }
+}
+
+impl fmt::Display for DeadLetterReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Producer of the records that can't be ingested to the dead letter topic, keeping their
+/// key and payload as is, with headers describing the error and where the record came from.
+pub struct DeadLetterTopic {
+    topic: String,
+    producer: FutureProducer,
+    metrics: DltMetrics,
+}
+
+impl fmt::Debug for DeadLetterTopic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DeadLetterTopic")
+            .field("topic", &self.topic)
+            .finish_non_exhaustive()
+    }
+}
+
+impl DeadLetterTopic {
+    pub fn new(config: &KafkaConfig, topic: &str, metrics: DltMetrics) -> anyhow::Result<Self> {
+        let producer = config.to_rdkafka_producer_config().create()?;
+        info!("Producing bad records to dead letter topic {topic}");
+
+        Ok(Self {
+            topic: topic.to_owned(),
+            producer,
+            metrics,
+        })
+    }
+
+    pub async fn send(
+        &self,
+        record: &ConsumerRecord,
+        reason: DeadLetterReason,
+        error: &str,
+    ) -> anyhow::Result<()> {
+        let partition = record.partition.to_string();
+        let offset = record.offset.to_string();
+        let headers = OwnedHeaders::new()
+            .insert(Header {
+                key: ERROR_HEADER,
+                value: Some(error),
+            })
+            .insert(Header {
+                key: REASON_HEADER,
+                value: Some(reason.as_str()),
+            })
+            .insert(Header {
+                key: SOURCE_TOPIC_HEADER,
+                value: Some(record.topic.as_str()),
+            })
+            .insert(Header {
+                key: SOURCE_PARTITION_HEADER,
+                value: Some(partition.as_str()),
+            })
+            .insert(Header {
+                key: SOURCE_OFFSET_HEADER,
+                value: Some(offset.as_str()),
+            });
+
+        let mut dead_letter = FutureRecord::<[u8], [u8]>::to(&self.topic).headers(headers);
+        if let Some(key) = &record.key {
+            dead_letter = dead_letter.key(key.as_slice());
+        }
+        if let Some(payload) = &record.payload {
+            dead_letter = dead_letter.payload(payload.as_slice());
+        }
+
+        if let Err((e, _)) = self.producer.send(dead_letter, QUEUE_TIMEOUT).await {
+            self.metrics.failed(&record.topic);
+            anyhow::bail!(
+                "Failed to produce record {}/{}@{} to dead letter topic {}: {e}",
+                record.topic,
+                record.partition,
+                record.offset,
+                self.topic
+            );
+        }
+
+        let size = record.payload.as_ref().map_or(0, Vec::len);
+        self.metrics.sent(&record.topic, reason, size);
+        debug!(
+            "Produced record {}/{}@{} to dead letter topic {} ({reason}): {error}",
+            record.topic, record.partition, record.offset, self.topic
+        );
+
+        Ok(())
+    }
+}
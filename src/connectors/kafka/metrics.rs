@@ -24,6 +24,8 @@ use prometheus::{
 use rdkafka::Statistics;
 use std::sync::{Arc, RwLock};
 
+use super::dlt::DeadLetterReason;
+
 #[derive(Debug)]
 pub struct KafkaMetricsCollector {
     stats: Arc<RwLock<Statistics>>,
@@ -34,6 +36,8 @@ pub struct KafkaMetricsCollector {
     partition_metrics: PartitionMetrics,
     consumer_metrics: ConsumerGroupMetrics,
     eos_metrics: EosMetrics,
+    dlt_metrics: DltMetrics,
+    offset_metrics: OffsetMetrics,
 }
 
 #[derive(Debug)]
@@ -126,6 +130,24 @@ struct EosMetrics {
     producer_epoch: IntGauge,
 }
 
+/// Volume of records produced to the dead letter topic, per source topic.
+/// Updated by the sink processor, hence not derived from the librdkafka statistics.
+#[derive(Debug, Clone)]
+pub struct DltMetrics {
+    records: IntCounterVec,
+    bytes: IntCounterVec,
+    failures: IntCounterVec,
+}
+
+/// Offsets of each assigned partition, as ingested into staging and as committed once
+/// durable. Updated by the stream worker, hence not derived from the librdkafka statistics.
+#[derive(Debug, Clone)]
+pub struct OffsetMetrics {
+    processed: IntGaugeVec,
+    committed: IntGaugeVec,
+    lag: IntGaugeVec,
+}
+
 impl CoreMetrics {
     fn new() -> anyhow::Result<Self> {
         Ok(Self {
@@ -744,8 +766,136 @@ impl EosMetrics {
     }
 }
 
+impl DltMetrics {
+    pub fn new() -> anyhow::Result<Self> {
+        Ok(Self {
+            records: IntCounterVec::new(
+                Opts::new(
+                    "kafka_dlt_records_total",
+                    "Total records produced to the dead letter topic",
+                ),
+                &["topic", "reason"],
+            )?,
+            bytes: IntCounterVec::new(
+                Opts::new(
+                    "kafka_dlt_bytes_total",
+                    "Total payload bytes produced to the dead letter topic",
+                ),
+                &["topic"],
+            )?,
+            failures: IntCounterVec::new(
+                Opts::new(
+                    "kafka_dlt_failures_total",
+                    "Total records that failed to be produced to the dead letter topic",
+                ),
+                &["topic"],
+            )?,
+        })
+    }
+
+    pub fn sent(&self, topic: &str, reason: DeadLetterReason, bytes: usize) {
+        self.records
+            .with_label_values(&[topic, reason.as_str()])
+            .inc();
+        self.bytes.with_label_values(&[topic]).inc_by(bytes as u64);
+    }
+
+    pub fn failed(&self, topic: &str) {
+        self.failures.with_label_values(&[topic]).inc();
+    }
+
+    fn descs(&self) -> impl Iterator<Item = Desc> + '_ {
+        [&self.records, &self.bytes, &self.failures]
+            .into_iter()
+            .flat_map(|counter| counter.desc().into_iter().cloned())
+    }
+
+    fn collect_all_metrics(&self, mfs: &mut Vec<proto::MetricFamily>) {
+        mfs.extend(self.records.collect());
+        mfs.extend(self.bytes.collect());
+        mfs.extend(self.failures.collect());
+    }
+}
+
+impl OffsetMetrics {
+    pub fn new() -> anyhow::Result<Self> {
+        let partition_labels = &["topic", "partition"];
+        Ok(Self {
+            processed: IntGaugeVec::new(
+                Opts::new(
+                    "kafka_sink_processed_offset",
+                    "Last offset ingested into staging, per partition",
+                ),
+                partition_labels,
+            )?,
+            committed: IntGaugeVec::new(
+                Opts::new(
+                    "kafka_sink_committed_offset",
+                    "Last offset committed once durable in staging, per partition",
+                ),
+                partition_labels,
+            )?,
+            lag: IntGaugeVec::new(
+                Opts::new(
+                    "kafka_sink_commit_lag",
+                    "Offsets ingested into staging but not committed yet, per partition",
+                ),
+                partition_labels,
+            )?,
+        })
+    }
+
+    pub fn processed(&self, topic: &str, partition: i32, offset: i64) {
+        let partition = partition.to_string();
+        let labels = [topic, partition.as_str()];
+        let processed = self.processed.with_label_values(&labels);
+        processed.set(processed.get().max(offset));
+        self.set_lag(&labels);
+    }
+
+    pub fn committed(&self, topic: &str, partition: i32, offset: i64) {
+        let partition = partition.to_string();
+        let labels = [topic, partition.as_str()];
+        self.committed.with_label_values(&labels).set(offset);
+        self.set_lag(&labels);
+    }
+
+    /// Drops the series of a partition that is no longer assigned
+    pub fn remove(&self, topic: &str, partition: i32) {
+        let partition = partition.to_string();
+        let labels = [topic, partition.as_str()];
+        for gauge in [&self.processed, &self.committed, &self.lag] {
+            let _ = gauge.remove_label_values(&labels);
+        }
+    }
+
+    fn set_lag(&self, labels: &[&str]) {
+        let processed = self.processed.with_label_values(labels).get();
+        let committed = self.committed.with_label_values(labels).get();
+        self.lag
+            .with_label_values(labels)
+            .set((processed - committed).max(0));
+    }
+
+    fn descs(&self) -> impl Iterator<Item = Desc> + '_ {
+        [&self.processed, &self.committed, &self.lag]
+            .into_iter()
+            .flat_map(|gauge| gauge.desc().into_iter().cloned())
+    }
+
+    fn collect_all_metrics(&self, mfs: &mut Vec<proto::MetricFamily>) {
+        mfs.extend(self.processed.collect());
+        mfs.extend(self.committed.collect());
+        mfs.extend(self.lag.collect());
+    }
+}
+
 impl KafkaMetricsCollector {
-    pub fn new(stats: Arc<RwLock<Statistics>>) -> anyhow::Result<KafkaMetricsCollector> {
+    pub fn new(
+        stats: Arc<RwLock<Statistics>>,
+        dlt_metrics: DltMetrics,
+        offset_metrics: OffsetMetrics,
+    ) -> anyhow::Result<KafkaMetricsCollector> {
         let mut descs = Vec::new();
         let topic_labels = &["topic"];
         let partition_labels = &["topic", "partition"];
@@ -756,6 +906,8 @@ impl KafkaMetricsCollector {
         let partition_metrics = PartitionMetrics::new(partition_labels, &mut descs);
         let consumer_metrics = ConsumerGroupMetrics::new()?;
         let eos_metrics = EosMetrics::new()?;
+        descs.extend(dlt_metrics.descs());
+        descs.extend(offset_metrics.descs());
 
         Ok(KafkaMetricsCollector {
             stats,
@@ -766,6 +918,8 @@ impl KafkaMetricsCollector {
             partition_metrics,
             consumer_metrics,
             eos_metrics,
+            dlt_metrics,
+            offset_metrics,
         })
     }
 
@@ -848,6 +1002,12 @@ impl Collector for KafkaMetricsCollector {
             mfs.extend(self.eos_metrics.collect_metrics(eos));
         }
 
+        // Collect dead letter topic metrics
+        self.dlt_metrics.collect_all_metrics(&mut mfs);
+
+        // Collect partition offset metrics
+        self.offset_metrics.collect_all_metrics(&mut mfs);
+
         mfs
     }
 }
@@ -860,7 +1020,16 @@ mod tests {
     #[test]
     fn test_kafka_metrics_collector() {
         let stats = Arc::new(RwLock::new(Statistics::default()));
-        let collector = KafkaMetricsCollector::new(stats).unwrap();
+        let dlt_metrics = DltMetrics::new().unwrap();
+        dlt_metrics.sent("logs", DeadLetterReason::Undecodable, 42);
+        let offset_metrics = OffsetMetrics::new().unwrap();
+        offset_metrics.processed("logs", 0, 100);
+        offset_metrics.committed("logs", 0, 60);
+        assert_eq!(
+            offset_metrics.lag.with_label_values(&["logs", "0"]).get(),
+            40
+        );
+        let collector = KafkaMetricsCollector::new(stats, dlt_metrics, offset_metrics).unwrap();
 
         let descs = collector.desc();
         assert!(!descs.is_empty());
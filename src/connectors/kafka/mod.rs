@@ -26,7 +26,6 @@ use rdkafka::producer::ProducerContext;
 use rdkafka::topic_partition_list::TopicPartitionListElem;
 use rdkafka::{ClientContext, Message, Offset, Statistics};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
 use std::error::Error;
 use std::sync::{Arc, RwLock};
 use tokio::sync::mpsc;
@@ -35,10 +34,15 @@ use tracing::{error, info, warn};
 
 pub mod config;
 pub mod consumer;
+pub mod decoder;
+pub mod dlt;
 pub mod metrics;
+pub mod offsets;
 mod partition_stream;
 pub mod processor;
 pub mod rebalance_listener;
+pub mod routing;
+pub mod schema_registry;
 pub mod sink;
 pub mod state;
 #[allow(dead_code)]
@@ -145,7 +149,7 @@ pub struct ConsumerRecord {
     pub partition: i32,
     pub offset: i64,
     pub timestamp: Option<i64>,
-    //pub headers: Option<HashMap<String, Option<String>>>,
+    pub headers: Vec<(String, Option<String>)>,
 }
 
 impl ConsumerRecord {
@@ -157,7 +161,7 @@ impl ConsumerRecord {
             partition: msg.partition(),
             offset: msg.offset(),
             timestamp: msg.timestamp().to_millis(),
-            //headers: extract_headers(&msg),
+            headers: extract_headers(&msg),
         }
     }
 
@@ -179,9 +183,8 @@ impl ConsumerRecord {
     }
 }
 
-#[allow(unused)]
-fn extract_headers(msg: &BorrowedMessage<'_>) -> Option<HashMap<String, Option<String>>> {
-    msg.headers().map(|headers| {
+fn extract_headers(msg: &BorrowedMessage<'_>) -> Vec<(String, Option<String>)> {
+    msg.headers().map_or_else(Vec::new, |headers| {
         headers
             .iter()
             .map(|header| {
@@ -0,0 +1,116 @@
+/*
+ * Parseable Server (C) 2022 - 2025 Parseable, Inc.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ */
+
+use std::collections::VecDeque;
+
+/// Batches of a partition that are being processed, ordered by offset.
+///
+/// Batches are processed concurrently and may become durable out of order, an offset
+/// is only safe to commit once every batch up to it is durable, so that a restart
+/// replays whatever could still be lost.
+#[derive(Debug, Default)]
+pub struct PartitionOffsets {
+    in_flight: VecDeque<(i64, bool)>,
+    started: bool,
+    /// Batches being retried after failing
+    failing: usize,
+}
+
+impl PartitionOffsets {
+    /// Tracks the batch spanning `first_offset..=last_offset`. On the partition's first
+    /// batch, returns the offset consumption resumed after, i.e. the last committed one.
+    pub fn begin(&mut self, first_offset: i64, last_offset: i64) -> Option<i64> {
+        let at = self
+            .in_flight
+            .partition_point(|(offset, _)| *offset < last_offset);
+        self.in_flight.insert(at, (last_offset, false));
+
+        (!std::mem::replace(&mut self.started, true)).then_some(first_offset - 1)
+    }
+
+    /// Marks the batch ending at `last_offset` as durable, returning the highest offset
+    /// that can now be committed, if any.
+    pub fn complete(&mut self, last_offset: i64) -> Option<i64> {
+        if let Some((_, durable)) = self
+            .in_flight
+            .iter_mut()
+            .find(|(offset, _)| *offset == last_offset)
+        {
+            *durable = true;
+        }
+
+        let mut committable = None;
+        while let Some(&(offset, true)) = self.in_flight.front() {
+            committable = Some(offset);
+            self.in_flight.pop_front();
+        }
+
+        committable
+    }
+
+    /// Tracks a batch that failed and is being retried. Returns `true` if no other batch
+    /// is, i.e. the partition is to be paused.
+    pub fn fail(&mut self) -> bool {
+        self.failing += 1;
+        self.failing == 1
+    }
+
+    /// Tracks a failed batch that went through once retried. Returns `true` if no other
+    /// batch is failing, i.e. the partition can be resumed.
+    pub fn recover(&mut self) -> bool {
+        self.failing = self.failing.saturating_sub(1);
+        self.failing == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commits_only_contiguous_durable_batches() {
+        let mut offsets = PartitionOffsets::default();
+        assert_eq!(offsets.begin(10, 19), Some(9));
+        assert_eq!(offsets.begin(20, 29), None);
+        assert_eq!(offsets.begin(30, 39), None);
+
+        // a later batch being durable doesn't make the earlier ones so
+        assert_eq!(offsets.complete(29), None);
+        assert_eq!(offsets.complete(19), Some(29));
+
+        // a batch that isn't durable yet holds back the ones after it
+        assert_eq!(offsets.begin(40, 49), None);
+        assert_eq!(offsets.complete(49), None);
+        assert_eq!(offsets.complete(39), Some(49));
+        assert_eq!(offsets.complete(39), None);
+    }
+
+    #[test]
+    fn paused_while_any_batch_is_failing() {
+        let mut offsets = PartitionOffsets::default();
+        assert_eq!(offsets.begin(10, 19), Some(9));
+        assert_eq!(offsets.begin(20, 29), None);
+
+        assert!(offsets.fail());
+        assert!(!offsets.fail());
+        assert!(!offsets.recover());
+        assert_eq!(offsets.complete(29), None);
+        assert!(offsets.recover());
+        assert_eq!(offsets.complete(19), Some(29));
+    }
+}
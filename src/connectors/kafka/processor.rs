@@ -17,39 +17,70 @@
  */
 
 use crate::{
-    connectors::common::processor::Processor,
+    LOCK_EXPECT,
+    connectors::common::{BadData, processor::Processor},
     event::{
         Event as ParseableEvent, USER_AGENT_KEY,
-        format::{EventFormat, LogSourceEntry, json},
+        format::{EventFormat, LogSourceEntry, arrow, json},
     },
     handlers::TelemetryType,
-    parseable::PARSEABLE,
+    parseable::{FlushAck, PARSEABLE},
     storage::StreamType,
 };
+use anyhow::anyhow;
 use async_trait::async_trait;
 use chrono::Utc;
-use futures_util::StreamExt;
+use futures_util::{StreamExt, future::join_all};
+use rdkafka::Offset;
 use rdkafka::consumer::{CommitMode, Consumer};
 use serde_json::Value;
-use std::collections::HashMap;
-use std::sync::Arc;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use tokio_stream::wrappers::ReceiverStream;
-use tracing::{debug, error};
+use tracing::{debug, error, warn};
 
-use super::{ConsumerRecord, StreamConsumer, TopicPartition, config::BufferConfig};
+use super::{
+    ConsumerRecord, StreamConsumer, TopicPartition,
+    config::BufferConfig,
+    decoder::{Chunk, Decoder, Values, metadata::RecordMetadata},
+    dlt::{DeadLetterReason, DeadLetterTopic},
+    metrics::OffsetMetrics,
+    offsets::PartitionOffsets,
+    routing::StreamRouter,
+};
+
+/// Delay before a failed batch is retried, doubled on each attempt up to [`MAX_RETRY_DELAY`]
+const RETRY_DELAY: Duration = Duration::from_secs(1);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
 
 #[derive(Default, Debug, Clone)]
-pub struct ParseableSinkProcessor;
+pub struct ParseableSinkProcessor {
+    bad_data: BadData,
+    dead_letter: Option<Arc<DeadLetterTopic>>,
+    decoder: Arc<Decoder>,
+    router: Arc<StreamRouter>,
+    metadata: Arc<RecordMetadata>,
+}
 
 impl ParseableSinkProcessor {
-    async fn build_event_from_chunk(
-        &self,
-        records: &[ConsumerRecord],
-    ) -> anyhow::Result<ParseableEvent> {
-        let stream_name = records
-            .first()
-            .map(|r| r.topic.as_str())
-            .unwrap_or_default();
+    pub fn new(
+        bad_data: BadData,
+        dead_letter: Option<Arc<DeadLetterTopic>>,
+        decoder: Decoder,
+        router: StreamRouter,
+        metadata: RecordMetadata,
+    ) -> Self {
+        Self {
+            bad_data,
+            dead_letter,
+            decoder: Arc::new(decoder),
+            router: Arc::new(router),
+            metadata: Arc::new(metadata),
+        }
+    }
+
+    async fn ensure_stream(&self, stream_name: &str) -> anyhow::Result<()> {
         let log_source_entry = LogSourceEntry::default();
         PARSEABLE
             .create_stream_if_not_exists(
@@ -61,71 +92,269 @@ impl ParseableSinkProcessor {
             )
             .await?;
 
+        Ok(())
+    }
+
+    fn build_event(
+        &self,
+        stream_name: &str,
+        values: Values,
+        records: &[&ConsumerRecord],
+        total_payload_size: u64,
+    ) -> anyhow::Result<Option<ParseableEvent>> {
         let stream = PARSEABLE.get_stream(stream_name)?;
         let schema = stream.get_schema_raw();
         let time_partition = stream.get_time_partition();
         let custom_partition = stream.get_custom_partition();
         let static_schema_flag = stream.get_static_schema_flag();
         let schema_version = stream.get_schema_version();
+        let settings = stream.get_settings();
 
-        let mut json_vec = Vec::with_capacity(records.len());
-        let mut total_payload_size = 0u64;
+        let mut p_custom_fields = HashMap::new();
+        p_custom_fields.insert(USER_AGENT_KEY.to_string(), "kafka".to_string());
 
-        for record in records.iter().filter_map(|r| r.payload.as_ref()) {
-            total_payload_size += record.len() as u64;
-            if let Ok(value) = serde_json::from_slice::<Value>(record) {
-                json_vec.push(value);
+        let p_event = match self.metadata.apply(values, records) {
+            // partitions are derived from, and the stream's settings applied to, JSON values only
+            Values::Rows(layout, rows)
+                if time_partition.is_none()
+                    && custom_partition.is_none()
+                    && !settings.transforms_events() =>
+            {
+                arrow::Event::new(layout.to_batch(&rows)?, Utc::now()).into_event(
+                    stream_name.to_string(),
+                    total_payload_size,
+                    &schema,
+                    static_schema_flag,
+                    None,
+                    None,
+                    schema_version,
+                    StreamType::UserDefined,
+                    &p_custom_fields,
+                    TelemetryType::Logs,
+                )?
             }
-        }
+            values => {
+                let Some(json) = settings.apply(
+                    stream_name,
+                    Value::Array(values.into_json()),
+                    &stream.rate_limiter,
+                    &p_custom_fields,
+                ) else {
+                    return Ok(None);
+                };
+                json::Event::new(json, Utc::now()).into_event(
+                    stream_name.to_string(),
+                    total_payload_size,
+                    &schema,
+                    static_schema_flag,
+                    custom_partition.as_ref(),
+                    time_partition.as_ref(),
+                    schema_version,
+                    StreamType::UserDefined,
+                    &p_custom_fields,
+                    TelemetryType::Logs,
+                )?
+            }
+        };
 
-        let mut p_custom_fields = HashMap::new();
-        p_custom_fields.insert(USER_AGENT_KEY.to_string(), "kafka".to_string());
+        Ok(Some(p_event))
+    }
 
-        let p_event = json::Event::new(Value::Array(json_vec), Utc::now()).into_event(
-            stream_name.to_string(),
-            total_payload_size,
-            &schema,
-            static_schema_flag,
-            custom_partition.as_ref(),
-            time_partition.as_ref(),
-            schema_version,
-            StreamType::UserDefined,
-            &p_custom_fields,
-            TelemetryType::Logs,
-        )?;
-
-        Ok(p_event)
+    /// Applies the bad data policy to a record that can't be ingested
+    async fn handle_bad_record(
+        &self,
+        record: &ConsumerRecord,
+        reason: DeadLetterReason,
+        error: anyhow::Error,
+    ) -> anyhow::Result<()> {
+        match (&self.bad_data, &self.dead_letter) {
+            (BadData::Dlt, Some(dead_letter)) => {
+                dead_letter
+                    .send(record, reason, &format!("{error:#}"))
+                    .await
+            }
+            (BadData::Drop, _) => {
+                warn!(
+                    "Dropping {reason} record {}/{}@{}: {error:#}",
+                    record.topic, record.partition, record.offset
+                );
+                Ok(())
+            }
+            _ => Err(error.context(format!(
+                "{reason} record {}/{}@{}",
+                record.topic, record.partition, record.offset
+            ))),
+        }
     }
 }
 
 #[async_trait]
-impl Processor<Vec<ConsumerRecord>, ()> for ParseableSinkProcessor {
-    async fn process(&self, records: Vec<ConsumerRecord>) -> anyhow::Result<()> {
+impl Processor<Arc<Batch>, ()> for ParseableSinkProcessor {
+    async fn process(&self, batch: Arc<Batch>) -> anyhow::Result<()> {
+        // records already staged or handled as bad data aren't processed again on retries
+        let records = batch.pending();
         let len = records.len();
         debug!("Processing {len} records");
 
-        self.build_event_from_chunk(&records).await?.process()?;
+        // fails only if the schema registry can't be reached, the records being good
+        let decoded = self.decoder.decode(records).await?;
+        for (record, e) in decoded.failed {
+            self.handle_bad_record(record, DeadLetterReason::Undecodable, e)
+                .await?;
+            batch.settle(&[record]);
+        }
+
+        let mut routed = Vec::with_capacity(decoded.decoded.len());
+        for (record, value) in decoded.decoded {
+            match self.router.route(record, &value) {
+                Ok(stream_name) => routed.push((stream_name, record, value)),
+                Err(e) => {
+                    self.handle_bad_record(record, DeadLetterReason::Rejected, e)
+                        .await?;
+                    batch.settle(&[record]);
+                }
+            }
+        }
+
+        for chunk in Chunk::group(routed) {
+            let stream_name = chunk.stream_name.as_str();
+            self.ensure_stream(stream_name).await?;
+            match self.build_event(
+                stream_name,
+                chunk.values,
+                &chunk.records,
+                chunk.payload_size,
+            ) {
+                Ok(Some(event)) => batch.stage(&chunk.records, event.process()?),
+                Ok(None) => batch.settle(&chunk.records),
+                Err(e) if self.bad_data == BadData::Fail => return Err(e),
+                // the chunk was rejected, ingest its records one by one to single out the bad ones
+                Err(e) => {
+                    debug!(
+                        "Batch rejected, ingesting its {} records one by one: {e:#}",
+                        chunk.records.len()
+                    );
+                    for record in chunk.records {
+                        let single = self.decoder.decode([record]).await?;
+                        let Some((_, value)) = single.decoded.into_iter().next() else {
+                            batch.settle(&[record]);
+                            continue;
+                        };
+                        let payload_size = record.payload.as_ref().map_or(0, Vec::len) as u64;
+                        match self.build_event(stream_name, value.into(), &[record], payload_size) {
+                            Ok(Some(event)) => batch.stage(&[record], event.process()?),
+                            Ok(None) => batch.settle(&[record]),
+                            Err(e) => {
+                                self.handle_bad_record(record, DeadLetterReason::Rejected, e)
+                                    .await?;
+                                batch.settle(&[record]);
+                            }
+                        }
+                    }
+                }
+            }
+        }
 
         debug!("Processed {len} records");
         Ok(())
     }
 }
 
+/// The records of a batch and what was done with them so far, such that a batch that
+/// failed is retried from where it failed
+#[derive(Debug)]
+pub struct Batch {
+    pub records: Vec<ConsumerRecord>,
+    progress: Mutex<Progress>,
+}
+
+#[derive(Debug, Default)]
+struct Progress {
+    /// Offsets of the records staged, sent to the dead letter topic or dropped
+    done: HashSet<i64>,
+    /// Acknowledgements of the records staged and not waited for yet, with their offsets
+    staged: Vec<(FlushAck, Vec<i64>)>,
+}
+
+impl Batch {
+    pub fn new(records: Vec<ConsumerRecord>) -> Self {
+        Self {
+            records,
+            progress: Mutex::default(),
+        }
+    }
+
+    /// Records left to be processed
+    pub fn pending(&self) -> Vec<&ConsumerRecord> {
+        let progress = self.progress.lock().expect(LOCK_EXPECT);
+        self.records
+            .iter()
+            .filter(|record| !progress.done.contains(&record.offset))
+            .collect()
+    }
+
+    /// Marks the records as sent to the dead letter topic, dropped or filtered out
+    pub fn settle(&self, records: &[&ConsumerRecord]) {
+        let mut progress = self.progress.lock().expect(LOCK_EXPECT);
+        progress
+            .done
+            .extend(records.iter().map(|record| record.offset));
+    }
+
+    /// Marks the records as staged, durable once `ack` is
+    pub fn stage(&self, records: &[&ConsumerRecord], ack: FlushAck) {
+        let offsets: Vec<i64> = records.iter().map(|record| record.offset).collect();
+        let mut progress = self.progress.lock().expect(LOCK_EXPECT);
+        progress.done.extend(&offsets);
+        progress.staged.push((ack, offsets));
+    }
+
+    /// Waits for the staged records to be durable, returns `false` if some of them
+    /// couldn't be made durable, these are left to be processed again
+    pub async fn flushed(&self) -> bool {
+        let staged = std::mem::take(&mut self.progress.lock().expect(LOCK_EXPECT).staged);
+        let flushed = join_all(
+            staged
+                .into_iter()
+                .map(|(ack, offsets)| async move { (ack.flushed().await, offsets) }),
+        )
+        .await;
+
+        let mut progress = self.progress.lock().expect(LOCK_EXPECT);
+        let mut all_flushed = true;
+        for (flushed, offsets) in flushed {
+            if !flushed {
+                all_flushed = false;
+                for offset in offsets {
+                    progress.done.remove(&offset);
+                }
+            }
+        }
+
+        all_flushed
+    }
+}
+
 #[derive(Clone)]
 pub struct StreamWorker<P>
 where
-    P: Processor<Vec<ConsumerRecord>, ()>,
+    P: Processor<Arc<Batch>, ()>,
 {
     processor: Arc<P>,
     consumer: Arc<StreamConsumer>,
     buffer_config: BufferConfig,
+    offset_metrics: OffsetMetrics,
 }
 
 impl<P> StreamWorker<P>
 where
-    P: Processor<Vec<ConsumerRecord>, ()> + Send + Sync + 'static,
+    P: Processor<Arc<Batch>, ()> + Send + Sync + 'static,
 {
-    pub fn new(processor: Arc<P>, consumer: Arc<StreamConsumer>) -> Self {
+    pub fn new(
+        processor: Arc<P>,
+        consumer: Arc<StreamConsumer>,
+        offset_metrics: OffsetMetrics,
+    ) -> Self {
         let buffer_config = consumer
             .context()
             .config()
@@ -137,9 +366,15 @@ where
             processor,
             consumer,
             buffer_config,
+            offset_metrics,
         }
     }
 
+    /// Offsets are committed only once the records up to them are durable in staging,
+    /// if the ingestor goes down before that, they are consumed again on restart.
+    /// A batch that fails is retried, from the records that weren't staged or handled as
+    /// bad data, until it succeeds. The partition is paused meanwhile as the batches after
+    /// it can't be committed either.
     pub async fn process_partition(
         &self,
         tp: TopicPartition,
@@ -150,30 +385,106 @@ where
             self.buffer_config.buffer_size,
             self.buffer_config.buffer_timeout,
         );
+        let offsets = Mutex::new(PartitionOffsets::default());
 
         chunked_stream
             .for_each_concurrent(None, |records| async {
-                if let Some(last_record) = records.iter().max_by_key(|r| r.offset) {
-                    let tpl = last_record.offset_to_commit().unwrap();
+                let (Some(first_offset), Some(last_offset)) = (
+                    records.iter().map(|r| r.offset).min(),
+                    records.iter().map(|r| r.offset).max(),
+                ) else {
+                    return;
+                };
 
-                    if let Err(e) = self.processor.process(records).await {
-                        error!("Failed to process records for {:?}: {:?}", tp, e);
-                    }
+                let resumed_from = offsets
+                    .lock()
+                    .expect(LOCK_EXPECT)
+                    .begin(first_offset, last_offset);
+                if let Some(offset) = resumed_from {
+                    self.offset_metrics
+                        .committed(&tp.topic, tp.partition, offset);
+                }
 
-                    //CommitMode::Async race condition.
-                    //@see https://github.com/confluentinc/librdkafka/issues/4534
-                    //@see https://github.com/confluentinc/librdkafka/issues/4059
-                    if let Err(e) = self.consumer.commit(&tpl, CommitMode::Sync) {
-                        error!(error = %e, "Failed to commit offsets for {:?}", tpl);
-                    } else {
-                        debug!("Committed offsets for {:?}", tpl);
+                let batch = Arc::new(Batch::new(records));
+                let mut delay = RETRY_DELAY;
+                let mut failed = false;
+                while let Err(e) = self.ingest(Arc::clone(&batch)).await {
+                    error!(
+                        "Failed to ingest records for {:?} from offset {}, retrying in {:?}: {:#}",
+                        tp, first_offset, delay, e
+                    );
+                    if !failed {
+                        failed = true;
+                        if offsets.lock().expect(LOCK_EXPECT).fail() {
+                            self.set_paused(&tp, true);
+                        }
                     }
+                    tokio::time::sleep(delay).await;
+                    delay = (delay * 2).min(MAX_RETRY_DELAY);
+                }
+                self.offset_metrics
+                    .processed(&tp.topic, tp.partition, last_offset);
+
+                // committing under the lock keeps concurrent batches from committing out of order
+                let mut offsets = offsets.lock().expect(LOCK_EXPECT);
+                if failed && offsets.recover() {
+                    self.set_paused(&tp, false);
+                }
+                if let Some(offset) = offsets.complete(last_offset) {
+                    self.commit(&tp, offset);
                 }
             })
             .await;
 
+        self.offset_metrics.remove(&tp.topic, tp.partition);
         self.processor.post_stream().await?;
 
         Ok(())
     }
+
+    /// Processes the records left in the batch and waits for them to be durable in staging
+    async fn ingest(&self, batch: Arc<Batch>) -> anyhow::Result<()> {
+        let processed = self.processor.process(Arc::clone(&batch)).await;
+        // the records staged before a failure are waited for all the same
+        if !batch.flushed().await {
+            return Err(anyhow!("records couldn't be persisted to staging"));
+        }
+
+        processed
+    }
+
+    /// Stops or resumes fetching the records of the partition
+    fn set_paused(&self, tp: &TopicPartition, paused: bool) {
+        let mut tpl = rdkafka::TopicPartitionList::new();
+        tpl.add_partition(&tp.topic, tp.partition);
+        let result = if paused {
+            self.consumer.pause(&tpl)
+        } else {
+            self.consumer.resume(&tpl)
+        };
+        if let Err(e) = result {
+            error!(error = %e, "Failed to set {:?} paused to {}", tp, paused);
+        }
+    }
+
+    fn commit(&self, tp: &TopicPartition, offset: i64) {
+        let mut tpl = rdkafka::TopicPartitionList::new();
+        if let Err(e) =
+            tpl.add_partition_offset(&tp.topic, tp.partition, Offset::Offset(offset + 1))
+        {
+            error!(error = %e, "Failed to build offsets to commit for {:?}", tp);
+            return;
+        }
+
+        //CommitMode::Async race condition.
+        //@see https://github.com/confluentinc/librdkafka/issues/4534
+        //@see https://github.com/confluentinc/librdkafka/issues/4059
+        if let Err(e) = self.consumer.commit(&tpl, CommitMode::Sync) {
+            error!(error = %e, "Failed to commit offsets for {:?}", tpl);
+        } else {
+            debug!("Committed offsets for {:?}", tpl);
+            self.offset_metrics
+                .committed(&tp.topic, tp.partition, offset);
+        }
+    }
 }
@@ -0,0 +1,119 @@
+/*
+ * Parseable Server (C) 2022 - 2025 Parseable, Inc.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ */
+
+//! Routing of records to the streams they are ingested in
+
+use anyhow::Context;
+
+use crate::{storage::StreamType, validator};
+
+use super::{
+    ConsumerRecord,
+    config::{ConsumerConfig, TopicRoute},
+    decoder::DecodedValue,
+};
+
+#[derive(Debug, Default)]
+pub struct StreamRouter {
+    routes: Vec<TopicRoute>,
+    field: Option<String>,
+}
+
+impl StreamRouter {
+    pub fn new(config: &ConsumerConfig) -> Self {
+        Self {
+            routes: config.stream_routes.clone(),
+            field: config.stream_route_field.clone(),
+        }
+    }
+
+    /// The stream named by the route field of the value if set, else by the
+    /// first route the topic matches, else after the topic. Fails if the
+    /// value or route names an invalid stream.
+    pub fn route(&self, record: &ConsumerRecord, value: &DecodedValue) -> anyhow::Result<String> {
+        let stream_name = match self.field.as_deref().and_then(|field| value.get_str(field)) {
+            Some(stream_name) => stream_name.to_owned(),
+            None => {
+                let Some((route, captures)) = self
+                    .routes
+                    .iter()
+                    .find_map(|route| Some((route, route.pattern.captures(&record.topic)?)))
+                else {
+                    return Ok(record.topic.clone());
+                };
+                let mut stream_name = String::new();
+                captures.expand(&route.template, &mut stream_name);
+                stream_name
+            }
+        };
+        validator::stream_name(&stream_name, StreamType::UserDefined)
+            .with_context(|| format!("Invalid stream name {stream_name}"))?;
+
+        Ok(stream_name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn record(topic: &str) -> ConsumerRecord {
+        ConsumerRecord {
+            payload: None,
+            key: None,
+            topic: topic.to_owned(),
+            partition: 0,
+            offset: 0,
+            timestamp: None,
+            headers: vec![],
+        }
+    }
+
+    #[test]
+    fn route_by_field_then_topic() {
+        let router = StreamRouter::new(&ConsumerConfig {
+            stream_routes: vec![
+                "logs\\.(?<app>[a-z]+)\\.v[0-9]+=app-${app}"
+                    .parse()
+                    .unwrap(),
+                "metrics\\..*=metrics".parse().unwrap(),
+            ],
+            stream_route_field: Some("stream".to_owned()),
+            ..Default::default()
+        });
+        let value = DecodedValue::Json(json!({"message": "hello"}));
+
+        let route = |topic: &str, value: &DecodedValue| router.route(&record(topic), value).ok();
+        assert_eq!(
+            route("logs.billing.v2", &value).as_deref(),
+            Some("app-billing")
+        );
+        assert_eq!(route("metrics.cpu", &value).as_deref(), Some("metrics"));
+        assert_eq!(
+            route("logs.billing", &value).as_deref(),
+            Some("logs.billing")
+        );
+
+        let value = DecodedValue::Json(json!({"stream": "audit"}));
+        assert_eq!(route("metrics.cpu", &value).as_deref(), Some("audit"));
+        let value = DecodedValue::Json(json!({"stream": "no spaces"}));
+        assert_eq!(route("metrics.cpu", &value), None);
+    }
+}
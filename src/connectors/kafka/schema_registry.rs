@@ -0,0 +1,204 @@
+/*
+ * Parseable Server (C) 2022 - 2025 Parseable, Inc.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ */
+
+//! Client of Confluent compatible schema registries, caching the schemas it fetches
+
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, RwLock};
+
+use anyhow::anyhow;
+use reqwest::{StatusCode, header::ACCEPT};
+use serde::{Deserialize, de::DeserializeOwned};
+use url::Url;
+
+use crate::{HTTP_CLIENT, LOCK_EXPECT};
+
+use super::decoder::{avro::AvroSchema, protobuf::ProtoSchema};
+
+const CONTENT_TYPES: &str = "application/vnd.schemaregistry.v1+json, application/json";
+
+/// Schemas referenced by a schema, directly or not, beyond which it is rejected
+const MAX_REFERENCES: usize = 256;
+
+#[derive(Debug)]
+pub enum RegisteredSchema {
+    Avro(AvroSchema),
+    Protobuf(ProtoSchema),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum RegistryError {
+    #[error("Schema {0} not found in the schema registry")]
+    NotFound(u32),
+    #[error("Schema {0} can't be used: {1:#}")]
+    Invalid(u32, anyhow::Error),
+    #[error("Schema registry request failed: {0}")]
+    Request(#[from] reqwest::Error),
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SchemaResponse {
+    schema: String,
+    /// Absent for Avro schemas
+    schema_type: Option<String>,
+    #[serde(default)]
+    references: Vec<SchemaReference>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SchemaReference {
+    /// Type name of Avro schemas, import path of Protobuf ones
+    name: String,
+    subject: String,
+    version: i32,
+}
+
+pub struct SchemaRegistry {
+    url: Url,
+    username: Option<String>,
+    password: Option<String>,
+    /// Schemas by id, which never change once registered
+    schemas: RwLock<HashMap<u32, Arc<RegisteredSchema>>>,
+}
+
+impl std::fmt::Debug for SchemaRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SchemaRegistry")
+            .field("url", &self.url.as_str())
+            .field("username", &self.username)
+            .finish_non_exhaustive()
+    }
+}
+
+impl SchemaRegistry {
+    pub fn new(url: Url, username: Option<String>, password: Option<String>) -> Self {
+        Self {
+            url,
+            username,
+            password,
+            schemas: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// The schema of the given id, fetched along with its references the first time
+    pub async fn get(&self, id: u32) -> Result<Arc<RegisteredSchema>, RegistryError> {
+        if let Some(schema) = self.schemas.read().expect(LOCK_EXPECT).get(&id) {
+            return Ok(schema.clone());
+        }
+
+        let response: SchemaResponse = self
+            .fetch(&["schemas", "ids", &id.to_string()])
+            .await?
+            .ok_or(RegistryError::NotFound(id))?;
+        let references = self.references(id, response.references).await?;
+        let schema = match response.schema_type.as_deref() {
+            None | Some("AVRO") => {
+                let references: Vec<_> = references
+                    .iter()
+                    .map(|(_, schema)| schema.as_str())
+                    .collect();
+                AvroSchema::parse(&response.schema, &references).map(RegisteredSchema::Avro)
+            }
+            Some("PROTOBUF") => {
+                ProtoSchema::parse(&response.schema, &references).map(RegisteredSchema::Protobuf)
+            }
+            Some(schema_type) => Err(anyhow!("{schema_type} schemas aren't supported")),
+        }
+        .map_err(|e| RegistryError::Invalid(id, e))?;
+
+        let schema = Arc::new(schema);
+        self.schemas
+            .write()
+            .expect(LOCK_EXPECT)
+            .insert(id, schema.clone());
+
+        Ok(schema)
+    }
+
+    /// Fetches the schemas referenced, directly or not, by name. Schemas come
+    /// after the ones they reference.
+    async fn references(
+        &self,
+        id: u32,
+        references: Vec<SchemaReference>,
+    ) -> Result<Vec<(String, String)>, RegistryError> {
+        let mut resolved = vec![];
+        let mut seen = HashSet::new();
+        // references along with their schema once their own references are queued
+        let mut stack: Vec<_> = references.into_iter().rev().map(|r| (r, None)).collect();
+        while let Some((reference, schema)) = stack.pop() {
+            if let Some(schema) = schema {
+                resolved.push((reference.name, schema));
+                continue;
+            }
+            if !seen.insert((reference.subject.clone(), reference.version)) {
+                continue;
+            }
+            if seen.len() > MAX_REFERENCES {
+                return Err(RegistryError::Invalid(
+                    id,
+                    anyhow!("more than {MAX_REFERENCES} schemas are referenced"),
+                ));
+            }
+
+            let version = reference.version.to_string();
+            let response: SchemaResponse = self
+                .fetch(&["subjects", &reference.subject, "versions", &version])
+                .await?
+                .ok_or_else(|| {
+                    RegistryError::Invalid(
+                        id,
+                        anyhow!(
+                            "referenced schema {} version {} not found",
+                            reference.subject,
+                            reference.version
+                        ),
+                    )
+                })?;
+            let nested = response.references;
+            stack.push((reference, Some(response.schema)));
+            stack.extend(nested.into_iter().rev().map(|r| (r, None)));
+        }
+
+        Ok(resolved)
+    }
+
+    /// Gets a resource of the registry, `None` if it doesn't exist
+    async fn fetch<T: DeserializeOwned>(
+        &self,
+        segments: &[&str],
+    ) -> Result<Option<T>, RegistryError> {
+        let mut url = self.url.clone();
+        url.path_segments_mut()
+            .expect("Schema registry URL is validated to be an http URL")
+            .pop_if_empty()
+            .extend(segments);
+
+        let mut request = HTTP_CLIENT.get(url).header(ACCEPT, CONTENT_TYPES);
+        if let Some(username) = &self.username {
+            request = request.basic_auth(username, self.password.as_ref());
+        }
+        let response = request.send().await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+
+        Ok(Some(response.error_for_status()?.json().await?))
+    }
+}
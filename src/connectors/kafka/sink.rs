@@ -17,9 +17,9 @@
  */
 use crate::connectors::common::build_runtime;
 use crate::connectors::common::processor::Processor;
-use crate::connectors::kafka::ConsumerRecord;
 use crate::connectors::kafka::consumer::KafkaStreams;
-use crate::connectors::kafka::processor::StreamWorker;
+use crate::connectors::kafka::metrics::OffsetMetrics;
+use crate::connectors::kafka::processor::{Batch, StreamWorker};
 use anyhow::Result;
 use futures_util::StreamExt;
 use rdkafka::consumer::Consumer;
@@ -29,7 +29,7 @@ use tracing::{error, info};
 
 pub struct KafkaSinkConnector<P>
 where
-    P: Processor<Vec<ConsumerRecord>, ()>,
+    P: Processor<Arc<Batch>, ()>,
 {
     streams: KafkaStreams,
     stream_processor: Arc<StreamWorker<P>>,
@@ -38,13 +38,14 @@ where
 
 impl<P> KafkaSinkConnector<P>
 where
-    P: Processor<Vec<ConsumerRecord>, ()> + Send + Sync + 'static,
+    P: Processor<Arc<Batch>, ()> + Send + Sync + 'static,
 {
-    pub fn new(kafka_streams: KafkaStreams, processor: P) -> Self {
+    pub fn new(kafka_streams: KafkaStreams, processor: P, offset_metrics: OffsetMetrics) -> Self {
         let consumer = kafka_streams.consumer();
         let stream_processor = Arc::new(StreamWorker::new(
             Arc::new(processor),
             Arc::clone(&consumer),
+            offset_metrics,
         ));
 
         let runtime = build_runtime(
@@ -19,11 +19,19 @@
 use std::sync::Arc;
 
 use actix_web_prometheus::PrometheusMetrics;
-use common::{processor::Processor, shutdown::Shutdown};
+use common::{BadData, processor::Processor, shutdown::Shutdown};
 use kafka::{
-    ConsumerRecord, KafkaContext, config::KafkaConfig, consumer::KafkaStreams,
-    metrics::KafkaMetricsCollector, processor::ParseableSinkProcessor,
-    rebalance_listener::RebalanceListener, sink::KafkaSinkConnector, state::StreamState,
+    KafkaContext,
+    config::KafkaConfig,
+    consumer::KafkaStreams,
+    decoder::{Decoder, metadata::RecordMetadata},
+    dlt::DeadLetterTopic,
+    metrics::{DltMetrics, KafkaMetricsCollector, OffsetMetrics},
+    processor::{Batch, ParseableSinkProcessor},
+    rebalance_listener::RebalanceListener,
+    routing::StreamRouter,
+    sink::KafkaSinkConnector,
+    state::StreamState,
 };
 use prometheus::Registry;
 use tokio::sync::RwLock;
@@ -44,7 +52,21 @@ pub async fn init(prometheus: &PrometheusMetrics) -> anyhow::Result<()> {
                 let config = PARSEABLE.kafka_config.clone();
                 let shutdown_handle = Shutdown::default();
                 let registry = prometheus.registry.clone();
-                let processor = ParseableSinkProcessor;
+                let dlt_metrics = DltMetrics::new()?;
+                let dead_letter = match &config.dlt_topic {
+                    Some(topic) if config.bad_data == BadData::Dlt => Some(Arc::new(
+                        DeadLetterTopic::new(&config, topic, dlt_metrics.clone())?,
+                    )),
+                    _ => None,
+                };
+                let consumer_config = config.consumer();
+                let processor = ParseableSinkProcessor::new(
+                    config.bad_data.clone(),
+                    dead_letter,
+                    consumer_config.map(Decoder::new).unwrap_or_default(),
+                    consumer_config.map(StreamRouter::new).unwrap_or_default(),
+                    consumer_config.map(RecordMetadata::new).unwrap_or_default(),
+                );
 
                 tokio::spawn({
                     let shutdown_handle = shutdown_handle.clone();
@@ -54,7 +76,8 @@ pub async fn init(prometheus: &PrometheusMetrics) -> anyhow::Result<()> {
                     }
                 });
 
-                run_kafka2parseable(config, registry, processor, shutdown_handle).await?;
+                run_kafka2parseable(config, registry, processor, dlt_metrics, shutdown_handle)
+                    .await?;
             }
         }
     }
@@ -66,10 +89,11 @@ async fn run_kafka2parseable<P>(
     config: KafkaConfig,
     registry: Registry,
     processor: P,
+    dlt_metrics: DltMetrics,
     shutdown_handle: Shutdown,
 ) -> anyhow::Result<()>
 where
-    P: Processor<Vec<ConsumerRecord>, ()> + Send + Sync + 'static,
+    P: Processor<Arc<Batch>, ()> + Send + Sync + 'static,
 {
     info!("Initializing KafkaSink connector...");
 
@@ -87,9 +111,15 @@ where
     let kafka_streams = KafkaStreams::init(kafka_context, stream_state, shutdown_handle.clone())?;
 
     let stats = kafka_streams.statistics();
-    registry.register(Box::new(KafkaMetricsCollector::new(stats)?))?;
-
-    let kafka_parseable_sink_connector = KafkaSinkConnector::new(kafka_streams, processor);
+    let offset_metrics = OffsetMetrics::new()?;
+    registry.register(Box::new(KafkaMetricsCollector::new(
+        stats,
+        dlt_metrics,
+        offset_metrics.clone(),
+    )?))?;
+
+    let kafka_parseable_sink_connector =
+        KafkaSinkConnector::new(kafka_streams, processor, offset_metrics);
 
     rebalance_listener.start();
     kafka_parseable_sink_connector.run().await?;
@@ -0,0 +1,328 @@
+/*
+ * Parseable Server (C) 2022 - 2025 Parseable, Inc.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ */
+
+//! Per-stream deduplication of events on ingestion, for at-least-once shippers that
+//! deliver the same event more than once
+
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use arrow::compute::filter_record_batch;
+use arrow::row::{RowConverter, SortField};
+use arrow_array::{ArrayRef, BooleanArray, RecordBatch};
+use arrow_schema::ArrowError;
+use serde::{Deserialize, Serialize};
+use xxhash_rust::xxh3::Xxh3;
+
+use crate::parseable::FlushAck;
+
+const DEFAULT_MAX_KEYS: usize = 1_000_000;
+
+#[derive(Debug, thiserror::Error)]
+#[error("{0}")]
+pub struct Error(&'static str);
+
+/// The dedup option of a stream, stored in `stream.json`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Dedup {
+    /// Fields identifying an event. If empty, events are identified by their content, i.e.
+    /// all of their fields except those added by Parseable, prefixed with `p_`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<String>,
+    /// Duplicates are dropped for this many seconds after an event was first seen
+    pub window_secs: u64,
+    /// Maximum number of keys remembered, the oldest are forgotten first once reached
+    #[serde(default = "default_max_keys")]
+    pub max_keys: usize,
+}
+
+fn default_max_keys() -> usize {
+    DEFAULT_MAX_KEYS
+}
+
+impl Dedup {
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.window_secs == 0 {
+            return Err(Error("window_secs should be greater than 0"));
+        }
+        if self.max_keys == 0 {
+            return Err(Error("max_keys should be greater than 0"));
+        }
+        if self.fields.iter().any(|field| field.is_empty()) {
+            return Err(Error("field names can't be empty"));
+        }
+
+        Ok(())
+    }
+
+    /// Columns of the batch that make up the key, in order of name
+    fn key_columns(&self, rb: &RecordBatch) -> Vec<(String, ArrayRef)> {
+        let schema = rb.schema();
+        let mut columns: Vec<(String, ArrayRef)> = schema
+            .fields()
+            .iter()
+            .zip(rb.columns())
+            .filter(|(field, _)| {
+                if self.fields.is_empty() {
+                    !field.name().starts_with("p_")
+                } else {
+                    self.fields.contains(field.name())
+                }
+            })
+            .map(|(field, column)| (field.name().clone(), column.clone()))
+            .collect();
+        columns.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        columns
+    }
+}
+
+/// Hashes of the keys seen within the dedup window of a stream, kept in memory
+#[derive(Debug, Default)]
+pub struct DedupIndex {
+    seen: HashMap<u128, i64>,
+    // keys in order of insertion with the time they were seen, for eviction
+    order: VecDeque<(u128, i64)>,
+    // keys of the events staged in files not finished yet, forgotten if the file can't be
+    pending: Vec<(FlushAck, Vec<u128>)>,
+}
+
+impl DedupIndex {
+    /// Drops the rows of `rb` whose key was seen within the window before the unix time `now`
+    /// in seconds, or earlier in the batch. Returns the remaining rows and their keys, to be
+    /// [inserted](Self::insert) once the rows are staged.
+    pub fn deduplicate(
+        &mut self,
+        dedup: &Dedup,
+        rb: RecordBatch,
+        now: i64,
+    ) -> Result<(RecordBatch, Vec<u128>), ArrowError> {
+        self.forget_unflushed();
+        let window = i64::try_from(dedup.window_secs).unwrap_or(i64::MAX);
+        self.evict(now.saturating_sub(window), dedup.max_keys);
+
+        let columns = dedup.key_columns(&rb);
+        // none of the key fields are present, events can't be told apart
+        if columns.is_empty() {
+            return Ok((rb, vec![]));
+        }
+
+        // the key of a row is made of its non null key fields only, such that events are
+        // identified the same regardless of the other events of the batch
+        let columns = columns
+            .into_iter()
+            .map(|(name, column)| {
+                let converter =
+                    RowConverter::new(vec![SortField::new(column.data_type().clone())])?;
+                let rows = converter.convert_columns(std::slice::from_ref(&column))?;
+                Ok((name, column.logical_nulls(), rows))
+            })
+            .collect::<Result<Vec<_>, ArrowError>>()?;
+
+        let mut keys = HashSet::new();
+        let keep: BooleanArray = (0..rb.num_rows())
+            .map(|i| {
+                let mut hasher = Xxh3::new();
+                let mut is_empty = true;
+                for (name, nulls, rows) in &columns {
+                    if nulls.as_ref().is_some_and(|nulls| nulls.is_null(i)) {
+                        continue;
+                    }
+                    hasher.update(name.as_bytes());
+                    hasher.update(&[0]);
+                    hasher.update(rows.row(i).as_ref());
+                    is_empty = false;
+                }
+                // events without any of the key fields can't be told apart
+                if is_empty {
+                    return Some(true);
+                }
+                let key = hasher.digest128();
+                Some(!self.seen.contains_key(&key) && keys.insert(key))
+            })
+            .collect();
+        let keys = keys.into_iter().collect();
+        if keep.false_count() == 0 {
+            return Ok((rb, keys));
+        }
+
+        Ok((filter_record_batch(&rb, &keep)?, keys))
+    }
+
+    /// Remembers the keys of staged rows from the unix time `now` in seconds, until the
+    /// window elapses or `ack` reports that the rows couldn't be made durable
+    pub fn insert(&mut self, dedup: &Dedup, keys: Vec<u128>, now: i64, ack: &FlushAck) {
+        for &key in &keys {
+            if self.seen.len() >= dedup.max_keys {
+                self.evict(i64::MIN, dedup.max_keys - 1);
+            }
+            if self.seen.insert(key, now).is_none() {
+                self.order.push_back((key, now));
+            }
+        }
+        if ack.status().is_none() {
+            self.pending.push((ack.clone(), keys));
+        }
+    }
+
+    /// Forgets the keys of rows whose staging file couldn't be finished, such that the
+    /// events are staged again when retried
+    fn forget_unflushed(&mut self) {
+        let seen = &mut self.seen;
+        self.pending.retain(|(ack, keys)| match ack.status() {
+            None => true,
+            Some(true) => false,
+            Some(false) => {
+                for key in keys {
+                    seen.remove(key);
+                }
+                false
+            }
+        });
+    }
+
+    /// Forgets keys seen at or before `expired`, and the oldest ones beyond `max_keys`
+    fn evict(&mut self, expired: i64, max_keys: usize) {
+        while let Some(&(key, seen_at)) = self.order.front() {
+            if seen_at > expired && self.seen.len() <= max_keys {
+                break;
+            }
+            self.order.pop_front();
+            // the key may have been forgotten and seen again since
+            if self.seen.get(&key) == Some(&seen_at) {
+                self.seen.remove(&key);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use arrow_array::{Int64Array, StringArray};
+    use arrow_schema::{DataType, Field, Schema};
+
+    use super::*;
+
+    fn batch(ids: &[i64], messages: &[&str]) -> RecordBatch {
+        let schema = Schema::new(vec![
+            Field::new("id", DataType::Int64, true),
+            Field::new("message", DataType::Utf8, true),
+            Field::new("p_user_agent", DataType::Utf8, true),
+        ]);
+        RecordBatch::try_new(
+            Arc::new(schema),
+            vec![
+                Arc::new(Int64Array::from(ids.to_vec())),
+                Arc::new(StringArray::from(messages.to_vec())),
+                Arc::new(StringArray::from(vec!["fluent-bit"; ids.len()])),
+            ],
+        )
+        .unwrap()
+    }
+
+    /// Deduplicates `rb` and remembers the keys of the rows kept, as once staged.
+    /// Returns the number of rows kept and dropped.
+    fn stage(index: &mut DedupIndex, dedup: &Dedup, rb: RecordBatch, now: i64) -> (usize, usize) {
+        let num_rows = rb.num_rows();
+        let (rb, keys) = index.deduplicate(dedup, rb, now).unwrap();
+        index.insert(dedup, keys, now, &FlushAck::done());
+
+        (rb.num_rows(), num_rows - rb.num_rows())
+    }
+
+    #[test]
+    fn drop_duplicates_within_window() {
+        let by_id = Dedup {
+            fields: vec!["id".to_owned()],
+            window_secs: 60,
+            max_keys: 100,
+        };
+        let mut index = DedupIndex::default();
+        let staged = stage(&mut index, &by_id, batch(&[1, 2, 1], &["a", "b", "c"]), 0);
+        assert_eq!(staged, (2, 1));
+        let (_, dropped) = stage(&mut index, &by_id, batch(&[2, 3], &["b", "d"]), 30);
+        assert_eq!(dropped, 1);
+        // id 1 was first seen more than a window ago
+        let (_, dropped) = stage(&mut index, &by_id, batch(&[1, 3], &["a", "d"]), 75);
+        assert_eq!(dropped, 1);
+
+        let by_content = Dedup {
+            fields: vec![],
+            window_secs: 60,
+            max_keys: 2,
+        };
+        let mut index = DedupIndex::default();
+        let rb = batch(&[1, 1, 2, 3], &["a", "a", "a", "a"]);
+        let (_, dropped) = stage(&mut index, &by_content, rb, 0);
+        assert_eq!(dropped, 1);
+        // only the 2 most recent keys are remembered
+        let (_, dropped) = stage(&mut index, &by_content, batch(&[1, 3], &["a", "a"]), 1);
+        assert_eq!(dropped, 1);
+        assert_eq!(index.seen.len(), 2);
+    }
+
+    #[test]
+    fn keys_remembered_once_staged() {
+        let by_id = Dedup {
+            fields: vec!["id".to_owned()],
+            window_secs: 60,
+            max_keys: 100,
+        };
+        let mut index = DedupIndex::default();
+        // the rows weren't staged, e.g. the push failed, a retry keeps them
+        let (rb, _) = index
+            .deduplicate(&by_id, batch(&[1, 2], &["a", "b"]), 0)
+            .unwrap();
+        assert_eq!(rb.num_rows(), 2);
+        assert_eq!(
+            stage(&mut index, &by_id, batch(&[1, 2], &["a", "b"]), 1),
+            (2, 0)
+        );
+        assert_eq!(
+            stage(&mut index, &by_id, batch(&[1, 2], &["a", "b"]), 2),
+            (0, 2)
+        );
+    }
+
+    #[test]
+    fn null_fields_are_not_part_of_the_key() {
+        let by_content = Dedup {
+            fields: vec![],
+            window_secs: 60,
+            max_keys: 100,
+        };
+        let mut index = DedupIndex::default();
+        stage(&mut index, &by_content, batch(&[1], &["a"]), 0);
+
+        // the same event, in a batch with a field it doesn't have
+        let rb = batch(&[1, 2], &["a", "b"]);
+        let (mut fields, mut columns): (Vec<_>, Vec<_>) = rb
+            .schema()
+            .fields()
+            .iter()
+            .cloned()
+            .zip(rb.columns().iter().cloned())
+            .unzip();
+        fields.push(Arc::new(Field::new("level", DataType::Utf8, true)));
+        columns.push(Arc::new(StringArray::from(vec![None, Some("info")])));
+        let rb = RecordBatch::try_new(Arc::new(Schema::new(fields)), columns).unwrap();
+
+        assert_eq!(stage(&mut index, &by_content, rb, 1), (1, 1));
+    }
+}
@@ -0,0 +1,378 @@
+/*
+ * Parseable Server (C) 2022 - 2025 Parseable, Inc.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ *
+ */
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use anyhow::anyhow;
+use arrow::compute::{can_cast_types, cast};
+use arrow_array::{ArrayRef, RecordBatch};
+use arrow_schema::{DataType, Field, Fields, Schema, TimeUnit};
+use chrono::{DateTime, Utc};
+use itertools::Itertools;
+
+use super::EventFormat;
+use crate::{handlers::TelemetryType, metadata::SchemaVersion, storage::StreamType};
+
+/// Arrow record batches received as is, e.g. over Arrow Flight `do_put`
+pub struct Event {
+    pub rb: RecordBatch,
+    pub p_timestamp: DateTime<Utc>,
+}
+
+impl Event {
+    pub fn new(rb: RecordBatch, p_timestamp: DateTime<Utc>) -> Self {
+        Self { rb, p_timestamp }
+    }
+}
+
+impl EventFormat for Event {
+    type Data = RecordBatch;
+
+    /// Returns the time at ingestion, i.e. the `p_timestamp` value
+    fn get_p_timestamp(&self) -> DateTime<Utc> {
+        self.p_timestamp
+    }
+
+    // normalize the column names of the incoming batch and derive the schema
+    // it should be stored with, based on the existing schema of the stream
+    fn to_data(
+        self,
+        schema: &HashMap<String, Arc<Field>>,
+        time_partition: Option<&String>,
+        schema_version: SchemaVersion,
+        static_schema_flag: bool,
+    ) -> Result<(Self::Data, Vec<Arc<Field>>, bool), anyhow::Error> {
+        let stream_schema = schema;
+
+        // Rename columns starting with '@' to '_' to match the schema
+        // Reject event if renaming would cause a column name collision
+        let rb = rename_columns(self.rb)?;
+        let incoming_schema = rb.schema();
+
+        let mut is_first = false;
+        let schema = match derive_arrow_schema(stream_schema, incoming_schema.fields()) {
+            Some(schema) => schema,
+            None => {
+                let infer_schema = super::update_field_type_in_schema(
+                    override_data_type(incoming_schema.clone(), schema_version),
+                    Some(stream_schema),
+                    time_partition,
+                    None,
+                    schema_version,
+                );
+                Schema::try_merge(vec![
+                    Schema::new(stream_schema.values().cloned().collect::<Fields>()),
+                    infer_schema.as_ref().clone(),
+                ]).map_err(|err| anyhow!("Could not merge schema of this event with that of the existing stream. {:?}", err))?;
+                is_first = true;
+                infer_schema
+                    .fields
+                    .iter()
+                    .filter(|field| !field.data_type().is_null())
+                    .cloned()
+                    .sorted_by(|a, b| a.name().cmp(b.name()))
+                    .collect()
+            }
+        };
+
+        if fields_mismatch(&schema, &incoming_schema, static_schema_flag) {
+            return Err(anyhow!(
+                "Could not process this event due to mismatch in datatype"
+            ));
+        }
+
+        Ok((rb, schema, is_first))
+    }
+
+    // Cast the columns of the incoming batch to the derived schema
+    fn decode(data: Self::Data, schema: Arc<Schema>) -> Result<RecordBatch, anyhow::Error> {
+        let columns = schema
+            .fields()
+            .iter()
+            .map(|field| {
+                let column = data
+                    .column_by_name(field.name())
+                    .ok_or_else(|| anyhow!("Column {} not found in record batch", field.name()))?;
+                if column.data_type() == field.data_type() {
+                    Ok(column.clone())
+                } else {
+                    cast(column, field.data_type()).map_err(|err| {
+                        anyhow!("Failed to cast column {} due to {:?}", field.name(), err)
+                    })
+                }
+            })
+            .collect::<Result<Vec<ArrayRef>, anyhow::Error>>()?;
+
+        Ok(RecordBatch::try_new(schema, columns)?)
+    }
+
+    /// Converts an Arrow record batch into a Parseable Event
+    fn into_event(
+        self,
+        stream_name: String,
+        origin_size: u64,
+        storage_schema: &HashMap<String, Arc<Field>>,
+        static_schema_flag: bool,
+        custom_partitions: Option<&String>,
+        time_partition: Option<&String>,
+        schema_version: SchemaVersion,
+        stream_type: StreamType,
+        p_custom_fields: &HashMap<String, String>,
+        telemetry_type: TelemetryType,
+    ) -> Result<super::Event, anyhow::Error> {
+        // a batch may carry several partition values, which staging expects split per event
+        if custom_partitions.is_some() {
+            return Err(anyhow!(
+                "Arrow ingestion is not supported for streams with custom partition"
+            ));
+        }
+        if time_partition.is_some() {
+            return Err(anyhow!(
+                "Arrow ingestion is not supported for streams with time partition"
+            ));
+        }
+
+        let parsed_timestamp = self.p_timestamp.naive_utc();
+        let (rb, is_first_event) = self.into_recordbatch(
+            storage_schema,
+            static_schema_flag,
+            time_partition,
+            schema_version,
+            p_custom_fields,
+        )?;
+
+        Ok(super::Event {
+            rb,
+            stream_name,
+            // stats are tracked under the json format, whatever the source
+            origin_format: "json",
+            origin_size,
+            is_first_event,
+            parsed_timestamp,
+            time_partition: None,
+            custom_partition_values: HashMap::new(),
+            stream_type,
+            telemetry_type,
+        })
+    }
+}
+
+/// Renames columns to match the schema transformation using normalize_field_name.
+/// Returns an error if renaming would cause a column name collision.
+fn rename_columns(rb: RecordBatch) -> Result<RecordBatch, anyhow::Error> {
+    let schema = rb.schema();
+    if !schema
+        .fields()
+        .iter()
+        .any(|field| field.name().starts_with('@'))
+    {
+        return Ok(rb);
+    }
+
+    let original_names: HashSet<&String> = schema.fields().iter().map(|f| f.name()).collect();
+    let mut fields = Vec::with_capacity(schema.fields().len());
+    for field in schema.fields() {
+        let mut name = field.name().clone();
+        super::normalize_field_name(&mut name);
+        if &name != field.name() && original_names.contains(&name) {
+            return Err(anyhow!(
+                "Key collision detected: '{}' and '{}' would both map to '{}'",
+                field.name(),
+                name,
+                name
+            ));
+        }
+        fields.push(field.as_ref().clone().with_name(name));
+    }
+
+    Ok(RecordBatch::try_new(
+        Arc::new(Schema::new(fields)),
+        rb.columns().to_vec(),
+    )?)
+}
+
+// Returns the stream's fields for the columns of the batch,
+// None if any of the columns is not yet part of the stream schema
+fn derive_arrow_schema(
+    schema: &HashMap<String, Arc<Field>>,
+    fields: &Fields,
+) -> Option<Vec<Arc<Field>>> {
+    fields
+        .iter()
+        .map(|field| schema.get(field.name()).cloned())
+        .collect()
+}
+
+// From Schema v1 onwards all numbers are stored as float64, timestamps
+// are always stored with millisecond precision and without timezone
+fn override_data_type(inferred_schema: Arc<Schema>, schema_version: SchemaVersion) -> Arc<Schema> {
+    let updated_schema: Vec<Field> = inferred_schema
+        .fields()
+        .iter()
+        .map(|field| match field.data_type() {
+            DataType::Timestamp(_, _) => Field::new(
+                field.name(),
+                DataType::Timestamp(TimeUnit::Millisecond, None),
+                true,
+            ),
+            data_type if schema_version == SchemaVersion::V1 && data_type.is_numeric() => {
+                Field::new(field.name(), DataType::Float64, true)
+            }
+            data_type => Field::new(field.name(), data_type.clone(), true),
+        })
+        .collect();
+
+    Arc::new(Schema::new(updated_schema))
+}
+
+fn fields_mismatch(schema: &[Arc<Field>], incoming: &Schema, static_schema_flag: bool) -> bool {
+    for field in incoming.fields() {
+        if field.data_type().is_null() {
+            continue;
+        }
+        let Some(expected) = schema.iter().find(|f| f.name() == field.name()) else {
+            return true;
+        };
+        let valid = if static_schema_flag {
+            field.data_type() == expected.data_type()
+        } else {
+            can_cast_types(field.data_type(), expected.data_type())
+        };
+        if !valid {
+            return true;
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow_array::{Float64Array, Int64Array, StringArray};
+
+    use super::*;
+
+    fn batch() -> RecordBatch {
+        RecordBatch::try_new(
+            Arc::new(Schema::new(vec![
+                Field::new("a", DataType::Int64, true),
+                Field::new("@b", DataType::Utf8, true),
+            ])),
+            vec![
+                Arc::new(Int64Array::from(vec![1, 2])),
+                Arc::new(StringArray::from(vec!["x", "y"])),
+            ],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn batch_into_recordbatch_inferred_schema() {
+        let (rb, is_first) = Event::new(batch(), Utc::now())
+            .into_recordbatch(
+                &HashMap::new(),
+                false,
+                None,
+                SchemaVersion::V1,
+                &HashMap::new(),
+            )
+            .unwrap();
+
+        assert!(is_first);
+        assert_eq!(rb.num_rows(), 2);
+        assert_eq!(
+            rb.column_by_name("a")
+                .unwrap()
+                .as_any()
+                .downcast_ref::<Float64Array>()
+                .unwrap(),
+            &Float64Array::from(vec![1.0, 2.0])
+        );
+        assert!(rb.column_by_name("_b").is_some());
+        assert!(rb.column_by_name("p_timestamp").is_some());
+    }
+
+    #[test]
+    fn batch_with_existing_schema_is_cast() {
+        let schema = HashMap::from([
+            (
+                "a".to_owned(),
+                Arc::new(Field::new("a", DataType::Float64, true)),
+            ),
+            (
+                "_b".to_owned(),
+                Arc::new(Field::new("_b", DataType::Utf8, true)),
+            ),
+        ]);
+
+        let (rb, is_first) = Event::new(batch(), Utc::now())
+            .into_recordbatch(&schema, false, None, SchemaVersion::V0, &HashMap::new())
+            .unwrap();
+
+        assert!(!is_first);
+        assert_eq!(
+            rb.column_by_name("a").unwrap().data_type(),
+            &DataType::Float64
+        );
+    }
+
+    #[test]
+    fn batch_schema_mismatch_with_static_schema() {
+        let schema = HashMap::from([
+            (
+                "a".to_owned(),
+                Arc::new(Field::new("a", DataType::Utf8, true)),
+            ),
+            (
+                "_b".to_owned(),
+                Arc::new(Field::new("_b", DataType::Utf8, true)),
+            ),
+        ]);
+
+        assert!(
+            Event::new(batch(), Utc::now())
+                .into_recordbatch(&schema, true, None, SchemaVersion::V0, &HashMap::new())
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn batch_for_time_partitioned_stream_is_rejected() {
+        let time_partition = "a".to_owned();
+
+        assert!(
+            Event::new(batch(), Utc::now())
+                .into_event(
+                    "stream".to_owned(),
+                    0,
+                    &HashMap::new(),
+                    false,
+                    None,
+                    Some(&time_partition),
+                    SchemaVersion::V1,
+                    StreamType::UserDefined,
+                    &HashMap::new(),
+                    TelemetryType::Logs,
+                )
+                .is_err()
+        );
+    }
+}
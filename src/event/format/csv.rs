@@ -0,0 +1,464 @@
+/*
+ * Parseable Server (C) 2022 - 2025 Parseable, Inc.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ *
+ */
+
+use std::{collections::HashMap, sync::Arc};
+
+use anyhow::anyhow;
+use arrow_array::{
+    ArrayRef, BooleanArray, Float64Array, Int64Array, NullArray, RecordBatch, RecordBatchOptions,
+    StringArray,
+};
+use arrow_schema::{DataType, Field, Schema};
+use chrono::{DateTime, Utc};
+use csv::{ReaderBuilder, StringRecord};
+use serde_json::{Map, Value};
+
+use super::{ColumnType, EventFormat, arrow};
+use crate::{handlers::TelemetryType, metadata::SchemaVersion, storage::StreamType};
+
+/// Options used to read a CSV/TSV payload
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CsvOptions {
+    pub delimiter: u8,
+    pub quote: u8,
+    /// When false, columns are named `column_1`, `column_2`, ...
+    pub has_header: bool,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        Self {
+            delimiter: b',',
+            quote: b'"',
+            has_header: true,
+        }
+    }
+}
+
+impl CsvOptions {
+    pub fn tsv() -> Self {
+        Self {
+            delimiter: b'\t',
+            ..Default::default()
+        }
+    }
+}
+
+/// Rows of a CSV/TSV payload, with the type of each column inferred from its non empty
+/// cells, `None` if all of them are empty
+struct Table {
+    headers: Vec<String>,
+    column_types: Vec<Option<ColumnType>>,
+    records: Vec<StringRecord>,
+}
+
+fn read(body: &[u8], options: &CsvOptions) -> Result<Table, csv::Error> {
+    let mut reader = ReaderBuilder::new()
+        .delimiter(options.delimiter)
+        .quote(options.quote)
+        .has_headers(options.has_header)
+        .from_reader(body);
+
+    let records = reader.records().collect::<Result<Vec<StringRecord>, _>>()?;
+    let headers: Vec<String> = if options.has_header {
+        reader.headers()?.iter().map(str::to_owned).collect()
+    } else {
+        let columns = records.first().map_or(0, StringRecord::len);
+        (1..=columns).map(|i| format!("column_{i}")).collect()
+    };
+
+    let mut column_types: Vec<Option<ColumnType>> = vec![None; headers.len()];
+    for record in &records {
+        for (column_type, value) in column_types.iter_mut().zip(record.iter()) {
+            if value.is_empty() {
+                continue;
+            }
+            let value_type = ColumnType::of(value);
+            *column_type = Some(column_type.map_or(value_type, |t| t.merge(value_type)));
+        }
+    }
+
+    Ok(Table {
+        headers,
+        column_types,
+        records,
+    })
+}
+
+/// Parses a CSV/TSV payload into a list of flat json objects, one per row, for the streams
+/// whose events are transformed as json. Empty cells are left out of the object, such that
+/// they end up as null.
+pub fn parse(body: &[u8], options: &CsvOptions) -> Result<Vec<Value>, csv::Error> {
+    let Table {
+        headers,
+        column_types,
+        records,
+    } = read(body, options)?;
+
+    let rows = records
+        .iter()
+        .map(|record| {
+            let row: Map<String, Value> = headers
+                .iter()
+                .zip(column_types.iter())
+                .zip(record.iter())
+                .filter(|(_, value)| !value.is_empty())
+                .map(|((name, column_type), value)| {
+                    let column_type = column_type.unwrap_or(ColumnType::Utf8);
+                    (name.clone(), column_type.to_value(value))
+                })
+                .collect();
+            Value::Object(row)
+        })
+        .collect();
+
+    Ok(rows)
+}
+
+/// Rows of a CSV/TSV payload decoded into a record batch, each column typed as inferred
+/// from its cells. The schema is then derived as that of json events, new columns of
+/// timestamps being recognised from their first value.
+pub struct Event {
+    pub rb: RecordBatch,
+    /// The first non empty value of each column
+    sample: Map<String, Value>,
+    pub p_timestamp: DateTime<Utc>,
+}
+
+impl Event {
+    pub fn new(
+        body: &[u8],
+        options: &CsvOptions,
+        p_timestamp: DateTime<Utc>,
+    ) -> Result<Self, csv::Error> {
+        let Table {
+            headers,
+            column_types,
+            records,
+        } = read(body, options)?;
+
+        let mut fields = Vec::with_capacity(headers.len());
+        let mut columns = Vec::with_capacity(headers.len());
+        let mut sample = Map::new();
+        for (i, (name, column_type)) in headers.into_iter().zip(column_types).enumerate() {
+            let cells = records
+                .iter()
+                .map(|record| record.get(i).filter(|value| !value.is_empty()));
+            let column: ArrayRef = match column_type {
+                None => Arc::new(NullArray::new(records.len())),
+                Some(ColumnType::Int) => Arc::new(
+                    cells
+                        .map(|cell| cell.and_then(|value| value.parse::<i64>().ok()))
+                        .collect::<Int64Array>(),
+                ),
+                Some(ColumnType::Float) => Arc::new(
+                    cells
+                        .map(|cell| cell.and_then(|value| value.parse::<f64>().ok()))
+                        .collect::<Float64Array>(),
+                ),
+                Some(ColumnType::Bool) => Arc::new(
+                    cells
+                        .map(|cell| cell.map(|value| value == "true"))
+                        .collect::<BooleanArray>(),
+                ),
+                Some(ColumnType::Utf8) => Arc::new(cells.collect::<StringArray>()),
+            };
+            if let Some(column_type) = column_type
+                && let Some(value) = records
+                    .iter()
+                    .filter_map(|record| record.get(i))
+                    .find(|value| !value.is_empty())
+            {
+                sample.insert(name.clone(), column_type.to_value(value));
+            }
+            fields.push(Field::new(name, column.data_type().clone(), true));
+            columns.push(column);
+        }
+        let rb = RecordBatch::try_new_with_options(
+            Arc::new(Schema::new(fields)),
+            columns,
+            &RecordBatchOptions::new().with_row_count(Some(records.len())),
+        )
+        .expect("columns are built from the fields, with a value per row");
+
+        Ok(Self {
+            rb,
+            sample,
+            p_timestamp,
+        })
+    }
+}
+
+impl EventFormat for Event {
+    type Data = RecordBatch;
+
+    /// Returns the time at ingestion, i.e. the `p_timestamp` value
+    fn get_p_timestamp(&self) -> DateTime<Utc> {
+        self.p_timestamp
+    }
+
+    // derive the schema of the rows as that of a batch of arrow, cells being text, a column
+    // is only cast to the type of the stream's field if its cells can be parsed as such
+    fn to_data(
+        self,
+        schema: &HashMap<String, Arc<Field>>,
+        time_partition: Option<&String>,
+        schema_version: SchemaVersion,
+        _static_schema_flag: bool,
+    ) -> Result<(Self::Data, Vec<Arc<Field>>, bool), anyhow::Error> {
+        let stream_schema = schema;
+        if self.rb.schema().fields().iter().any(|field| {
+            stream_schema
+                .get(field.name())
+                .is_some_and(|expected| !valid_type(field.data_type(), expected.data_type()))
+        }) {
+            return Err(anyhow!(
+                "Could not process this event due to mismatch in datatype"
+            ));
+        }
+
+        // static schemas are checked for new fields when the batch is turned into an event,
+        // the types of the cells being inferred, those of known fields were checked above
+        let (rb, fields, is_first) = arrow::Event::new(self.rb, self.p_timestamp).to_data(
+            stream_schema,
+            time_partition,
+            schema_version,
+            false,
+        )?;
+        if !is_first {
+            return Ok((rb, fields, is_first));
+        }
+
+        // new columns are typed from their values as json fields are, e.g. timestamps
+        let inferred = super::override_data_type(
+            Arc::new(Schema::new(fields.clone())),
+            Value::Object(self.sample),
+            schema_version,
+        );
+        let fields = fields
+            .into_iter()
+            .zip(inferred.fields().iter())
+            .map(|(field, inferred)| {
+                if stream_schema.contains_key(field.name()) {
+                    field
+                } else {
+                    inferred.clone()
+                }
+            })
+            .collect();
+
+        Ok((rb, fields, is_first))
+    }
+
+    // Cast the columns of the rows to the derived schema
+    fn decode(data: Self::Data, schema: Arc<Schema>) -> Result<RecordBatch, anyhow::Error> {
+        arrow::Event::decode(data, schema)
+    }
+
+    /// Converts the rows of a CSV/TSV payload into a Parseable Event
+    fn into_event(
+        self,
+        stream_name: String,
+        origin_size: u64,
+        storage_schema: &HashMap<String, Arc<Field>>,
+        static_schema_flag: bool,
+        custom_partitions: Option<&String>,
+        time_partition: Option<&String>,
+        schema_version: SchemaVersion,
+        stream_type: StreamType,
+        p_custom_fields: &HashMap<String, String>,
+        telemetry_type: TelemetryType,
+    ) -> Result<super::Event, anyhow::Error> {
+        // rows are split per partition value on the json path
+        if custom_partitions.is_some() || time_partition.is_some() {
+            return Err(anyhow!(
+                "CSV rows of partitioned streams are to be ingested as json events"
+            ));
+        }
+
+        let parsed_timestamp = self.p_timestamp.naive_utc();
+        let (rb, is_first_event) = self.into_recordbatch(
+            storage_schema,
+            static_schema_flag,
+            time_partition,
+            schema_version,
+            p_custom_fields,
+        )?;
+
+        Ok(super::Event {
+            rb,
+            stream_name,
+            origin_format: "csv",
+            origin_size,
+            is_first_event,
+            parsed_timestamp,
+            time_partition: None,
+            custom_partition_values: HashMap::new(),
+            stream_type,
+            telemetry_type,
+        })
+    }
+}
+
+/// Whether a column of cells inferred as `inferred` can be stored as `expected`
+fn valid_type(inferred: &DataType, expected: &DataType) -> bool {
+    match (inferred, expected) {
+        (DataType::Null, _) => true,
+        (inferred, expected) if inferred == expected => true,
+        (DataType::Int64, DataType::Float64) => true,
+        (DataType::Utf8, DataType::Timestamp(_, _)) => true,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow_array::{Float64Array, StringArray};
+    use arrow_schema::TimeUnit;
+    use chrono::Utc;
+    use serde_json::json;
+
+    use super::*;
+    use crate::event::format::json;
+
+    #[test]
+    fn parse_with_header() {
+        let body = b"name,count,ratio,ok\n\"a, b\",1,0.5,true\nc,2,,false\n";
+
+        let rows = parse(body, &CsvOptions::default()).unwrap();
+
+        assert_eq!(
+            rows,
+            vec![
+                json!({"name": "a, b", "count": 1, "ratio": 0.5, "ok": true}),
+                json!({"name": "c", "count": 2, "ok": false}),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_tsv_without_header() {
+        let options = CsvOptions {
+            has_header: false,
+            ..CsvOptions::tsv()
+        };
+
+        let rows = parse(b"a\t1\nb\tx\n", &options).unwrap();
+
+        assert_eq!(
+            rows,
+            vec![
+                json!({"column_1": "a", "column_2": "1"}),
+                json!({"column_1": "b", "column_2": "x"}),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_rejects_uneven_rows() {
+        assert!(parse(b"a,b\n1,2,3\n", &CsvOptions::default()).is_err());
+    }
+
+    #[test]
+    fn event_typed_like_json() {
+        let body = b"timestamp,value,host,empty\n2025-05-15T15:30:00Z,10,a,\n";
+        let event = Event::new(body, &CsvOptions::default(), Utc::now()).unwrap();
+
+        let (rb, is_first) = event
+            .into_recordbatch(
+                &HashMap::new(),
+                false,
+                None,
+                SchemaVersion::V1,
+                &HashMap::new(),
+            )
+            .unwrap();
+
+        assert!(is_first);
+        assert_eq!(
+            rb.column_by_name("timestamp").unwrap().data_type(),
+            &DataType::Timestamp(TimeUnit::Millisecond, None)
+        );
+        assert_eq!(
+            rb.column_by_name("value")
+                .unwrap()
+                .as_any()
+                .downcast_ref::<Float64Array>()
+                .unwrap(),
+            &Float64Array::from(vec![10.0])
+        );
+        assert_eq!(
+            rb.column_by_name("host").unwrap().data_type(),
+            &DataType::Utf8
+        );
+        assert!(rb.column_by_name("empty").is_none());
+
+        // cells are only cast to the type of a known field if they can be parsed as such
+        let schema = HashMap::from([(
+            "value".to_owned(),
+            Arc::new(Field::new("value", DataType::Float64, true)),
+        )]);
+        let event = Event::new(b"value\nx\n", &CsvOptions::default(), Utc::now()).unwrap();
+        assert!(
+            event
+                .into_recordbatch(&schema, false, None, SchemaVersion::V1, &HashMap::new())
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn rows_are_typed_like_json() {
+        let rows = parse(
+            b"timestamp,value,host\n2025-05-15T15:30:00Z,10,a\n",
+            &CsvOptions::default(),
+        )
+        .unwrap();
+
+        let (rb, _) = json::Event::new(Value::Array(rows), Utc::now())
+            .into_recordbatch(
+                &HashMap::new(),
+                false,
+                None,
+                SchemaVersion::V1,
+                &HashMap::new(),
+            )
+            .unwrap();
+
+        assert_eq!(
+            rb.column_by_name("timestamp").unwrap().data_type(),
+            &DataType::Timestamp(TimeUnit::Millisecond, None)
+        );
+        assert_eq!(
+            rb.column_by_name("value")
+                .unwrap()
+                .as_any()
+                .downcast_ref::<Float64Array>()
+                .unwrap(),
+            &Float64Array::from(vec![10.0])
+        );
+        assert_eq!(
+            rb.column_by_name("host")
+                .unwrap()
+                .as_any()
+                .downcast_ref::<StringArray>()
+                .unwrap(),
+            &StringArray::from(vec!["a"])
+        );
+    }
+}
@@ -0,0 +1,202 @@
+/*
+ * Parseable Server (C) 2022 - 2025 Parseable, Inc.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ */
+
+//! Compiles Grok expressions, e.g. `%{IP:client} %{WORD:method}`, into regular expressions
+
+use std::collections::HashMap;
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use super::known_schema::FieldType;
+
+/// Standard Grok patterns bundled with the server
+const GROK_PATTERNS: &str = include_str!("../../../resources/grok-patterns");
+
+static BUNDLED_PATTERNS: Lazy<HashMap<&'static str, &'static str>> = Lazy::new(|| {
+    GROK_PATTERNS
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| line.split_once(' '))
+        .collect()
+});
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("unknown grok pattern '{0}'")]
+    UnknownPattern(String),
+    #[error("grok pattern '{0}' refers to itself")]
+    Recursive(String),
+    #[error("unterminated grok reference '%{{{0}'")]
+    Unterminated(String),
+    #[error("unsupported type '{1}' of field '{0}', expected int or float")]
+    UnsupportedType(String, String),
+    #[error("field '{0}' is captured more than once")]
+    DuplicateField(String),
+    #[error("{0}")]
+    Regex(#[from] regex::Error),
+}
+
+/// A Grok expression compiled to a regular expression
+#[derive(Debug)]
+pub struct Grok {
+    pub regex: Regex,
+    /// Fields captured by the expression in order of appearance, along with the type
+    /// given with `%{NAME:field:type}`
+    pub fields: Vec<(String, Option<FieldType>)>,
+}
+
+/// Compiles `expression`, references are resolved with `definitions` before the
+/// bundled patterns, such that a definition can also override a bundled pattern
+pub fn compile(expression: &str, definitions: &HashMap<String, String>) -> Result<Grok, Error> {
+    let mut compiler = Compiler {
+        definitions,
+        fields: vec![],
+        stack: vec![],
+    };
+    let mut regex = String::new();
+    compiler.expand(expression, &mut regex)?;
+
+    Ok(Grok {
+        regex: Regex::new(&regex)?,
+        fields: compiler.fields,
+    })
+}
+
+struct Compiler<'a> {
+    definitions: &'a HashMap<String, String>,
+    fields: Vec<(String, Option<FieldType>)>,
+    // patterns being expanded, to detect cycles
+    stack: Vec<&'a str>,
+}
+
+impl<'a> Compiler<'a> {
+    /// Appends `expression` to `regex`, with each `%{NAME}` replaced by a group of the
+    /// pattern's expansion, named after the field if one is given
+    fn expand(&mut self, expression: &'a str, regex: &mut String) -> Result<(), Error> {
+        let mut rest = expression;
+        while let Some(start) = rest.find("%{") {
+            regex.push_str(&rest[..start]);
+            let reference = &rest[start + 2..];
+            let Some(end) = reference.find('}') else {
+                return Err(Error::Unterminated(reference.to_owned()));
+            };
+            rest = &reference[end + 1..];
+
+            let mut parts = reference[..end].splitn(3, ':');
+            let name = parts.next().unwrap_or_default();
+            let field = parts.next().filter(|field| !field.is_empty());
+            let field_type = match parts.next() {
+                None => None,
+                Some("int") => Some(FieldType::Int),
+                Some("float") => Some(FieldType::Float),
+                Some(other) => {
+                    return Err(Error::UnsupportedType(
+                        field.unwrap_or_default().to_owned(),
+                        other.to_owned(),
+                    ));
+                }
+            };
+
+            let (name, definition) = match self.definitions.get_key_value(name) {
+                Some((name, definition)) => (name.as_str(), definition.as_str()),
+                None => BUNDLED_PATTERNS
+                    .get_key_value(name)
+                    .map(|(name, definition)| (*name, *definition))
+                    .ok_or_else(|| Error::UnknownPattern(name.to_owned()))?,
+            };
+            if self.stack.contains(&name) {
+                return Err(Error::Recursive(name.to_owned()));
+            }
+
+            match field {
+                Some(field) => {
+                    if self.fields.iter().any(|(captured, _)| captured == field) {
+                        return Err(Error::DuplicateField(field.to_owned()));
+                    }
+                    self.fields.push((field.to_owned(), field_type));
+                    regex.push_str(&format!("(?P<{field}>"));
+                }
+                None => regex.push_str("(?:"),
+            }
+            self.stack.push(name);
+            self.expand(definition, regex)?;
+            self.stack.pop();
+            regex.push(')');
+        }
+        regex.push_str(rest);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bundled_patterns_compile() {
+        for name in BUNDLED_PATTERNS.keys() {
+            let expression = format!("%{{{name}}}");
+            if let Err(err) = compile(&expression, &HashMap::new()) {
+                panic!("{name}: {err}");
+            }
+        }
+    }
+
+    #[test]
+    fn compile_with_definitions() {
+        let definitions = HashMap::from([
+            ("QUEUE".to_owned(), "[a-z]+-%{INT}".to_owned()),
+            ("LOOP".to_owned(), "%{LOOP}".to_owned()),
+        ]);
+        let grok = compile(
+            "^%{IP:client} %{QUEUE:queue} took %{NUMBER:duration:float}ms$",
+            &definitions,
+        )
+        .unwrap();
+        let captures = grok.regex.captures("10.0.0.1 orders-3 took 1.5ms").unwrap();
+        assert_eq!(&captures["client"], "10.0.0.1");
+        assert_eq!(&captures["queue"], "orders-3");
+        assert_eq!(
+            grok.fields,
+            vec![
+                ("client".to_owned(), None),
+                ("queue".to_owned(), None),
+                ("duration".to_owned(), Some(FieldType::Float)),
+            ]
+        );
+
+        for (expression, error) in [
+            ("%{LOOP}", "grok pattern 'LOOP' refers to itself"),
+            ("%{NOPE}", "unknown grok pattern 'NOPE'"),
+            ("%{IP:a} %{IP:a}", "field 'a' is captured more than once"),
+            (
+                "%{INT:a:bool}",
+                "unsupported type 'bool' of field 'a', expected int or float",
+            ),
+            ("%{INT", "unterminated grok reference '%{INT'"),
+        ] {
+            assert_eq!(
+                compile(expression, &definitions).unwrap_err().to_string(),
+                error
+            );
+        }
+    }
+}
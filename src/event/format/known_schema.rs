@@ -17,14 +17,24 @@
  */
 
 use std::collections::{HashMap, HashSet};
+use std::net::IpAddr;
+use std::sync::{Arc, RwLock};
 
+use arrow_schema::{DataType, Field};
+use chrono::{DateTime, NaiveDateTime, SecondsFormat};
 use once_cell::sync::Lazy;
 use regex::Regex;
-use serde::{Deserialize, Deserializer};
-use serde_json::{Map, Value};
+use relative_path::RelativePathBuf;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Number, Value};
 use tracing::error;
 
+use crate::LOCK_EXPECT;
 use crate::event::FORMAT_VERIFY_KEY;
+use crate::event::format::{LogSource, grok};
+use crate::metastore::metastore_traits::MetastoreObject;
+use crate::metrics::FORMAT_TYPE_MISMATCHES;
+use crate::storage::{LOG_FORMATS_DIR, SETTINGS_ROOT_DIRECTORY};
 
 /// Predefined JSON with known textual logging formats
 const FORMATS_JSON: &str = include_str!("../../../resources/formats.json");
@@ -43,36 +53,165 @@ pub enum Error {
         "Unsupported log format: '{0}'. This format cannot be parsed by the current version. Please create an issue on our GitHub repository (github.com/parseablehq/parseable) with a sample log event, or reach out in the #support-channel on our Slack community for assistance. Include this error message and your log sample to help us improve compatibility."
     )]
     Unknown(String),
+    #[error("Invalid log format: {0}")]
+    InvalidFormat(String),
 }
 
-/// Deserializes a string pattern into a compiled Regex
-/// NOTE: we only warn if the pattern doesn't compile
-pub fn deserialize_regex<'de, D>(deserializer: D) -> Result<Option<Regex>, D::Error>
-where
-    D: Deserializer<'de>,
-{
-    let pattern = String::deserialize(deserializer)?;
+/// Type of an extracted field, captures are kept as strings unless typed otherwise
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum FieldType {
+    String,
+    Int,
+    Float,
+    /// Parsed with the given `chrono` format string, e.g. `%d/%b/%Y:%H:%M:%S %z`, or
+    /// as RFC 3339/RFC 2822 without one, and stored as an RFC 3339 timestamp
+    Timestamp {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        format: Option<String>,
+    },
+    /// Validated as an IPv4 or IPv6 address
+    Ip,
+}
+
+impl FieldType {
+    /// Converts a captured value to its type, `None` if the value isn't of the type
+    pub fn to_value(&self, value: &str) -> Option<Value> {
+        match self {
+            FieldType::String => Some(Value::String(value.to_owned())),
+            FieldType::Int => value.parse::<i64>().ok().map(Value::from),
+            FieldType::Float => value
+                .parse::<f64>()
+                .ok()
+                .and_then(Number::from_f64)
+                .map(Value::Number),
+            FieldType::Timestamp { format } => {
+                let timestamp = match format {
+                    Some(format) => DateTime::parse_from_str(value, format)
+                        .map(|t| t.to_utc())
+                        .or_else(|_| {
+                            NaiveDateTime::parse_from_str(value, format).map(|t| t.and_utc())
+                        })
+                        .ok()?,
+                    None => DateTime::parse_from_rfc3339(value)
+                        .or_else(|_| DateTime::parse_from_rfc2822(value))
+                        .ok()?
+                        .to_utc(),
+                };
+                Some(Value::String(
+                    timestamp.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+                ))
+            }
+            FieldType::Ip => value
+                .parse::<IpAddr>()
+                .ok()
+                .map(|ip| Value::String(ip.to_string())),
+        }
+    }
+}
 
-    let regex = Regex::new(&pattern)
-        .inspect_err(|err| error!("Error compiling regex pattern: {err}; Pattern: {pattern}"))
-        .ok();
+/// A field of a pattern, either just its name or its name along with its type,
+/// e.g. `"sc_status"` or `{"name": "sc_status", "type": "int"}`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum FieldDefinition {
+    Name(String),
+    Typed {
+        name: String,
+        #[serde(flatten)]
+        field_type: FieldType,
+    },
+}
+
+/// Configuration for a single pattern within a log format, as found in JSON, given either
+/// as a regular expression with named capture groups or as a Grok expression, e.g.
+/// `%{IP:client} %{WORD:method}`, whose captures need not be listed in `fields`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PatternDefinition {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub grok: Option<String>,
+    /// Patterns the Grok expression can refer to in addition to the bundled ones
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub pattern_definitions: HashMap<String, String>,
+    #[serde(default)]
+    pub fields: Vec<FieldDefinition>,
+}
+
+impl PatternDefinition {
+    /// Compiles the pattern, the fields captured by a Grok expression are added to those
+    /// listed, with the types given in `fields` taking precedence
+    fn compile(&self) -> Result<Pattern, Error> {
+        let mut pattern = Pattern::from_fields(&self.fields);
+        match (&self.pattern, &self.grok) {
+            (Some(_), Some(_)) => {
+                return Err(Error::InvalidFormat(
+                    "only one of pattern or grok can be given".to_owned(),
+                ));
+            }
+            (Some(regex), None) => {
+                pattern.pattern =
+                    Some(Regex::new(regex).map_err(|err| Error::InvalidFormat(err.to_string()))?);
+            }
+            (None, Some(expression)) => {
+                let compiled = grok::compile(expression, &self.pattern_definitions)
+                    .map_err(|err| Error::InvalidFormat(err.to_string()))?;
+                for (field, field_type) in compiled.fields {
+                    if let Some(field_type) = field_type {
+                        pattern.types.entry(field.clone()).or_insert(field_type);
+                    }
+                    pattern.fields.insert(field);
+                }
+                pattern.pattern = Some(compiled.regex);
+            }
+            (None, None) => {}
+        }
 
-    Ok(regex)
+        Ok(pattern)
+    }
 }
 
 /// Configuration for a single pattern within a log format
-#[derive(Debug, Default, Deserialize)]
+#[derive(Debug, Default)]
 struct Pattern {
     /// Regular expression pattern used to match and capture fields from log strings
-    #[serde(deserialize_with = "deserialize_regex", default)]
     pattern: Option<Regex>,
     // Maps field names to regex capture groups
     fields: HashSet<String>,
+    // Types of the fields that aren't extracted as strings
+    types: HashMap<String, FieldType>,
+}
+
+impl Pattern {
+    fn from_fields(definitions: &[FieldDefinition]) -> Self {
+        let mut fields = HashSet::new();
+        let mut types = HashMap::new();
+        for field in definitions {
+            match field {
+                FieldDefinition::Name(name) => {
+                    fields.insert(name.clone());
+                }
+                FieldDefinition::Typed { name, field_type } => {
+                    fields.insert(name.clone());
+                    types.insert(name.clone(), field_type.clone());
+                }
+            }
+        }
+
+        Self {
+            pattern: None,
+            fields,
+            types,
+        }
+    }
 }
 
 /// Defines a schema for extracting structured data from logs using regular expressions
 #[derive(Debug, Default)]
 pub struct SchemaDefinition {
+    /// Name of the log format
+    format: String,
     patterns: Vec<Pattern>,
 }
 
@@ -82,9 +221,14 @@ impl SchemaDefinition {
     /// This function checks if the given object already contains all expected fields
     /// or attempts to extract them from a log event string if a pattern is available.
     ///
+    /// Typed fields are kept as captured where the stream already has them as strings, such
+    /// that streams created before their type was declared can still be ingested into. A value
+    /// that isn't of the declared type is extracted as null, and counted as a type mismatch.
+    ///
     /// # Arguments
     /// * `obj` - The JSON object to check or extract fields into
     /// * `extract_log` - Optional field name containing the raw log text
+    /// * `stream_schema` - Schema of the stream the object is ingested into, if any yet
     ///
     /// # Returns
     /// * `Some` - If all expected fields are already present in the object OR if extraction was successful
@@ -94,6 +238,7 @@ impl SchemaDefinition {
         &self,
         obj: &mut Map<String, Value>,
         extract_log: Option<&str>,
+        stream_schema: &HashMap<String, Arc<Field>>,
     ) -> Option<HashSet<String>> {
         if let Some(pattern) = self
             .patterns
@@ -118,12 +263,24 @@ impl SchemaDefinition {
 
             // With named capture groups, you can iterate over the field names
             for field_name in format.fields.iter() {
-                if let Some(value) = captures.name(field_name) {
-                    extracted_fields.insert(
-                        field_name.to_owned(),
-                        Value::String(value.as_str().to_string()),
-                    );
-                }
+                let Some(value) = captures.name(field_name) else {
+                    continue;
+                };
+                let is_string = stream_schema
+                    .get(field_name)
+                    .is_some_and(|field| field.data_type() == &DataType::Utf8);
+                let value = match format.types.get(field_name) {
+                    Some(field_type) if !is_string => {
+                        field_type.to_value(value.as_str()).unwrap_or_else(|| {
+                            FORMAT_TYPE_MISMATCHES
+                                .with_label_values(&[&self.format, field_name])
+                                .inc();
+                            Value::Null
+                        })
+                    }
+                    _ => Value::String(value.as_str().to_string()),
+                };
+                extracted_fields.insert(field_name.to_owned(), value);
             }
 
             // add `P_FORMAT_VERIFY_KEY` to the object
@@ -149,7 +306,92 @@ impl SchemaDefinition {
 #[derive(Debug, Deserialize)]
 struct Format {
     name: String,
-    regex: Vec<Pattern>,
+    regex: Vec<PatternDefinition>,
+}
+
+/// A log format registered at runtime, defined as those of `formats.json` are
+/// and stored in the metastore, such that it is known to all nodes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogFormat {
+    #[serde(default)]
+    pub name: String,
+    pub regex: Vec<PatternDefinition>,
+}
+
+impl MetastoreObject for LogFormat {
+    fn get_object_path(&self) -> String {
+        RelativePathBuf::from_iter([
+            SETTINGS_ROOT_DIRECTORY,
+            LOG_FORMATS_DIR,
+            &format!("{}.json", self.name),
+        ])
+        .to_string()
+    }
+
+    fn get_object_id(&self) -> String {
+        self.name.clone()
+    }
+}
+
+impl LogFormat {
+    /// Compiles the patterns of the format, unlike the formats of `formats.json` a pattern
+    /// that doesn't compile, or lacks a capture group for one of its fields, is an error
+    pub fn schema_definition(&self) -> Result<SchemaDefinition, Error> {
+        if self.name.is_empty()
+            || !self
+                .name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        {
+            return Err(Error::InvalidFormat(format!(
+                "name '{}' should only contain alphanumeric characters, '_' or '-'",
+                self.name
+            )));
+        }
+        // names of the other log sources can't be used as they never resolve to a custom one
+        if !matches!(LogSource::from(self.name.as_str()), LogSource::Custom(_)) {
+            return Err(Error::InvalidFormat(format!(
+                "'{}' is a reserved log source",
+                self.name
+            )));
+        }
+        if self.regex.is_empty() {
+            return Err(Error::InvalidFormat(
+                "at least one pattern is required".to_owned(),
+            ));
+        }
+
+        let mut patterns = vec![];
+        for definition in &self.regex {
+            let pattern = definition.compile()?;
+            let Some(regex) = pattern.pattern.as_ref() else {
+                return Err(Error::InvalidFormat(
+                    "each pattern requires either a pattern or a grok expression".to_owned(),
+                ));
+            };
+            if let Some(field) = pattern.fields.iter().find(|field| {
+                !regex
+                    .capture_names()
+                    .flatten()
+                    .any(|name| name == field.as_str())
+            }) {
+                return Err(Error::InvalidFormat(format!(
+                    "field '{field}' is not a named capture group of pattern '{}'",
+                    definition
+                        .pattern
+                        .as_deref()
+                        .or(definition.grok.as_deref())
+                        .unwrap_or_default()
+                )));
+            }
+            patterns.push(pattern);
+        }
+
+        Ok(SchemaDefinition {
+            format: self.name.clone(),
+            patterns,
+        })
+    }
 }
 
 /// Manages a collection of schema definitions for various log formats
@@ -157,6 +399,8 @@ struct Format {
 pub struct EventProcessor {
     /// Map of format names to their corresponding schema definitions
     pub schema_definitions: HashMap<String, SchemaDefinition>,
+    /// Formats registered at runtime along with their definitions, see [`LogFormat`]
+    custom_definitions: RwLock<HashMap<String, (LogFormat, SchemaDefinition)>>,
 }
 
 impl EventProcessor {
@@ -164,6 +408,7 @@ impl EventProcessor {
     fn new(json_text: &str) -> Self {
         let mut processor = EventProcessor {
             schema_definitions: HashMap::new(),
+            custom_definitions: RwLock::new(HashMap::new()),
         };
 
         let formats: Vec<Format> =
@@ -174,21 +419,77 @@ impl EventProcessor {
                 let schema = processor
                     .schema_definitions
                     .entry(format.name.clone())
-                    .or_default();
-
-                schema.patterns.push(regex);
+                    .or_insert_with(|| SchemaDefinition {
+                        format: format.name.clone(),
+                        patterns: vec![],
+                    });
+
+                // NOTE: we only warn if the pattern doesn't compile
+                let pattern = regex.compile().unwrap_or_else(|err| {
+                    error!("Error compiling pattern of format {}: {err}", format.name);
+                    Pattern::from_fields(&regex.fields)
+                });
+                schema.patterns.push(pattern);
             }
         }
 
         processor
     }
 
+    /// Checks that the format can be registered as a custom one, returning its definition
+    pub fn validate_custom_format(&self, format: &LogFormat) -> Result<SchemaDefinition, Error> {
+        if self.schema_definitions.contains_key(&format.name) {
+            return Err(Error::InvalidFormat(format!(
+                "'{}' is a built-in format",
+                format.name
+            )));
+        }
+
+        format.schema_definition()
+    }
+
+    /// Registers a custom format, or replaces the one of the same name
+    pub fn set_custom_format(&self, format: &LogFormat) -> Result<(), Error> {
+        let schema = self.validate_custom_format(format)?;
+        self.custom_definitions
+            .write()
+            .expect(LOCK_EXPECT)
+            .insert(format.name.clone(), (format.clone(), schema));
+
+        Ok(())
+    }
+
+    pub fn custom_format(&self, name: &str) -> Option<LogFormat> {
+        self.custom_definitions
+            .read()
+            .expect(LOCK_EXPECT)
+            .get(name)
+            .map(|(format, _)| format.clone())
+    }
+
+    pub fn custom_formats(&self) -> Vec<LogFormat> {
+        self.custom_definitions
+            .read()
+            .expect(LOCK_EXPECT)
+            .values()
+            .map(|(format, _)| format.clone())
+            .collect()
+    }
+
+    pub fn remove_custom_format(&self, name: &str) {
+        self.custom_definitions
+            .write()
+            .expect(LOCK_EXPECT)
+            .remove(name);
+    }
+
     /// Extracts fields from logs embedded within a JSON string
     ///
     /// # Arguments
     /// * `json` - JSON value containing log entries
     /// * `log_source` - Name of the log format to use for extraction
     /// * `extract_log` - Optional field name containing the raw log text
+    /// * `stream_schema` - Schema of the stream the logs are ingested into, if any yet
     ///
     /// # Returns
     /// * `Ok` - The original JSON will now contain extracted fields
@@ -199,8 +500,14 @@ impl EventProcessor {
         p_custom_fields: &mut HashMap<String, String>,
         log_source: &str,
         extract_log: Option<&str>,
+        stream_schema: &HashMap<String, Arc<Field>>,
     ) -> Result<HashSet<String>, Error> {
-        let Some(schema) = self.schema_definitions.get(log_source) else {
+        let custom_definitions = self.custom_definitions.read().expect(LOCK_EXPECT);
+        let Some(schema) = self
+            .schema_definitions
+            .get(log_source)
+            .or_else(|| custom_definitions.get(log_source).map(|(_, schema)| schema))
+        else {
             return Err(Error::Unknown(log_source.to_owned()));
         };
 
@@ -211,7 +518,9 @@ impl EventProcessor {
                     let Value::Object(event) = event else {
                         continue;
                     };
-                    if let Some(known_fields) = schema.check_or_extract(event, extract_log) {
+                    if let Some(known_fields) =
+                        schema.check_or_extract(event, extract_log, stream_schema)
+                    {
                         fields.extend(known_fields);
                     } else {
                         // add `P_FORMAT_VERIFY_KEY` to the object
@@ -220,7 +529,9 @@ impl EventProcessor {
                 }
             }
             Value::Object(event) => {
-                if let Some(known_fields) = schema.check_or_extract(event, extract_log) {
+                if let Some(known_fields) =
+                    schema.check_or_extract(event, extract_log, stream_schema)
+                {
                     return Ok(known_fields);
                 } else {
                     // add `P_FORMAT_VERIFY_KEY` to the object
@@ -275,7 +586,7 @@ mod tests {
         ));
 
         // Use check_or_extract instead of extract
-        let result = schema.check_or_extract(&mut obj, Some(log_field));
+        let result = schema.check_or_extract(&mut obj, Some(log_field), &HashMap::new());
         assert!(result.is_some(), "Failed to extract fields from valid log");
 
         // Verify extracted fields were added to the object
@@ -290,6 +601,52 @@ mod tests {
         assert_eq!(obj.get("bytes").unwrap().as_str().unwrap(), "2326");
     }
 
+    #[test]
+    fn test_typed_field_extraction() {
+        let processor = EventProcessor::new(
+            r#"[{
+                "name": "typed_access",
+                "regex": [{
+                    "pattern": "^(?P<ip>\\S+) \\[(?P<timestamp>[^\\]]+)\\] (?P<status>\\d+) (?P<bytes>\\d+|-) (?P<latency>[\\d.]+)",
+                    "fields": [
+                        {"name": "ip", "type": "ip"},
+                        {"name": "timestamp", "type": "timestamp", "format": "%d/%b/%Y:%H:%M:%S %z"},
+                        {"name": "status", "type": "int"},
+                        {"name": "bytes", "type": "int"},
+                        {"name": "latency", "type": "float"}
+                    ]
+                }]
+            }]"#,
+        );
+        let schema = processor.schema_definitions.get("typed_access").unwrap();
+
+        let mut obj = Map::new();
+        obj.insert(
+            "raw_log".to_string(),
+            Value::String("::1 [10/Oct/2023:13:55:36 +0530] 404 - 0.25".to_string()),
+        );
+        let result = schema.check_or_extract(&mut obj, Some("raw_log"), &HashMap::new());
+
+        assert_eq!(result.unwrap().len(), 5);
+        assert_eq!(obj.get("ip").unwrap(), "::1");
+        assert_eq!(obj.get("timestamp").unwrap(), "2023-10-10T08:25:36Z");
+        assert_eq!(obj.get("status").unwrap(), &json!(404));
+        assert_eq!(obj.get("latency").unwrap(), &json!(0.25));
+        // values not of the declared type are null
+        assert_eq!(obj.get("bytes").unwrap(), &Value::Null);
+
+        // fields the stream already has as strings are kept as captured
+        let stream_schema = HashMap::from([(
+            "status".to_owned(),
+            Arc::new(Field::new("status", DataType::Utf8, true)),
+        )]);
+        obj.retain(|key, _| key == "raw_log");
+        schema.check_or_extract(&mut obj, Some("raw_log"), &stream_schema);
+
+        assert_eq!(obj.get("status").unwrap(), "404");
+        assert_eq!(obj.get("latency").unwrap(), &json!(0.25));
+    }
+
     #[test]
     fn test_custom_log_extraction() {
         let processor = EventProcessor::new(TEST_CONFIG);
@@ -306,7 +663,7 @@ mod tests {
         );
 
         // Use check_or_extract instead of extract
-        let result = schema.check_or_extract(&mut obj, Some(log_field));
+        let result = schema.check_or_extract(&mut obj, Some(log_field), &HashMap::new());
         assert!(result.is_some(), "Failed to extract fields from valid log");
 
         // Verify extracted fields were added to the object
@@ -339,7 +696,7 @@ mod tests {
         );
 
         // check_or_extract should return true without modifying anything
-        let result = schema.check_or_extract(&mut obj, None);
+        let result = schema.check_or_extract(&mut obj, None, &HashMap::new());
         assert!(
             result.is_some(),
             "Should return true when fields already exist"
@@ -366,7 +723,7 @@ mod tests {
         );
 
         // check_or_extract should return false
-        let result = schema.check_or_extract(&mut obj, Some(log_field));
+        let result = schema.check_or_extract(&mut obj, Some(log_field), &HashMap::new());
         assert!(
             result.is_none(),
             "Should not extract fields from invalid log format"
@@ -384,7 +741,9 @@ mod tests {
             patterns: vec![Pattern {
                 pattern: None,
                 fields: HashSet::from_iter(["field1".to_string(), "field2".to_string()]),
+                types: HashMap::new(),
             }],
+            ..Default::default()
         };
 
         // Create an object missing the required fields
@@ -395,7 +754,7 @@ mod tests {
         );
 
         // check_or_extract should return false
-        let result = schema.check_or_extract(&mut obj, Some("log"));
+        let result = schema.check_or_extract(&mut obj, Some("log"), &HashMap::new());
         assert!(
             result.is_none(),
             "Should return false when no pattern and missing fields"
@@ -414,7 +773,7 @@ mod tests {
         // Updated to handle check_or_extract
         let result = if let Value::Object(ref mut obj) = json_value {
             let schema = processor.schema_definitions.get("custom_app_log").unwrap();
-            schema.check_or_extract(obj, Some("raw_log"));
+            schema.check_or_extract(obj, Some("raw_log"), &HashMap::new());
             json_value
         } else {
             json_value
@@ -447,7 +806,7 @@ mod tests {
             for item in array {
                 if let Value::Object(obj) = item {
                     let schema = processor.schema_definitions.get("custom_app_log").unwrap();
-                    schema.check_or_extract(obj, Some("raw_log"));
+                    schema.check_or_extract(obj, Some("raw_log"), &HashMap::new());
                 }
             }
         }
@@ -481,7 +840,7 @@ mod tests {
         // Try to extract with a non-existent format
         if let Value::Object(ref mut obj) = json_value {
             if let Some(schema) = processor.schema_definitions.get("nonexistent_format") {
-                schema.check_or_extract(obj, Some("raw_log"));
+                schema.check_or_extract(obj, Some("raw_log"), &HashMap::new());
             }
         }
 
@@ -503,7 +862,7 @@ mod tests {
         obj.insert("id".to_string(), Value::String("12345".to_string()));
 
         // check_or_extract should return false
-        let result = schema.check_or_extract(&mut obj, Some("raw_log"));
+        let result = schema.check_or_extract(&mut obj, Some("raw_log"), &HashMap::new());
         assert!(
             result.is_none(),
             "Should return false when log field is missing"
@@ -535,7 +894,7 @@ mod tests {
             let log_field = "raw_log";
             obj.insert(log_field.to_string(), Value::String(log_text.to_string()));
 
-            let result = schema.check_or_extract(&mut obj, Some(log_field));
+            let result = schema.check_or_extract(&mut obj, Some(log_field), &HashMap::new());
             assert!(
                 result.is_some(),
                 "Failed to extract fields from rust server log {}: {}",
@@ -605,4 +964,90 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_custom_format_registration() {
+        let processor = EventProcessor::new(TEST_CONFIG);
+        let format: LogFormat = serde_json::from_value(json!({
+            "name": "billing_log",
+            "regex": [{
+                "pattern": "^(?P<invoice>\\w+) (?P<amount>[\\d.]+)$",
+                "fields": ["invoice", {"name": "amount", "type": "float"}]
+            }]
+        }))
+        .unwrap();
+        processor.set_custom_format(&format).unwrap();
+
+        let mut json = json!({"raw": "INV42 12.5"});
+        let fields = processor
+            .extract_from_inline_log(
+                &mut json,
+                &mut HashMap::new(),
+                "billing_log",
+                Some("raw"),
+                &HashMap::new(),
+            )
+            .unwrap();
+        assert_eq!(fields.len(), 2);
+        assert_eq!(json["amount"], json!(12.5));
+
+        processor.remove_custom_format("billing_log");
+        assert!(matches!(
+            processor.extract_from_inline_log(
+                &mut json,
+                &mut HashMap::new(),
+                "billing_log",
+                None,
+                &HashMap::new(),
+            ),
+            Err(Error::Unknown(_))
+        ));
+
+        // built-in and reserved names, as well as fields missing from the pattern, are rejected
+        for (name, field) in [
+            ("apache_access", "invoice"),
+            ("syslog", "invoice"),
+            ("billing_log", "customer"),
+        ] {
+            let mut format = format.clone();
+            format.name = name.to_owned();
+            format.regex[0].fields = vec![FieldDefinition::Name(field.to_owned())];
+            assert!(processor.set_custom_format(&format).is_err());
+        }
+    }
+
+    #[test]
+    fn test_grok_format_extraction() {
+        let processor = EventProcessor::new(
+            r#"[{
+                "name": "grok_access",
+                "regex": [{
+                    "grok": "^%{COMBINEDAPACHELOG}( %{DURATION:duration})?$",
+                    "pattern_definitions": {"DURATION": "%{NUMBER}ms"},
+                    "fields": [{"name": "timestamp", "type": "timestamp", "format": "%d/%b/%Y:%H:%M:%S %z"}]
+                }]
+            }]"#,
+        );
+        let schema = processor.schema_definitions.get("grok_access").unwrap();
+
+        let mut obj = Map::new();
+        obj.insert(
+            "raw_log".to_string(),
+            Value::String(
+                "127.0.0.1 - frank [10/Oct/2000:13:55:36 -0700] \"GET /index.html HTTP/1.0\" 200 2326 \"-\" \"curl/8.0\" 12ms"
+                    .to_string(),
+            ),
+        );
+        let fields = schema
+            .check_or_extract(&mut obj, Some("raw_log"), &HashMap::new())
+            .unwrap();
+
+        assert!(fields.contains("clientip") && fields.contains("duration"));
+        assert_eq!(obj.get("clientip").unwrap(), "127.0.0.1");
+        assert_eq!(obj.get("verb").unwrap(), "GET");
+        assert_eq!(obj.get("response").unwrap(), &json!(200));
+        assert_eq!(obj.get("timestamp").unwrap(), "2000-10-10T20:55:36Z");
+        assert_eq!(obj.get("duration").unwrap(), "12ms");
+        assert!(!obj.contains_key("rawrequest"));
+    }
 }
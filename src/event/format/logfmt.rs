@@ -0,0 +1,182 @@
+/*
+ * Parseable Server (C) 2022 - 2025 Parseable, Inc.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ *
+ */
+
+use std::{collections::HashMap, iter::Peekable, str::Chars};
+
+use serde_json::{Map, Value};
+
+use super::ColumnType;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("line {0}: unterminated quoted value")]
+    UnterminatedQuote(usize),
+    #[error("line {0}: unexpected '{1}' in key")]
+    InvalidKey(usize, char),
+}
+
+/// Parses newline delimited logfmt lines (`level=info msg="hello world" dur=12ms`) into
+/// a list of flat json objects, one per non empty line. A key without a value is read as
+/// `true`, empty values are left out of the object. Values are typed per key across the
+/// whole body, a key is only typed as number or boolean when all of its values are such.
+/// Malformed lines are skipped and returned along with the rows.
+pub fn parse(body: &str) -> (Vec<Value>, Vec<Error>) {
+    let (lines, errors): (Vec<_>, Vec<_>) = body
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| parse_line(line, i + 1))
+        .partition(Result::is_ok);
+    let lines: Vec<_> = lines.into_iter().flatten().collect();
+    let errors = errors.into_iter().filter_map(Result::err).collect();
+
+    let mut key_types: HashMap<&str, ColumnType> = HashMap::new();
+    for (key, value) in lines.iter().flatten() {
+        if value.is_empty() {
+            continue;
+        }
+        let value_type = ColumnType::of(value);
+        key_types
+            .entry(key.as_str())
+            .and_modify(|t| *t = t.merge(value_type))
+            .or_insert(value_type);
+    }
+
+    let rows = lines
+        .iter()
+        .map(|pairs| {
+            let row: Map<String, Value> = pairs
+                .iter()
+                .filter(|(_, value)| !value.is_empty())
+                .map(|(key, value)| {
+                    let key_type = key_types
+                        .get(key.as_str())
+                        .copied()
+                        .unwrap_or(ColumnType::Utf8);
+                    (key.clone(), key_type.to_value(value))
+                })
+                .collect();
+            Value::Object(row)
+        })
+        .collect();
+
+    (rows, errors)
+}
+
+// Splits a single logfmt line into its key value pairs, in order of occurrence
+fn parse_line(line: &str, line_number: usize) -> Result<Vec<(String, String)>, Error> {
+    let mut pairs = vec![];
+    let mut chars = line.chars().peekable();
+
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        if chars.peek().is_none() {
+            break;
+        }
+
+        let mut key = String::new();
+        while let Some(c) = chars.next_if(|c| !c.is_whitespace() && *c != '=') {
+            if c == '"' {
+                return Err(Error::InvalidKey(line_number, c));
+            }
+            key.push(c);
+        }
+        if key.is_empty() {
+            return Err(Error::InvalidKey(line_number, '='));
+        }
+
+        let value = if chars.next_if_eq(&'=').is_some() {
+            if chars.next_if_eq(&'"').is_some() {
+                read_quoted(&mut chars).ok_or(Error::UnterminatedQuote(line_number))?
+            } else {
+                let mut value = String::new();
+                while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+                    value.push(c);
+                }
+                value
+            }
+        } else {
+            "true".to_owned()
+        };
+
+        pairs.push((key, value));
+    }
+
+    Ok(pairs)
+}
+
+// Reads a quoted value up to the closing quote, which has already been opened,
+// returns None if the line ends before the quote is closed
+fn read_quoted(chars: &mut Peekable<Chars>) -> Option<String> {
+    let mut value = String::new();
+    loop {
+        match chars.next()? {
+            '"' => return Some(value),
+            '\\' => match chars.next()? {
+                'n' => value.push('\n'),
+                't' => value.push('\t'),
+                'r' => value.push('\r'),
+                c => value.push(c),
+            },
+            c => value.push(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn parse_lines() {
+        let body = "level=info msg=\"hello \\\"world\\\"\" dur=12ms count=1 cached\n\n\
+                    level=error msg= count=2.5\n";
+
+        let (rows, errors) = parse(body);
+
+        assert!(errors.is_empty());
+        assert_eq!(
+            rows,
+            vec![
+                json!({"level": "info", "msg": "hello \"world\"", "dur": "12ms", "count": 1.0, "cached": true}),
+                json!({"level": "error", "count": 2.5}),
+            ]
+        );
+    }
+
+    #[test]
+    fn values_with_mixed_types_are_strings() {
+        let (rows, _) = parse("id=1\nid=abc\n");
+
+        assert_eq!(rows, vec![json!({"id": "1"}), json!({"id": "abc"})]);
+    }
+
+    #[test]
+    fn malformed_lines_are_skipped() {
+        let (rows, errors) = parse("level=info\nmsg=\"oops\nlevel=warn\n");
+
+        assert_eq!(
+            rows,
+            vec![json!({"level": "info"}), json!({"level": "warn"})]
+        );
+        assert!(matches!(errors[..], [Error::UnterminatedQuote(2)]));
+    }
+}
@@ -28,7 +28,7 @@ use arrow_array::RecordBatch;
 use arrow_schema::{DataType, Field, Schema, TimeUnit};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use serde_json::{Number, Value};
 
 use crate::{
     handlers::TelemetryType,
@@ -39,8 +39,13 @@ use crate::{
 
 use super::{DEFAULT_TIMESTAMP_KEY, Event};
 
+pub mod arrow;
+pub mod csv;
+pub mod grok;
 pub mod json;
 pub mod known_schema;
+pub mod logfmt;
+pub mod ndjson;
 
 static TIME_FIELD_NAME_PARTS: [&str; 11] = [
     "time",
@@ -87,11 +92,35 @@ pub enum LogSource {
     // Internal Stream format
     #[serde(rename = "pmeta")]
     Pmeta,
+    // Arrow record batches, e.g. sent over Arrow Flight
+    #[serde(rename = "arrow")]
+    Arrow,
+    // Comma or tab separated values, with or without a header row
+    #[serde(rename = "csv")]
+    Csv,
+    // Newline delimited `key=value` pairs, as emitted by Go and Heroku style loggers
+    #[serde(rename = "logfmt")]
+    Logfmt,
+    // RFC 5424 and RFC 3164 messages received by the syslog listener
+    #[serde(rename = "syslog")]
+    Syslog,
+    // Records received by the Fluent Forward listener, e.g. from Fluentd or Fluent Bit
+    #[serde(rename = "fluent")]
+    Fluent,
+    // Streams of log lines pushed by Loki clients, e.g. Promtail or the Grafana Agent
+    #[serde(rename = "loki")]
+    Loki,
+    // Events sent to the Splunk HTTP Event Collector compatible endpoints
+    #[serde(rename = "splunk")]
+    Splunk,
+    // Samples sent by Prometheus over remote_write, stored as metrics
+    #[serde(rename = "prometheus")]
+    Prometheus,
     #[default]
     #[serde(rename = "json")]
     // Json object or array
     Json,
-    // Custom Log Sources e.g. "syslog"
+    // Custom Log Sources e.g. "access_log"
     #[serde(untagged)]
     Custom(String),
 }
@@ -104,6 +133,14 @@ impl From<&str> for LogSource {
             "otel-metrics" => LogSource::OtelMetrics,
             "otel-traces" => LogSource::OtelTraces,
             "pmeta" => LogSource::Pmeta,
+            "arrow" => LogSource::Arrow,
+            "csv" => LogSource::Csv,
+            "logfmt" => LogSource::Logfmt,
+            "syslog" => LogSource::Syslog,
+            "fluent" => LogSource::Fluent,
+            "loki" => LogSource::Loki,
+            "splunk" => LogSource::Splunk,
+            "prometheus" => LogSource::Prometheus,
             "" | "json" => LogSource::Json,
             custom => LogSource::Custom(custom.to_owned()),
         }
@@ -119,6 +156,14 @@ impl Display for LogSource {
             LogSource::OtelTraces => "otel-traces",
             LogSource::Json => "json",
             LogSource::Pmeta => "pmeta",
+            LogSource::Arrow => "arrow",
+            LogSource::Csv => "csv",
+            LogSource::Logfmt => "logfmt",
+            LogSource::Syslog => "syslog",
+            LogSource::Fluent => "fluent",
+            LogSource::Loki => "loki",
+            LogSource::Splunk => "splunk",
+            LogSource::Prometheus => "prometheus",
             LogSource::Custom(custom) => custom,
         })
     }
@@ -141,6 +186,54 @@ impl LogSourceEntry {
     }
 }
 
+/// Inferred type of a column of text values, e.g. CSV or logfmt, a column is only
+/// typed as number or boolean when all of its non empty values can be parsed as such
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnType {
+    Int,
+    Float,
+    Bool,
+    Utf8,
+}
+
+impl ColumnType {
+    pub fn of(value: &str) -> Self {
+        if value.parse::<i64>().is_ok() {
+            ColumnType::Int
+        } else if value.parse::<f64>().is_ok_and(f64::is_finite) {
+            ColumnType::Float
+        } else if value == "true" || value == "false" {
+            ColumnType::Bool
+        } else {
+            ColumnType::Utf8
+        }
+    }
+
+    pub fn merge(self, other: Self) -> Self {
+        match (self, other) {
+            (a, b) if a == b => a,
+            (ColumnType::Int, ColumnType::Float) | (ColumnType::Float, ColumnType::Int) => {
+                ColumnType::Float
+            }
+            _ => ColumnType::Utf8,
+        }
+    }
+
+    pub fn to_value(self, value: &str) -> Value {
+        match self {
+            ColumnType::Int => value.parse::<i64>().map(Value::from).ok(),
+            ColumnType::Float => value
+                .parse::<f64>()
+                .ok()
+                .and_then(Number::from_f64)
+                .map(Value::Number),
+            ColumnType::Bool => Some(Value::Bool(value == "true")),
+            ColumnType::Utf8 => None,
+        }
+        .unwrap_or_else(|| Value::String(value.to_owned()))
+    }
+}
+
 // Global Trait for event format
 // This trait is implemented by all the event formats
 pub trait EventFormat: Sized {
@@ -0,0 +1,139 @@
+/*
+ * Parseable Server (C) 2022 - 2025 Parseable, Inc.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ */
+
+use serde_json::Value;
+
+use crate::utils::json::strict::StrictValue;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("line exceeds the limit of {0} bytes")]
+    TooLong(usize),
+    #[error("{0}")]
+    Json(#[from] serde_json::Error),
+    #[error("expected a JSON object")]
+    NotAnObject,
+}
+
+/// A parsed line along with its line number, counting from 1
+pub type Line = (usize, Result<Value, Error>);
+
+/// Splits newline delimited json arriving in chunks into its lines, only the partial line
+/// at the end of a chunk is held on to. Lines longer than `max_line_size` are skipped
+/// without being buffered, empty lines are ignored.
+pub struct LineSplitter {
+    buf: Vec<u8>,
+    line_number: usize,
+    max_line_size: usize,
+    overflow: bool,
+}
+
+impl LineSplitter {
+    pub fn new(max_line_size: usize) -> Self {
+        Self {
+            buf: Vec::new(),
+            line_number: 0,
+            max_line_size,
+            overflow: false,
+        }
+    }
+
+    /// Parses the lines completed by `chunk` into `lines`
+    pub fn push(&mut self, chunk: &[u8], lines: &mut Vec<Line>) {
+        for part in chunk.split_inclusive(|b| *b == b'\n') {
+            let (part, complete) = match part.strip_suffix(b"\n") {
+                Some(part) => (part, true),
+                None => (part, false),
+            };
+            if !self.overflow {
+                if self.buf.len() + part.len() > self.max_line_size {
+                    self.overflow = true;
+                    self.buf.clear();
+                } else {
+                    self.buf.extend_from_slice(part);
+                }
+            }
+            if complete {
+                self.end_line(lines);
+            }
+        }
+    }
+
+    /// Parses the last line, for bodies that don't end with a newline
+    pub fn finish(mut self, lines: &mut Vec<Line>) {
+        if !self.buf.is_empty() || self.overflow {
+            self.end_line(lines);
+        }
+    }
+
+    fn end_line(&mut self, lines: &mut Vec<Line>) {
+        self.line_number += 1;
+        if self.overflow {
+            self.overflow = false;
+            lines.push((self.line_number, Err(Error::TooLong(self.max_line_size))));
+            return;
+        }
+
+        let line = self.buf.strip_suffix(b"\r").unwrap_or(&self.buf);
+        if !line.trim_ascii().is_empty() {
+            lines.push((self.line_number, parse_line(line)));
+        }
+        self.buf.clear();
+    }
+}
+
+fn parse_line(line: &[u8]) -> Result<Value, Error> {
+    match serde_json::from_slice::<StrictValue>(line)?.into_inner() {
+        value @ Value::Object(_) => Ok(value),
+        _ => Err(Error::NotAnObject),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn split_lines_across_chunks() {
+        let mut splitter = LineSplitter::new(32);
+        let mut lines = vec![];
+        splitter.push(b"{\"a\":1}\n{\"a\":", &mut lines);
+        splitter.push(b"2}\r\n\n[1]\n{\"a\":\"", &mut lines);
+        splitter.push(&[b'x'; 40], &mut lines);
+        splitter.push(b"\"}\n{oops}\n{\"a\":3}", &mut lines);
+        splitter.finish(&mut lines);
+
+        let lines: Vec<_> = lines
+            .into_iter()
+            .map(|(n, line)| (n, line.map_err(|e| e.to_string())))
+            .collect();
+        assert_eq!(lines[0], (1, Ok(json!({"a": 1}))));
+        assert_eq!(lines[1], (2, Ok(json!({"a": 2}))));
+        assert_eq!(lines[2], (4, Err("expected a JSON object".to_owned())));
+        assert_eq!(
+            lines[3],
+            (5, Err("line exceeds the limit of 32 bytes".to_owned()))
+        );
+        assert_eq!(lines[4].0, 6);
+        assert!(lines[4].1.is_err());
+        assert_eq!(lines[5], (7, Ok(json!({"a": 3}))));
+        assert_eq!(lines.len(), 6);
+    }
+}
@@ -0,0 +1,391 @@
+/*
+ * Parseable Server (C) 2022 - 2025 Parseable, Inc.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ */
+
+//! GeoIP and ASN enrichment of IP fields on ingestion, from local MaxMind format databases
+//! given with `P_GEOIP_DB`. For each configured field, `<field>_geo_country`, `<field>_geo_city`,
+//! `<field>_geo_lat`, `<field>_geo_lon` and `<field>_asn` are added when known.
+
+use std::collections::{BTreeMap, HashMap};
+use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
+use std::sync::RwLock;
+use std::time::{Duration, SystemTime};
+
+use maxminddb::Reader;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tracing::{info, warn};
+
+use super::SOURCE_IP_KEY;
+use crate::{LOCK_EXPECT, parseable::PARSEABLE};
+
+/// How often the databases are checked for changes
+const RELOAD_INTERVAL: Duration = Duration::from_secs(60);
+
+static DATABASES: Lazy<RwLock<Vec<Database>>> = Lazy::new(RwLock::default);
+
+struct Database {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    reader: Reader<Vec<u8>>,
+}
+
+/// The fields of a record looked up, as found in City, Country and ASN databases
+#[derive(Debug, Default, Deserialize)]
+struct Record<'a> {
+    #[serde(borrow)]
+    country: Option<Place<'a>>,
+    #[serde(borrow)]
+    city: Option<Place<'a>>,
+    location: Option<Location>,
+    autonomous_system_number: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Place<'a> {
+    iso_code: Option<&'a str>,
+    #[serde(borrow)]
+    names: Option<BTreeMap<&'a str, &'a str>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Location {
+    latitude: Option<f64>,
+    longitude: Option<f64>,
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("field names can't be empty")]
+pub struct Error;
+
+/// The IP fields of a stream to enrich, stored in `stream.json`. The source IP of
+/// requests can be enriched with [`SOURCE_IP_KEY`].
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct GeoIp {
+    fields: Vec<String>,
+}
+
+impl GeoIp {
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.fields.iter().any(|field| field.is_empty()) {
+            return Err(Error);
+        }
+
+        Ok(())
+    }
+
+    /// Enriches the events of `json`, an object or an array of objects, looking up
+    /// [`SOURCE_IP_KEY`] in `p_custom_fields` if the events don't have it
+    pub fn apply(&self, json: &mut Value, p_custom_fields: &HashMap<String, String>) {
+        if self.is_empty() {
+            return;
+        }
+        let databases = DATABASES.read().expect(LOCK_EXPECT);
+        if databases.is_empty() {
+            return;
+        }
+
+        match json {
+            Value::Object(event) => self.apply_event(&databases, event, p_custom_fields),
+            Value::Array(events) => {
+                for event in events.iter_mut().filter_map(Value::as_object_mut) {
+                    self.apply_event(&databases, event, p_custom_fields);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn apply_event(
+        &self,
+        databases: &[Database],
+        event: &mut Map<String, Value>,
+        p_custom_fields: &HashMap<String, String>,
+    ) {
+        for field in &self.fields {
+            let ip = match event.get(field) {
+                Some(Value::String(ip)) => parse_ip(ip),
+                Some(_) => None,
+                None if field == SOURCE_IP_KEY => p_custom_fields
+                    .get(SOURCE_IP_KEY)
+                    .and_then(|ip| parse_ip(ip)),
+                None => None,
+            };
+            let Some(ip) = ip else {
+                continue;
+            };
+
+            let record = lookup(databases, ip);
+            let country = record.country.and_then(|country| country.iso_code);
+            let city = record
+                .city
+                .and_then(|city| city.names)
+                .and_then(|names| names.get("en").copied());
+            let location = record.location;
+            let columns = [
+                ("geo_country", country.map(Value::from)),
+                ("geo_city", city.map(Value::from)),
+                (
+                    "geo_lat",
+                    location.as_ref().and_then(|l| l.latitude).map(Value::from),
+                ),
+                (
+                    "geo_lon",
+                    location.as_ref().and_then(|l| l.longitude).map(Value::from),
+                ),
+                ("asn", record.autonomous_system_number.map(Value::from)),
+            ];
+            for (suffix, value) in columns {
+                if let Some(value) = value {
+                    event.insert(format!("{field}_{suffix}"), value);
+                }
+            }
+        }
+    }
+}
+
+/// Parses an IP address, with or without a port
+fn parse_ip(ip: &str) -> Option<IpAddr> {
+    let ip = ip.trim();
+    ip.parse()
+        .ok()
+        .or_else(|| ip.parse::<SocketAddr>().ok().map(|addr| addr.ip()))
+}
+
+/// Looks the address up in each database, the first database to know a field wins
+fn lookup(databases: &[Database], ip: IpAddr) -> Record<'_> {
+    let mut merged = Record::default();
+    for database in databases {
+        let Ok(record) = database.reader.lookup::<Record>(ip) else {
+            continue;
+        };
+        merged.country = merged.country.or(record.country);
+        merged.city = merged.city.or(record.city);
+        merged.location = merged.location.or(record.location);
+        merged.autonomous_system_number = merged
+            .autonomous_system_number
+            .or(record.autonomous_system_number);
+    }
+
+    merged
+}
+
+/// Loads the databases given with `P_GEOIP_DB`, if any, and reloads them whenever they change
+pub fn init() {
+    let paths = PARSEABLE.options.geoip_db.clone();
+    if paths.is_empty() {
+        return;
+    }
+
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(RELOAD_INTERVAL);
+        loop {
+            interval.tick().await;
+            let paths = paths.clone();
+            if let Err(err) = tokio::task::spawn_blocking(move || reload(&paths)).await {
+                warn!("GeoIP database reload panicked: {err}");
+            }
+        }
+    });
+}
+
+/// (Re)loads the databases that changed since they were last loaded. A database that
+/// can't be read is skipped, keeping the version loaded before if any.
+fn reload(paths: &[PathBuf]) {
+    for path in paths {
+        let modified = std::fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .ok();
+        {
+            let databases = DATABASES.read().expect(LOCK_EXPECT);
+            if databases
+                .iter()
+                .any(|database| &database.path == path && database.modified == modified)
+            {
+                continue;
+            }
+        }
+
+        let reader = match Reader::open_readfile(path) {
+            Ok(reader) => reader,
+            Err(err) => {
+                warn!("Failed to load GeoIP database {}: {err}", path.display());
+                continue;
+            }
+        };
+        info!(
+            "Loaded GeoIP database {} ({})",
+            path.display(),
+            reader.metadata.database_type
+        );
+
+        let database = Database {
+            path: path.clone(),
+            modified,
+            reader,
+        };
+        let mut databases = DATABASES.write().expect(LOCK_EXPECT);
+        match databases.iter_mut().find(|loaded| &loaded.path == path) {
+            Some(loaded) => *loaded = database,
+            None => databases.push(database),
+        }
+        // lookups go through the databases in the order they were given in
+        databases.sort_by_key(|database| paths.iter().position(|path| path == &database.path));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use temp_dir::TempDir;
+
+    use super::*;
+
+    /// A database in the MaxMind DB format with a single record, found for the addresses
+    /// of `0.0.0.0/1` and for none other
+    fn database(country: &str) -> Vec<u8> {
+        fn string(value: &str) -> Vec<u8> {
+            let mut bytes = vec![(2 << 5) | value.len() as u8];
+            bytes.extend(value.as_bytes());
+            bytes
+        }
+        fn map(pairs: Vec<(&str, Vec<u8>)>) -> Vec<u8> {
+            let mut bytes = vec![(7 << 5) | pairs.len() as u8];
+            for (key, value) in pairs {
+                bytes.extend(string(key));
+                bytes.extend(value);
+            }
+            bytes
+        }
+        fn double(value: f64) -> Vec<u8> {
+            let mut bytes = vec![(3 << 5) | 8];
+            bytes.extend(value.to_be_bytes());
+            bytes
+        }
+        let uint16 = |value: u8| vec![(5 << 5) | 1, value];
+
+        // a single node, addresses with a first bit of 0 point to the record, the others
+        // to the node count, i.e. not found
+        let node_count = 1;
+        let record = node_count + 16;
+        let mut bytes = vec![0, 0, record, 0, 0, node_count];
+        bytes.extend([0; 16]);
+        bytes.extend(map(vec![
+            ("country", map(vec![("iso_code", string(country))])),
+            (
+                "city",
+                map(vec![("names", map(vec![("en", string("London"))]))]),
+            ),
+            (
+                "location",
+                map(vec![
+                    ("latitude", double(51.5)),
+                    ("longitude", double(-0.1)),
+                ]),
+            ),
+            ("autonomous_system_number", vec![(6 << 5) | 2, 0xfc, 0x00]),
+        ]));
+        bytes.extend(b"\xab\xcd\xefMaxMind.com");
+        bytes.extend(map(vec![
+            ("node_count", vec![(6 << 5) | 1, node_count]),
+            ("record_size", uint16(24)),
+            ("ip_version", uint16(4)),
+            ("database_type", string("GeoLite2-City")),
+            ("languages", [vec![1, 4], string("en")].concat()),
+            ("binary_format_major_version", uint16(2)),
+            ("binary_format_minor_version", vec![5 << 5]),
+            ("build_epoch", vec![0, 2]),
+            ("description", map(vec![])),
+        ]));
+
+        bytes
+    }
+
+    #[test]
+    fn parse_ips() {
+        for (ip, expected) in [
+            ("203.0.113.7", Some("203.0.113.7")),
+            ("203.0.113.7:51234", Some("203.0.113.7")),
+            ("2001:db8::1", Some("2001:db8::1")),
+            ("[2001:db8::1]:443", Some("2001:db8::1")),
+            ("unknown", None),
+        ] {
+            assert_eq!(parse_ip(ip), expected.map(|ip| ip.parse().unwrap()));
+        }
+    }
+
+    #[test]
+    fn enrich_events_from_reloaded_database() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("city.mmdb");
+        std::fs::write(&path, database("GB")).unwrap();
+        reload(std::slice::from_ref(&path));
+
+        let geoip = GeoIp {
+            fields: vec!["client".to_owned(), SOURCE_IP_KEY.to_owned()],
+        };
+        let p_custom_fields = HashMap::from([(SOURCE_IP_KEY.to_owned(), "10.0.0.1".to_owned())]);
+        let mut json = json!([
+            {"client": "81.2.69.142:443"},
+            {"client": "203.0.113.7", SOURCE_IP_KEY: "unknown"}
+        ]);
+        geoip.apply(&mut json, &p_custom_fields);
+        assert_eq!(
+            json,
+            json!([
+                {
+                    "client": "81.2.69.142:443",
+                    "client_geo_country": "GB",
+                    "client_geo_city": "London",
+                    "client_geo_lat": 51.5,
+                    "client_geo_lon": -0.1,
+                    "client_asn": 64512,
+                    // the source IP of the request, as the event doesn't have it
+                    "p_src_ip_geo_country": "GB",
+                    "p_src_ip_geo_city": "London",
+                    "p_src_ip_geo_lat": 51.5,
+                    "p_src_ip_geo_lon": -0.1,
+                    "p_src_ip_asn": 64512
+                },
+                // neither address is known
+                {"client": "203.0.113.7", SOURCE_IP_KEY: "unknown"}
+            ])
+        );
+
+        // the database is reloaded once changed
+        let modified = std::fs::metadata(&path).unwrap().modified().unwrap();
+        std::fs::write(&path, database("FR")).unwrap();
+        std::fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(modified + Duration::from_secs(1))
+            .unwrap();
+        reload(std::slice::from_ref(&path));
+
+        let mut json = json!({"client": "81.2.69.142"});
+        geoip.apply(&mut json, &HashMap::new());
+        assert_eq!(json["client_geo_country"], "FR");
+    }
+}
@@ -17,7 +17,13 @@
 *
 */
 
+pub mod dedup;
 pub mod format;
+pub mod geoip;
+pub mod processors;
+pub mod redaction;
+pub mod sampling;
+pub mod settings;
 
 use arrow_array::RecordBatch;
 use arrow_schema::{Field, Fields, Schema};
@@ -30,7 +36,7 @@ use crate::{
     handlers::TelemetryType,
     metadata::update_stats,
     metrics::{increment_events_ingested_by_date, increment_events_ingested_size_by_date},
-    parseable::{PARSEABLE, StagingError},
+    parseable::{FlushAck, PARSEABLE, StagingError},
     storage::StreamType,
 };
 use chrono::NaiveDateTime;
@@ -41,6 +47,7 @@ pub const USER_AGENT_KEY: &str = "p_user_agent";
 pub const SOURCE_IP_KEY: &str = "p_src_ip";
 pub const FORMAT_KEY: &str = "p_format";
 pub const FORMAT_VERIFY_KEY: &str = "p_format_verified";
+pub const SAMPLE_WEIGHT_KEY: &str = "p_sample_weight";
 
 #[derive(Clone)]
 pub struct Event {
@@ -58,7 +65,21 @@ pub struct Event {
 
 // Events holds the schema related to a each event for a single log stream
 impl Event {
-    pub fn process(self) -> Result<(), EventError> {
+    /// Stages the event, returns the acknowledgement of it being durable in staging
+    pub fn process(mut self) -> Result<FlushAck, EventError> {
+        let stream = PARSEABLE.get_or_create_stream(&self.stream_name);
+        // duplicates are dropped before anything is staged or counted, the keys of the
+        // rows kept are only remembered once staged, such that a retry isn't dropped
+        let num_rows = self.rb.num_rows();
+        let (rb, dedup_keys) = stream.deduplicate(self.rb)?;
+        self.rb = rb;
+        if self.rb.num_rows() == 0 {
+            return Ok(FlushAck::done());
+        }
+        if self.rb.num_rows() < num_rows {
+            self.origin_size = self.origin_size * self.rb.num_rows() as u64 / num_rows as u64;
+        }
+
         let mut key = get_schema_key(&self.rb.schema().fields);
         if self.time_partition.is_some() {
             let parsed_timestamp_to_min = self.parsed_timestamp.format("%Y%m%dT%H%M").to_string();
@@ -75,13 +96,14 @@ impl Event {
             commit_schema(&self.stream_name, self.rb.schema())?;
         }
 
-        PARSEABLE.get_or_create_stream(&self.stream_name).push(
+        let ack = stream.push(
             &key,
             &self.rb,
             self.parsed_timestamp,
             &self.custom_partition_values,
             self.stream_type,
         )?;
+        stream.remember_keys(dedup_keys, &ack);
 
         update_stats(
             &self.stream_name,
@@ -98,7 +120,7 @@ impl Event {
 
         crate::livetail::LIVETAIL.process(&self.stream_name, &self.rb);
 
-        Ok(())
+        Ok(ack)
     }
 
     pub fn process_unchecked(&self) -> Result<(), EventError> {
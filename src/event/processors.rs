@@ -0,0 +1,317 @@
+/*
+ * Parseable Server (C) 2022 - 2025 Parseable, Inc.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ */
+
+//! Per-stream processor chains, applied to events on ingestion before the schema is inferred.
+//! Processors see events as they are stored, i.e. flattened, with nested keys joined by `_`.
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Number, Value};
+
+#[derive(Debug, thiserror::Error)]
+#[error("processor {index}: {msg}")]
+pub struct Error {
+    index: usize,
+    msg: String,
+}
+
+/// The processors of a stream, stored in `stream.json` and applied in order
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Pipeline {
+    processors: Vec<Processor>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum Processor {
+    /// Removes the fields
+    Drop { fields: Vec<String> },
+    /// Moves the value of `from` to `to`, replacing any value of `to`
+    Rename { from: String, to: String },
+    /// Sets the field to a constant value
+    Set { field: String, value: Value },
+    /// Converts the value of the field, a value that can't be converted is removed
+    Cast { field: String, to: CastType },
+    /// Copies the value of `from` to `to`, replacing any value of `to`
+    Copy { from: String, to: String },
+    /// Drops the whole event if the condition holds
+    DropEvent {
+        #[serde(rename = "if")]
+        condition: Condition,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CastType {
+    String,
+    Int,
+    Float,
+    Bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Condition {
+    Equals {
+        field: String,
+        value: Value,
+    },
+    NotEquals {
+        field: String,
+        value: Value,
+    },
+    /// The field is a string matching the regular expression
+    Matches {
+        field: String,
+        #[serde(with = "regex_serde")]
+        pattern: Regex,
+    },
+    Exists {
+        field: String,
+    },
+    Missing {
+        field: String,
+    },
+}
+
+impl Pipeline {
+    pub fn is_empty(&self) -> bool {
+        self.processors.is_empty()
+    }
+
+    pub fn validate(&self) -> Result<(), Error> {
+        for (index, processor) in self.processors.iter().enumerate() {
+            let error = |msg: &str| Error {
+                index,
+                msg: msg.to_owned(),
+            };
+            let fields = match processor {
+                Processor::Drop { fields } => fields.iter().collect(),
+                Processor::Rename { from, to } | Processor::Copy { from, to } => {
+                    if from == to {
+                        return Err(error("from and to are the same field"));
+                    }
+                    vec![from, to]
+                }
+                Processor::Set { field, value } => {
+                    if value.is_object() {
+                        return Err(error("value can't be an object"));
+                    }
+                    vec![field]
+                }
+                Processor::Cast { field, .. } => vec![field],
+                Processor::DropEvent { condition } => vec![condition.field()],
+            };
+            if fields.is_empty() || fields.iter().any(|field| field.is_empty()) {
+                return Err(error("field names can't be empty"));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Applies the processors to each event of `json`, an object or an array of objects,
+    /// `None` if all of the events are dropped
+    pub fn apply(&self, json: Value) -> Option<Value> {
+        if self.is_empty() {
+            return Some(json);
+        }
+
+        match json {
+            Value::Object(mut event) => {
+                self.apply_event(&mut event).then_some(Value::Object(event))
+            }
+            Value::Array(events) => {
+                let events: Vec<Value> = events
+                    .into_iter()
+                    .filter_map(|event| match event {
+                        Value::Object(mut event) => {
+                            self.apply_event(&mut event).then_some(Value::Object(event))
+                        }
+                        event => Some(event),
+                    })
+                    .collect();
+                (!events.is_empty()).then_some(Value::Array(events))
+            }
+            json => Some(json),
+        }
+    }
+
+    /// Returns `false` if the event is to be dropped
+    fn apply_event(&self, event: &mut Map<String, Value>) -> bool {
+        for processor in &self.processors {
+            match processor {
+                Processor::Drop { fields } => {
+                    for field in fields {
+                        event.remove(field);
+                    }
+                }
+                Processor::Rename { from, to } => {
+                    if let Some(value) = event.remove(from) {
+                        event.insert(to.clone(), value);
+                    }
+                }
+                Processor::Set { field, value } => {
+                    event.insert(field.clone(), value.clone());
+                }
+                Processor::Cast { field, to } => {
+                    if let Some(value) = event.remove(field)
+                        && let Some(value) = to.cast(value)
+                    {
+                        event.insert(field.clone(), value);
+                    }
+                }
+                Processor::Copy { from, to } => {
+                    if let Some(value) = event.get(from).cloned() {
+                        event.insert(to.clone(), value);
+                    }
+                }
+                Processor::DropEvent { condition } => {
+                    if condition.holds(event) {
+                        return false;
+                    }
+                }
+            }
+        }
+
+        true
+    }
+}
+
+impl CastType {
+    fn cast(self, value: Value) -> Option<Value> {
+        if value.is_null() {
+            return Some(value);
+        }
+        match (self, value) {
+            (CastType::String, value @ Value::String(_)) => Some(value),
+            (CastType::String, value) => Some(Value::String(value.to_string())),
+            (CastType::Int, Value::Number(n)) => n
+                .as_i64()
+                .or_else(|| n.as_f64().filter(|f| f.fract() == 0.0).map(|f| f as i64))
+                .map(Value::from),
+            (CastType::Int, Value::String(s)) => s.trim().parse::<i64>().ok().map(Value::from),
+            (CastType::Int, Value::Bool(b)) => Some(Value::from(i64::from(b))),
+            (CastType::Float, Value::Number(n)) => n.as_f64().map(Value::from),
+            (CastType::Float, Value::String(s)) => s
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .and_then(Number::from_f64)
+                .map(Value::Number),
+            (CastType::Bool, value @ Value::Bool(_)) => Some(value),
+            (CastType::Bool, Value::String(s)) => match s.trim().to_ascii_lowercase().as_str() {
+                "true" => Some(Value::Bool(true)),
+                "false" => Some(Value::Bool(false)),
+                _ => None,
+            },
+            (CastType::Bool, Value::Number(n)) => match n.as_i64() {
+                Some(0) => Some(Value::Bool(false)),
+                Some(1) => Some(Value::Bool(true)),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+impl Condition {
+    pub(crate) fn field(&self) -> &String {
+        match self {
+            Condition::Equals { field, .. }
+            | Condition::NotEquals { field, .. }
+            | Condition::Matches { field, .. }
+            | Condition::Exists { field }
+            | Condition::Missing { field } => field,
+        }
+    }
+
+    pub(crate) fn holds(&self, event: &Map<String, Value>) -> bool {
+        match self {
+            Condition::Equals { field, value } => event.get(field) == Some(value),
+            Condition::NotEquals { field, value } => event.get(field) != Some(value),
+            Condition::Matches { field, pattern } => event
+                .get(field)
+                .and_then(Value::as_str)
+                .is_some_and(|s| pattern.is_match(s)),
+            Condition::Exists { field } => event.contains_key(field),
+            Condition::Missing { field } => !event.contains_key(field),
+        }
+    }
+}
+
+mod regex_serde {
+    use regex::Regex;
+    use serde::{Deserialize, Deserializer, Serializer, de::Error};
+
+    pub fn serialize<S: Serializer>(regex: &Regex, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(regex.as_str())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Regex, D::Error> {
+        let pattern = String::deserialize(deserializer)?;
+        Regex::new(&pattern).map_err(D::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn apply_pipeline() {
+        let pipeline: Pipeline = serde_json::from_value(json!([
+            {"op": "drop_event", "if": {"matches": {"field": "path", "pattern": "^/health"}}},
+            {"op": "drop", "fields": ["password", "token"]},
+            {"op": "rename", "from": "msg", "to": "message"},
+            {"op": "copy", "from": "message", "to": "raw"},
+            {"op": "set", "field": "env", "value": "prod"},
+            {"op": "cast", "field": "status", "to": "int"},
+            {"op": "cast", "field": "latency", "to": "float"}
+        ]))
+        .unwrap();
+        pipeline.validate().unwrap();
+
+        let events = json!([
+            {"path": "/healthz", "msg": "ok"},
+            {"path": "/login", "msg": "denied", "password": "x", "status": "401", "latency": "n/a"}
+        ]);
+        assert_eq!(
+            pipeline.apply(events).unwrap(),
+            json!([{
+                "path": "/login",
+                "message": "denied",
+                "raw": "denied",
+                "env": "prod",
+                "status": 401
+            }])
+        );
+        assert!(pipeline.apply(json!({"path": "/health"})).is_none());
+
+        let invalid: Pipeline =
+            serde_json::from_value(json!([{"op": "rename", "from": "a", "to": "a"}])).unwrap();
+        assert_eq!(
+            invalid.validate().unwrap_err().to_string(),
+            "processor 0: from and to are the same field"
+        );
+    }
+}
@@ -0,0 +1,280 @@
+/*
+ * Parseable Server (C) 2022 - 2025 Parseable, Inc.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ */
+
+//! Per-stream sampling and rate limiting, applied to events on ingestion.
+//! Kept events carry their sample weight in [`SAMPLE_WEIGHT_KEY`], the number of ingested
+//! events each of them stands for, such that counts can be re-scaled at query time.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use super::{SAMPLE_WEIGHT_KEY, processors::Condition};
+use crate::LOCK_EXPECT;
+
+/// Rate limits are tracked for at most this many values of the field at once,
+/// events with other values share a single limit once it is reached
+const MAX_RATE_LIMIT_KEYS: usize = 10_000;
+
+#[derive(Debug, thiserror::Error)]
+#[error("{0}")]
+pub struct Error(&'static str);
+
+/// The sampling policy of a stream, stored in `stream.json`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Sampling {
+    /// Percentage of events kept, between 0 and 100
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub percentage: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate_limit: Option<RateLimit>,
+    /// Events for which any of the conditions hold are always kept, with a weight of 1
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub always_keep: Vec<Condition>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RateLimit {
+    /// Events are limited per value of the field, all events share the limit if not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub field: Option<String>,
+    pub events_per_sec: u32,
+}
+
+impl Sampling {
+    pub fn is_empty(&self) -> bool {
+        self.percentage.is_none() && self.rate_limit.is_none()
+    }
+
+    pub fn validate(&self) -> Result<(), Error> {
+        if self
+            .percentage
+            .is_some_and(|percentage| !(0.0..=100.0).contains(&percentage))
+        {
+            return Err(Error("percentage should be between 0 and 100"));
+        }
+        if let Some(rate_limit) = &self.rate_limit {
+            if rate_limit.events_per_sec == 0 {
+                return Err(Error("events_per_sec should be greater than 0"));
+            }
+            if rate_limit
+                .field
+                .as_ref()
+                .is_some_and(|field| field.is_empty())
+            {
+                return Err(Error("rate limit field can't be empty"));
+            }
+        }
+        if self
+            .always_keep
+            .iter()
+            .any(|condition| condition.field().is_empty())
+        {
+            return Err(Error("field names can't be empty"));
+        }
+
+        Ok(())
+    }
+
+    /// Samples the events of `json`, an object or an array of objects, at the unix time `now`
+    /// in seconds, `None` if all of the events are dropped
+    pub fn apply(&self, json: Value, limiter: &Mutex<RateLimiter>, now: i64) -> Option<Value> {
+        if self.is_empty() {
+            return Some(json);
+        }
+
+        let mut limiter = limiter.lock().expect(LOCK_EXPECT);
+        match json {
+            Value::Object(mut event) => self
+                .sample_event(&mut event, &mut limiter, now)
+                .then_some(Value::Object(event)),
+            Value::Array(events) => {
+                let events: Vec<Value> = events
+                    .into_iter()
+                    .filter_map(|event| match event {
+                        Value::Object(mut event) => self
+                            .sample_event(&mut event, &mut limiter, now)
+                            .then_some(Value::Object(event)),
+                        event => Some(event),
+                    })
+                    .collect();
+                (!events.is_empty()).then_some(Value::Array(events))
+            }
+            json => Some(json),
+        }
+    }
+
+    /// Returns `false` if the event is to be dropped, else sets its weight
+    fn sample_event(
+        &self,
+        event: &mut Map<String, Value>,
+        limiter: &mut RateLimiter,
+        now: i64,
+    ) -> bool {
+        let mut weight = 1.0;
+        if !self
+            .always_keep
+            .iter()
+            .any(|condition| condition.holds(event))
+        {
+            if let Some(percentage) = self.percentage
+                && percentage < 100.0
+            {
+                if (limiter.random)() * 100.0 >= percentage {
+                    return false;
+                }
+                weight *= 100.0 / percentage;
+            }
+            if let Some(rate_limit) = &self.rate_limit {
+                let key = match rate_limit.field.as_ref().and_then(|field| event.get(field)) {
+                    Some(Value::String(value)) => value.clone(),
+                    Some(value) => value.to_string(),
+                    None => String::new(),
+                };
+                match limiter.admit(key, rate_limit.events_per_sec, now) {
+                    Some(rate_weight) => weight *= rate_weight,
+                    None => return false,
+                }
+            }
+        }
+        event.insert(SAMPLE_WEIGHT_KEY.to_owned(), Value::from(weight));
+
+        true
+    }
+}
+
+/// Per-key event counts of the current and previous second, kept per stream
+#[derive(Debug)]
+pub struct RateLimiter {
+    windows: HashMap<String, Window>,
+    /// Source of the uniform numbers in `[0, 1)` events are sampled with
+    random: fn() -> f64,
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self {
+            windows: HashMap::new(),
+            random: rand::random::<f64>,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Window {
+    second: i64,
+    seen: u64,
+    kept: u64,
+    previous_seen: u64,
+    /// Events dropped over the limit, added to the weight of the next kept event
+    dropped: u64,
+}
+
+impl RateLimiter {
+    /// Returns the weight of the event if it is kept.
+    /// Once a key exceeded its limit in the previous second, its events are kept with a
+    /// probability of `limit / previous_seen`, such that the weight estimates the events
+    /// it stands for. Never more than `limit` events are kept per second, those dropped over
+    /// the limit, as in the first second a key exceeds it, are added to the next kept one.
+    fn admit(&mut self, mut key: String, limit: u32, now: i64) -> Option<f64> {
+        if !self.windows.contains_key(&key) && self.windows.len() >= MAX_RATE_LIMIT_KEYS {
+            self.windows.retain(|_, window| window.second >= now - 1);
+            if self.windows.len() >= MAX_RATE_LIMIT_KEYS {
+                key = String::new();
+            }
+        }
+
+        let window = self.windows.entry(key).or_default();
+        if window.second != now {
+            window.previous_seen = if window.second == now - 1 {
+                window.seen
+            } else {
+                0
+            };
+            window.second = now;
+            window.seen = 0;
+            window.kept = 0;
+        }
+        window.seen += 1;
+
+        let limit = u64::from(limit);
+        if window.kept >= limit {
+            window.dropped += 1;
+            return None;
+        }
+        let probability = if window.previous_seen > limit {
+            limit as f64 / window.previous_seen as f64
+        } else {
+            1.0
+        };
+        if (self.random)() >= probability {
+            return None;
+        }
+        window.kept += 1;
+
+        Some(1.0 / probability + std::mem::take(&mut window.dropped) as f64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn weights(json: Option<Value>) -> Vec<f64> {
+        json.into_iter()
+            .flat_map(|json| json.as_array().cloned().unwrap_or_default())
+            .map(|event| event[SAMPLE_WEIGHT_KEY].as_f64().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn rate_limit_per_key() {
+        let sampling: Sampling = serde_json::from_value(json!({
+            "rate_limit": {"field": "host", "events_per_sec": 2},
+            "always_keep": [{"equals": {"field": "level", "value": "error"}}]
+        }))
+        .unwrap();
+        sampling.validate().unwrap();
+        // every event passes the random draw, such that weights are deterministic
+        let limiter = Mutex::new(RateLimiter {
+            random: || 0.0,
+            ..Default::default()
+        });
+
+        let mut events: Vec<Value> = (0..5).map(|_| json!({"host": "a"})).collect();
+        events.push(json!({"host": "b"}));
+        events.push(json!({"host": "a", "level": "error"}));
+        let kept = sampling.apply(Value::Array(events.clone()), &limiter, 10);
+        assert_eq!(weights(kept), vec![1.0, 1.0, 1.0, 1.0]);
+
+        // 5 events of host a were counted in the previous second, the error one being kept
+        // regardless, so each kept one stands for 2.5, the first also for the 3 dropped
+        // over the limit
+        let kept = sampling.apply(Value::Array(events), &limiter, 11);
+        assert_eq!(weights(kept), vec![5.5, 2.5, 1.0, 1.0]);
+
+        let none: Sampling = serde_json::from_value(json!({"percentage": 0})).unwrap();
+        assert!(none.apply(json!([{"host": "a"}]), &limiter, 12).is_none());
+        let invalid: Sampling = serde_json::from_value(json!({"percentage": 120})).unwrap();
+        assert!(invalid.validate().is_err());
+    }
+}
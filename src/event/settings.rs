@@ -0,0 +1,131 @@
+/*
+ * Parseable Server (C) 2022 - 2025 Parseable, Inc.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ */
+
+//! The ingestion settings of a stream, set, stored and synced with ingestors as a whole
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::{
+    dedup::{self, Dedup},
+    geoip::{self, GeoIp},
+    processors::{self, Pipeline},
+    redaction::{self, Redaction},
+    sampling::{self, RateLimiter, Sampling},
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("Invalid processors: {0}")]
+    Processors(#[from] processors::Error),
+    #[error("Invalid redaction rules: {0}")]
+    Redaction(#[from] redaction::Error),
+    #[error("Invalid sampling policy: {0}")]
+    Sampling(#[from] sampling::Error),
+    #[error("Invalid dedup option: {0}")]
+    Dedup(#[from] dedup::Error),
+    #[error("Invalid GeoIP fields: {0}")]
+    GeoIp(#[from] geoip::Error),
+}
+
+/// The ingestion settings of a stream, stored flattened in `stream.json`. Settings left
+/// out are disabled.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StreamSettings {
+    #[serde(default, skip_serializing_if = "Pipeline::is_empty")]
+    pub processors: Pipeline,
+    #[serde(default, skip_serializing_if = "Redaction::is_empty")]
+    pub redaction: Redaction,
+    #[serde(default, skip_serializing_if = "Sampling::is_empty")]
+    pub sampling: Sampling,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dedup: Option<Dedup>,
+    #[serde(default, skip_serializing_if = "GeoIp::is_empty")]
+    pub geoip: GeoIp,
+}
+
+impl StreamSettings {
+    pub fn validate(&self) -> Result<(), Error> {
+        self.processors.validate()?;
+        self.redaction.validate()?;
+        self.sampling.validate()?;
+        if let Some(dedup) = &self.dedup {
+            dedup.validate()?;
+        }
+        self.geoip.validate()?;
+
+        Ok(())
+    }
+
+    /// Whether [`Self::apply`] may change or drop events, dedup runs on the batch instead
+    pub fn transforms_events(&self) -> bool {
+        !(self.processors.is_empty()
+            && self.sampling.is_empty()
+            && self.geoip.is_empty()
+            && self.redaction.is_empty())
+    }
+
+    /// Runs the processors, sampling, GeoIP enrichment and redaction, in this order, on the
+    /// flattened events of `json`, an object or an array of objects. Returns `None` if all
+    /// of the events were dropped.
+    pub fn apply(
+        &self,
+        stream_name: &str,
+        json: Value,
+        rate_limiter: &Mutex<RateLimiter>,
+        p_custom_fields: &HashMap<String, String>,
+    ) -> Option<Value> {
+        let json = self.processors.apply(json)?;
+        // sampling sees the output of the processors, rate limited as events are received
+        let mut json = self
+            .sampling
+            .apply(json, rate_limiter, Utc::now().timestamp())?;
+        // kept events are enriched before redaction, such that IPs can be redacted
+        // while keeping their location
+        self.geoip.apply(&mut json, p_custom_fields);
+        // and redacted, such that PII never reaches staging
+        self.redaction.apply(stream_name, &mut json);
+
+        Some(json)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn settings_left_out_are_disabled() {
+        let settings: StreamSettings =
+            serde_json::from_value(json!({"dedup": {"window_secs": 60}})).unwrap();
+
+        assert!(settings.validate().is_ok());
+        assert!(settings.processors.is_empty());
+        assert!(settings.sampling.is_empty());
+        assert_eq!(
+            serde_json::to_value(&settings).unwrap(),
+            json!({"dedup": {"window_secs": 60, "max_keys": 1_000_000}})
+        );
+    }
+}
@@ -0,0 +1,171 @@
+/*
+ * Parseable Server (C) 2022 - 2025 Parseable, Inc.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ */
+
+use std::net::{IpAddr, SocketAddr};
+
+use serde_json::Value;
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    net::TcpListener,
+};
+use tracing::{error, info, warn};
+
+use crate::{
+    event::{FORMAT_KEY, format::LogSource},
+    handlers::{
+        TelemetryType,
+        http::{
+            ingest::PostError,
+            modal::utils::ingest_utils::{
+                collect_custom_fields, flatten_and_push_logs, setup_stream_for_ingestion,
+            },
+        },
+    },
+    parseable::PARSEABLE,
+};
+
+pub mod protocol;
+
+/// Size of the reads off a connection
+const READ_BUFFER_SIZE: usize = 64 * 1024;
+
+/// Starts the Fluent Forward listener if `P_FLUENT_FORWARD_PORT` is set. Like the syslog
+/// listeners it is meant for trusted networks, the shared key handshake is not supported.
+pub fn init() {
+    let Some(port) = PARSEABLE.options.fluent_forward_port else {
+        return;
+    };
+
+    tokio::spawn(async move {
+        if let Err(err) = serve(port).await {
+            error!("Fluent Forward listener on port {port} stopped: {err}");
+        }
+    });
+}
+
+async fn serve(port: u16) -> std::io::Result<()> {
+    let mut addr: SocketAddr = PARSEABLE
+        .options
+        .address
+        .parse()
+        .expect("valid socket address");
+    addr.set_port(port);
+    let socket = TcpListener::bind(addr).await?;
+    info!("Fluent Forward listener started on port {port}");
+
+    loop {
+        let (conn, peer) = socket.accept().await?;
+        tokio::spawn(async move {
+            if let Err(err) = handle_connection(conn, peer.ip()).await {
+                warn!("Fluent Forward connection from {peer} closed: {err}");
+            }
+        });
+    }
+}
+
+// Reads messages off the connection as they arrive, each message is pushed to
+// its stream before acking it, if asked to, and reading the next one
+async fn handle_connection(
+    mut conn: impl AsyncRead + AsyncWrite + Unpin,
+    peer: IpAddr,
+) -> std::io::Result<()> {
+    let max_size = PARSEABLE.options.max_event_payload_size;
+    let mut buf = Vec::new();
+    let mut read_buf = vec![0; READ_BUFFER_SIZE];
+    let mut scanner = protocol::ValueScanner::default();
+
+    loop {
+        // a message is only decoded once all of it is buffered
+        while let Some(len) = scanner.scan(&buf).map_err(invalid_data)? {
+            let (value, _) = protocol::read_value(&buf[..len])
+                .map_err(invalid_data)?
+                .ok_or_else(|| invalid_data("truncated message"))?;
+            buf.drain(..len);
+            let message = protocol::decode_message(value, max_size).map_err(invalid_data)?;
+            let chunk = message.chunk.clone();
+            match push(message, peer).await {
+                Ok(()) => {
+                    if let Some(chunk) = chunk {
+                        conn.write_all(&protocol::ack(&chunk)).await?;
+                    }
+                }
+                // without an ack the client retries the chunk, otherwise it's dropped
+                Err(err) => error!("Failed to ingest Fluent Forward message: {err}"),
+            }
+        }
+
+        if buf.len() > max_size {
+            return Err(invalid_data("message exceeds the payload limit"));
+        }
+        let read = conn.read(&mut read_buf).await?;
+        if read == 0 {
+            return Ok(());
+        }
+        buf.extend_from_slice(&read_buf[..read]);
+    }
+}
+
+fn invalid_data(err: impl ToString) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, err.to_string())
+}
+
+/// Tags are dot separated, e.g. `kube.var.log`, which becomes the `kube_var_log` stream
+fn stream_name(tag: &str) -> String {
+    tag.chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+async fn push(message: protocol::ForwardMessage, peer: IpAddr) -> Result<(), PostError> {
+    if message.records.is_empty() {
+        return Ok(());
+    }
+
+    let stream_name = stream_name(&message.tag);
+    setup_stream_for_ingestion(&stream_name, LogSource::Fluent, TelemetryType::Logs).await?;
+
+    let mut p_custom_fields = collect_custom_fields("", &peer.to_string(), std::iter::empty());
+    p_custom_fields.insert(FORMAT_KEY.to_string(), LogSource::Fluent.to_string());
+
+    flatten_and_push_logs(
+        Value::Array(message.records),
+        &stream_name,
+        &LogSource::Fluent,
+        &p_custom_fields,
+        None,
+        TelemetryType::Logs,
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tag_as_stream_name() {
+        assert_eq!(stream_name("kube.var.log"), "kube_var_log");
+        assert_eq!(stream_name("app-1_access"), "app-1_access");
+    }
+}
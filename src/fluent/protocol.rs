@@ -0,0 +1,479 @@
+/*
+ * Parseable Server (C) 2022 - 2025 Parseable, Inc.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ */
+
+//! Decoding of the Fluent Forward protocol v1, see
+//! https://github.com/fluent/fluentd/wiki/Forward-Protocol-Specification-v1
+
+use std::io::{Cursor, ErrorKind, Read};
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use flate2::read::MultiGzDecoder;
+use rmpv::Value as MsgPack;
+use serde_json::{Map, Number, Value};
+
+/// Extension type of `EventTime`, seconds and nanoseconds as big endian u32s
+const EVENT_TIME_EXT: i8 = 0;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("Malformed msgpack: {0}")]
+    Decode(#[from] rmpv::decode::Error),
+    #[error("Malformed forward message: {0}")]
+    Invalid(&'static str),
+    #[error("Failed to decompress entries: {0}")]
+    Decompress(#[from] std::io::Error),
+}
+
+/// A message in any of the modes, i.e. Message, Forward, PackedForward and CompressedPackedForward
+#[derive(Debug, PartialEq)]
+pub struct ForwardMessage {
+    pub tag: String,
+    /// Records of the message as json objects, along with their time as `timestamp`
+    pub records: Vec<Value>,
+    /// Chunk id of a message that expects an ack
+    pub chunk: Option<String>,
+}
+
+/// Reads a single msgpack value off the start of the buffer, returning it along with
+/// the number of bytes read, or None if the buffer doesn't hold a complete value yet
+pub fn read_value(buf: &[u8]) -> Result<Option<(MsgPack, usize)>, Error> {
+    let mut cursor = Cursor::new(buf);
+    match rmpv::decode::read_value(&mut cursor) {
+        Ok(value) => Ok(Some((value, cursor.position() as usize))),
+        Err(
+            rmpv::decode::Error::InvalidMarkerRead(err) | rmpv::decode::Error::InvalidDataRead(err),
+        ) if err.kind() == ErrorKind::UnexpectedEof => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Finds where the msgpack value at the start of a buffer ends without decoding it, such
+/// that a value arriving over several reads is only decoded once it's complete. Each scan
+/// resumes where the previous one stopped, as more of the value is buffered.
+#[derive(Debug, Default)]
+pub struct ValueScanner {
+    pos: usize,
+    /// Items left to scan of the arrays and maps the scan is in, innermost last
+    remaining: Vec<u64>,
+}
+
+impl ValueScanner {
+    /// Returns the length of the value once the buffer holds all of it, the scanner is
+    /// then reset for the value that follows, which starts at the returned length
+    pub fn scan(&mut self, buf: &[u8]) -> Result<Option<usize>, Error> {
+        loop {
+            let Some((size, items)) = item_size(&buf[self.pos..])? else {
+                return Ok(None);
+            };
+            if buf.len() - self.pos < size {
+                return Ok(None);
+            }
+            self.pos += size;
+
+            if items > 0 {
+                self.remaining.push(items);
+                continue;
+            }
+            // the item is complete, as is any container it was the last item of
+            loop {
+                let Some(left) = self.remaining.last_mut() else {
+                    return Ok(Some(std::mem::take(&mut self.pos)));
+                };
+                *left -= 1;
+                if *left > 0 {
+                    break;
+                }
+                self.remaining.pop();
+            }
+        }
+    }
+}
+
+// Size of the item at the start of the buffer, including its payload for scalars, along
+// with the number of items in it for arrays and maps, or None if the marker or length of
+// the item isn't buffered yet
+fn item_size(buf: &[u8]) -> Result<Option<(usize, u64)>, Error> {
+    let Some(&marker) = buf.first() else {
+        return Ok(None);
+    };
+    // length of the item as a big endian uint of `n` bytes following the marker
+    let length = |n: usize| {
+        buf.get(1..1 + n)
+            .map(|bytes| bytes.iter().fold(0u64, |len, &b| (len << 8) | b as u64))
+    };
+    let sized = |n: usize, extra: usize| length(n).map(|len| (1 + n + extra + len as usize, 0));
+    let counted = |n: usize, per_item: u64| length(n).map(|len| (1 + n, len * per_item));
+
+    let item = match marker {
+        0x00..=0x7f | 0xc0 | 0xc2 | 0xc3 | 0xe0..=0xff => Some((1, 0)),
+        0x80..=0x8f => Some((1, (marker & 0x0f) as u64 * 2)),
+        0x90..=0x9f => Some((1, (marker & 0x0f) as u64)),
+        0xa0..=0xbf => Some((1 + (marker & 0x1f) as usize, 0)),
+        0xc4 | 0xd9 => sized(1, 0),
+        0xc5 | 0xda => sized(2, 0),
+        0xc6 | 0xdb => sized(4, 0),
+        0xc7 => sized(1, 1),
+        0xc8 => sized(2, 1),
+        0xc9 => sized(4, 1),
+        0xca => Some((5, 0)),
+        0xcb => Some((9, 0)),
+        0xcc | 0xd0 => Some((2, 0)),
+        0xcd | 0xd1 => Some((3, 0)),
+        0xce | 0xd2 => Some((5, 0)),
+        0xcf | 0xd3 => Some((9, 0)),
+        0xd4..=0xd8 => Some((2 + (1 << (marker - 0xd4)), 0)),
+        0xdc => counted(2, 1),
+        0xdd => counted(4, 1),
+        0xde => counted(2, 2),
+        0xdf => counted(4, 2),
+        0xc1 => return Err(Error::Invalid("reserved msgpack marker")),
+    };
+
+    Ok(item)
+}
+
+/// Decodes a message, `max_size` bounds the size of decompressed entries
+pub fn decode_message(value: MsgPack, max_size: usize) -> Result<ForwardMessage, Error> {
+    let MsgPack::Array(mut parts) = value else {
+        return Err(Error::Invalid("message is not an array"));
+    };
+    if parts.len() < 2 {
+        return Err(Error::Invalid("message is missing the tag or entries"));
+    }
+
+    let options = match parts.len() {
+        // Message mode is [tag, time, record] or [tag, time, record, option]
+        3 if is_time(&parts[1]) => MsgPack::Nil,
+        4 if is_time(&parts[1]) => parts.pop().unwrap_or(MsgPack::Nil),
+        3 => parts.pop().unwrap_or(MsgPack::Nil),
+        _ => MsgPack::Nil,
+    };
+    let mut parts = parts.into_iter();
+    let tag = match parts.next() {
+        Some(MsgPack::String(tag)) => tag.into_str().ok_or(Error::Invalid("tag is not utf8"))?,
+        _ => return Err(Error::Invalid("tag is not a string")),
+    };
+    let option = |key: &str| {
+        options.as_map().and_then(|options| {
+            options
+                .iter()
+                .find(|(k, _)| k.as_str() == Some(key))
+                .map(|(_, v)| v)
+        })
+    };
+
+    let records = match parts.next() {
+        // Forward mode, an array of [time, record] entries
+        Some(MsgPack::Array(entries)) => entries
+            .into_iter()
+            .map(decode_entry)
+            .collect::<Result<_, _>>()?,
+        // PackedForward mode, a stream of [time, record] entries, possibly gzip compressed
+        Some(MsgPack::Binary(entries)) => decode_packed(&entries, option("compressed"), max_size)?,
+        Some(MsgPack::String(entries)) => {
+            decode_packed(entries.as_bytes(), option("compressed"), max_size)?
+        }
+        // Message mode, a single record
+        Some(time) => {
+            let record = parts
+                .next()
+                .ok_or(Error::Invalid("message is missing the record"))?;
+            vec![decode_entry(MsgPack::Array(vec![time, record]))?]
+        }
+        None => unreachable!("message has at least 2 parts"),
+    };
+
+    let chunk = option("chunk").and_then(|chunk| chunk.as_str().map(str::to_owned));
+
+    Ok(ForwardMessage {
+        tag,
+        records,
+        chunk,
+    })
+}
+
+/// Response to a message with a chunk id, sent once its records are in staging
+pub fn ack(chunk: &str) -> Vec<u8> {
+    let response = MsgPack::Map(vec![(MsgPack::from("ack"), MsgPack::from(chunk))]);
+    let mut buf = vec![];
+    rmpv::encode::write_value(&mut buf, &response).expect("writing to a vec does not fail");
+    buf
+}
+
+fn decode_packed(
+    entries: &[u8],
+    compressed: Option<&MsgPack>,
+    max_size: usize,
+) -> Result<Vec<Value>, Error> {
+    let decompressed;
+    let mut entries = match compressed.and_then(MsgPack::as_str) {
+        Some("gzip") => {
+            let mut buf = vec![];
+            MultiGzDecoder::new(entries)
+                .take(max_size as u64 + 1)
+                .read_to_end(&mut buf)?;
+            if buf.len() > max_size {
+                return Err(Error::Invalid(
+                    "decompressed entries exceed the payload limit",
+                ));
+            }
+            decompressed = buf;
+            decompressed.as_slice()
+        }
+        Some(_) => return Err(Error::Invalid("unsupported compression")),
+        None => entries,
+    };
+
+    let mut records = vec![];
+    while !entries.is_empty() {
+        let (entry, read) = read_value(entries)?.ok_or(Error::Invalid("truncated entry"))?;
+        records.push(decode_entry(entry)?);
+        entries = &entries[read..];
+    }
+
+    Ok(records)
+}
+
+// Converts a [time, record] entry into a json object, with the time as `timestamp`
+// unless the record has a field of the same name
+fn decode_entry(entry: MsgPack) -> Result<Value, Error> {
+    let MsgPack::Array(entry) = entry else {
+        return Err(Error::Invalid("entry is not an array"));
+    };
+    let [time, record] = <[MsgPack; 2]>::try_from(entry)
+        .map_err(|_| Error::Invalid("entry is not a [time, record] pair"))?;
+
+    let time = event_time(&time).ok_or(Error::Invalid("invalid event time"))?;
+    let Value::Object(mut record) = to_json(record) else {
+        return Err(Error::Invalid("record is not a map"));
+    };
+    record
+        .entry("timestamp")
+        .or_insert_with(|| Value::String(time.to_rfc3339_opts(SecondsFormat::AutoSi, true)));
+
+    Ok(Value::Object(record))
+}
+
+fn is_time(value: &MsgPack) -> bool {
+    matches!(
+        value,
+        MsgPack::Integer(_) | MsgPack::F32(_) | MsgPack::F64(_) | MsgPack::Ext(EVENT_TIME_EXT, _)
+    )
+}
+
+// Times are either unix epoch seconds or the `EventTime` extension with nanoseconds
+fn event_time(value: &MsgPack) -> Option<DateTime<Utc>> {
+    match value {
+        MsgPack::Integer(seconds) => DateTime::from_timestamp(seconds.as_i64()?, 0),
+        MsgPack::F32(_) | MsgPack::F64(_) => {
+            let seconds = value.as_f64()?;
+            Some(DateTime::from_timestamp_nanos((seconds * 1e9) as i64))
+        }
+        MsgPack::Ext(EVENT_TIME_EXT, data) if data.len() == 8 => {
+            let seconds = u32::from_be_bytes(data[..4].try_into().ok()?);
+            let nanos = u32::from_be_bytes(data[4..].try_into().ok()?);
+            DateTime::from_timestamp(seconds as i64, nanos)
+        }
+        _ => None,
+    }
+}
+
+fn to_json(value: MsgPack) -> Value {
+    match value {
+        MsgPack::Nil => Value::Null,
+        MsgPack::Boolean(b) => Value::Bool(b),
+        MsgPack::Integer(i) => i
+            .as_i64()
+            .map(Value::from)
+            .or_else(|| i.as_u64().map(Value::from))
+            .unwrap_or(Value::Null),
+        MsgPack::F32(f) => Number::from_f64(f as f64).map_or(Value::Null, Value::Number),
+        MsgPack::F64(f) => Number::from_f64(f).map_or(Value::Null, Value::Number),
+        MsgPack::String(s) => Value::String(String::from_utf8_lossy(s.as_bytes()).into_owned()),
+        MsgPack::Binary(b) => Value::String(String::from_utf8_lossy(&b).into_owned()),
+        MsgPack::Array(values) => Value::Array(values.into_iter().map(to_json).collect()),
+        MsgPack::Map(pairs) => {
+            let map: Map<String, Value> = pairs
+                .into_iter()
+                .map(|(key, value)| {
+                    let key = match key {
+                        MsgPack::String(s) => String::from_utf8_lossy(s.as_bytes()).into_owned(),
+                        key => key.to_string(),
+                    };
+                    (key, to_json(value))
+                })
+                .collect();
+            Value::Object(map)
+        }
+        MsgPack::Ext(EVENT_TIME_EXT, _) => event_time(&value)
+            .map(|time| Value::String(time.to_rfc3339_opts(SecondsFormat::AutoSi, true)))
+            .unwrap_or(Value::Null),
+        MsgPack::Ext(..) => Value::Null,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::{Compression, write::GzEncoder};
+    use serde_json::json;
+
+    use super::*;
+
+    fn encode(value: &MsgPack) -> Vec<u8> {
+        let mut buf = vec![];
+        rmpv::encode::write_value(&mut buf, value).unwrap();
+        buf
+    }
+
+    fn entry(seconds: u32, nanos: u32, message: &str) -> MsgPack {
+        let mut time = seconds.to_be_bytes().to_vec();
+        time.extend(nanos.to_be_bytes());
+        MsgPack::Array(vec![
+            MsgPack::Ext(EVENT_TIME_EXT, time),
+            MsgPack::Map(vec![(MsgPack::from("message"), MsgPack::from(message))]),
+        ])
+    }
+
+    fn records() -> Vec<Value> {
+        vec![
+            json!({"message": "a", "timestamp": "2023-11-14T22:13:20.500Z"}),
+            json!({"message": "b", "timestamp": "2023-11-14T22:13:21Z"}),
+        ]
+    }
+
+    #[test]
+    fn decode_message_mode() {
+        let message = MsgPack::Array(vec![
+            MsgPack::from("app.access"),
+            MsgPack::from(1_700_000_000),
+            MsgPack::Map(vec![
+                (MsgPack::from("status"), MsgPack::from(200)),
+                (MsgPack::from("path"), MsgPack::Binary(b"/".to_vec())),
+            ]),
+            MsgPack::Map(vec![(MsgPack::from("chunk"), MsgPack::from("abc"))]),
+        ]);
+
+        assert_eq!(
+            decode_message(message, usize::MAX).unwrap(),
+            ForwardMessage {
+                tag: "app.access".to_owned(),
+                records: vec![
+                    json!({"status": 200, "path": "/", "timestamp": "2023-11-14T22:13:20Z"})
+                ],
+                chunk: Some("abc".to_owned()),
+            }
+        );
+    }
+
+    #[test]
+    fn decode_forward_mode() {
+        let message = MsgPack::Array(vec![
+            MsgPack::from("app"),
+            MsgPack::Array(vec![
+                entry(1_700_000_000, 500_000_000, "a"),
+                entry(1_700_000_001, 0, "b"),
+            ]),
+        ]);
+
+        let message = decode_message(message, usize::MAX).unwrap();
+
+        assert_eq!(message.records, records());
+        assert_eq!(message.chunk, None);
+    }
+
+    #[test]
+    fn decode_packed_forward_modes() {
+        let mut packed = encode(&entry(1_700_000_000, 500_000_000, "a"));
+        packed.extend(encode(&entry(1_700_000_001, 0, "b")));
+        let message = MsgPack::Array(vec![
+            MsgPack::from("app"),
+            MsgPack::Binary(packed.clone()),
+            MsgPack::Map(vec![(MsgPack::from("chunk"), MsgPack::from("p1"))]),
+        ]);
+
+        let message = decode_message(message, usize::MAX).unwrap();
+        assert_eq!(message.records, records());
+        assert_eq!(message.chunk.as_deref(), Some("p1"));
+
+        let mut encoder = GzEncoder::new(vec![], Compression::default());
+        encoder.write_all(&packed).unwrap();
+        let compressed = MsgPack::Array(vec![
+            MsgPack::from("app"),
+            MsgPack::Binary(encoder.finish().unwrap()),
+            MsgPack::Map(vec![(MsgPack::from("compressed"), MsgPack::from("gzip"))]),
+        ]);
+
+        assert_eq!(
+            decode_message(compressed.clone(), usize::MAX)
+                .unwrap()
+                .records,
+            records()
+        );
+        assert!(decode_message(compressed, 8).is_err());
+    }
+
+    #[test]
+    fn read_partial_values() {
+        let buf = encode(&MsgPack::Array(vec![
+            MsgPack::from("app"),
+            MsgPack::from(1),
+        ]));
+
+        assert!(read_value(&buf[..buf.len() - 1]).unwrap().is_none());
+        assert_eq!(
+            read_value(&buf).unwrap().map(|(_, read)| read),
+            Some(buf.len())
+        );
+    }
+
+    #[test]
+    fn scan_values_read_in_parts() {
+        let first = encode(&MsgPack::Array(vec![
+            MsgPack::from("app"),
+            MsgPack::Array(vec![
+                entry(1_700_000_000, 0, "a"),
+                entry(1_700_000_001, 0, "b"),
+            ]),
+            MsgPack::Map(vec![(
+                MsgPack::from("chunk"),
+                MsgPack::Binary(vec![7; 300]),
+            )]),
+            MsgPack::Array(vec![]),
+            MsgPack::from(-1_000_000),
+        ]));
+        let mut buf = first.clone();
+        buf.extend(encode(&MsgPack::from(1.5)));
+
+        let mut scanner = ValueScanner::default();
+        for end in 0..first.len() {
+            assert_eq!(scanner.scan(&buf[..end]).unwrap(), None);
+        }
+        assert_eq!(scanner.scan(&buf).unwrap(), Some(first.len()));
+        assert_eq!(scanner.scan(&buf[first.len()..]).unwrap(), Some(9));
+    }
+
+    #[test]
+    fn ack_response() {
+        assert_eq!(
+            read_value(&ack("abc")).unwrap().unwrap().0,
+            MsgPack::Map(vec![(MsgPack::from("ack"), MsgPack::from("abc"))])
+        );
+    }
+}
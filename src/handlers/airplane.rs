@@ -18,30 +18,44 @@
 
 use arrow_array::RecordBatch;
 use arrow_flight::PollInfo;
+use arrow_flight::decode::FlightRecordBatchStream;
+use arrow_flight::error::FlightError;
 use arrow_flight::flight_service_server::FlightServiceServer;
 use arrow_schema::ArrowError;
-use serde_json::json;
+use chrono::Utc;
+use serde_json::{Value, json};
+use std::collections::HashMap;
 use std::net::SocketAddr;
 use std::time::Instant;
 use tonic::codec::CompressionEncoding;
 use tracing::{error, info};
 
-use futures_util::{Future, TryFutureExt};
+use futures_util::{Future, StreamExt, TryFutureExt, TryStreamExt};
 
 use tonic::transport::{Identity, Server, ServerTlsConfig};
 use tonic_web::GrpcWebLayer;
 
+use crate::event::format::{self, EventFormat, LogSource};
+use crate::handlers::TelemetryType;
 use crate::handlers::http::cluster::get_node_info;
+use crate::handlers::http::ingest::PostError;
+use crate::handlers::http::modal::utils::ingest_utils::{
+    setup_stream_for_ingestion, verify_dataset_fields_count,
+};
 use crate::handlers::http::modal::{NodeMetadata, NodeType};
 use crate::handlers::http::query::into_query;
 use crate::handlers::livetail::cross_origin_config;
+use crate::handlers::otel_grpc::get_custom_fields_from_metadata;
 use crate::metrics::QUERY_EXECUTE_TIME;
+use crate::option::Mode;
 use crate::parseable::PARSEABLE;
 use crate::query::{QUERY_SESSION, execute, resolve_stream_names};
+use crate::storage::StreamType;
 use crate::utils::arrow::flight::{
     append_temporary_events, get_query_from_ticket, into_flight_data, run_do_get_rpc,
     send_to_ingester,
 };
+use crate::utils::arrow::record_batches_to_json;
 use crate::utils::time::TimeRange;
 use crate::utils::user_auth_for_datasets;
 use arrow_flight::{
@@ -246,13 +260,73 @@ impl FlightService for AirServiceImpl {
         out.map_err(|e| *e)
     }
 
+    /// do_put ingests a stream of record batches into the stream named by the
+    /// path of the FlightDescriptor, sent along with the first FlightData message
     async fn do_put(
         &self,
-        _request: Request<Streaming<FlightData>>,
+        req: Request<Streaming<FlightData>>,
     ) -> Result<Response<Self::DoPutStream>, Status> {
-        Err(Status::unimplemented(
-            "do_put not implemented because we are only using flight for querying",
-        ))
+        if PARSEABLE.options.mode == Mode::Query {
+            return Err(Status::failed_precondition(
+                "Ingestion is not allowed in Query mode",
+            ));
+        }
+
+        let key = extract_session_key(req.metadata()).map_err(|e| *e)?;
+        let p_custom_fields = get_custom_fields_from_metadata(&req, &LogSource::Arrow);
+
+        let mut flight_data = req.into_inner();
+        let first = flight_data
+            .message()
+            .await?
+            .ok_or_else(|| Status::invalid_argument("do_put stream is empty"))?;
+        let stream_name = first
+            .flight_descriptor
+            .as_ref()
+            .and_then(|descriptor| descriptor.path.first())
+            .cloned()
+            .ok_or_else(|| {
+                Status::invalid_argument(
+                    "FlightDescriptor with the stream name as path is required",
+                )
+            })?;
+
+        // try authorize
+        match Users.authorize(key, rbac::role::Action::Ingest, Some(&stream_name), None) {
+            rbac::Response::Authorized => (),
+            rbac::Response::UnAuthorized => {
+                return Err(Status::permission_denied(
+                    "user is not authorized to access this resource",
+                ));
+            }
+            rbac::Response::ReloadRequired => {
+                return Err(Status::unauthenticated("reload required"));
+            }
+        }
+
+        setup_stream_for_ingestion(&stream_name, LogSource::Arrow, TelemetryType::Logs)
+            .await
+            .map_err(|err| Status::failed_precondition(err.to_string()))?;
+
+        let flight_data = stream::once(async { Ok(first) })
+            .chain(flight_data)
+            .map_err(FlightError::from);
+        let results = FlightRecordBatchStream::new_from_flight_data(flight_data).map(move |rb| {
+            let rb = rb.map_err(|err| Status::invalid_argument(err.to_string()))?;
+            let num_rows = rb.num_rows();
+            push_batch(rb, &stream_name, &p_custom_fields).map_err(|err| {
+                error!("Failed to ingest record batch into {stream_name}: {err}");
+                Status::invalid_argument(err.to_string())
+            })?;
+
+            Ok(PutResult {
+                app_metadata: json!({ "stream": stream_name, "rows": num_rows })
+                    .to_string()
+                    .into(),
+            })
+        });
+
+        Ok(Response::new(Box::pin(results)))
     }
 
     async fn do_action(
@@ -283,6 +357,69 @@ impl FlightService for AirServiceImpl {
     }
 }
 
+// pushes a single record batch to staging, adapting it to the stream schema
+fn push_batch(
+    rb: RecordBatch,
+    stream_name: &str,
+    p_custom_fields: &HashMap<String, String>,
+) -> Result<(), PostError> {
+    verify_dataset_fields_count(stream_name)?;
+
+    let stream = PARSEABLE.get_stream(stream_name)?;
+    let settings = stream.get_settings();
+    let origin_size = rb.get_array_memory_size() as u64;
+    // the fields added from the request are redacted as well, GeoIP still sees the source IP
+    let redacted_custom_fields = settings
+        .redaction
+        .apply_custom_fields(stream_name, p_custom_fields);
+    let schema = stream.get_schema_raw();
+    let static_schema_flag = stream.get_static_schema_flag();
+    let custom_partition = stream.get_custom_partition();
+    let time_partition = stream.get_time_partition();
+    let schema_version = stream.get_schema_version();
+
+    // the stream's settings run on JSON events, batches are only converted if there are any
+    let event = if settings.transforms_events() {
+        let rows = record_batches_to_json(&[rb])?;
+        let Some(json) = settings.apply(
+            stream_name,
+            Value::Array(rows.into_iter().map(Value::Object).collect()),
+            &stream.rate_limiter,
+            p_custom_fields,
+        ) else {
+            return Ok(());
+        };
+        format::json::Event::new(json, Utc::now()).into_event(
+            stream_name.to_owned(),
+            origin_size,
+            &schema,
+            static_schema_flag,
+            custom_partition.as_ref(),
+            time_partition.as_ref(),
+            schema_version,
+            StreamType::UserDefined,
+            &redacted_custom_fields,
+            TelemetryType::Logs,
+        )?
+    } else {
+        format::arrow::Event::new(rb, Utc::now()).into_event(
+            stream_name.to_owned(),
+            origin_size,
+            &schema,
+            static_schema_flag,
+            custom_partition.as_ref(),
+            time_partition.as_ref(),
+            schema_version,
+            StreamType::UserDefined,
+            &redacted_custom_fields,
+            TelemetryType::Logs,
+        )?
+    };
+    event.process()?;
+
+    Ok(())
+}
+
 pub fn server() -> impl Future<Output = Result<(), Box<dyn std::error::Error + Send>>> + Send {
     let mut addr: SocketAddr = PARSEABLE
         .options
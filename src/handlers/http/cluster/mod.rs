@@ -41,6 +41,8 @@ use url::Url;
 use utils::{IngestionStats, QueriedStats, StorageStats, check_liveness, to_url_string};
 
 use crate::INTRA_CLUSTER_CLIENT;
+use crate::event::format::known_schema::LogFormat;
+use crate::event::settings::StreamSettings;
 use crate::handlers::http::query::{Query, QueryError, TIME_ELAPSED_HEADER};
 use crate::metrics::prom_utils::Metrics;
 use crate::option::Mode;
@@ -52,6 +54,7 @@ use crate::storage::{ObjectStorageError, ObjectStoreFormat};
 
 use super::base_path_without_preceding_slash;
 use super::ingest::PostError;
+use super::log_format::LogFormatError;
 use super::logstream::error::StreamError;
 use super::modal::{IngestorMetadata, Metadata, NodeMetadata, NodeType, QuerierMetadata};
 use super::rbac::RBACError;
@@ -724,6 +727,138 @@ pub async fn sync_role_update_with_ingestors(
     .await
 }
 
+// forward the put log format request to all ingestors to keep them in sync
+pub async fn sync_log_format_with_ingestors(format: &LogFormat) -> Result<(), LogFormatError> {
+    let format = format.clone();
+
+    for_each_live_ingestor(move |ingestor| {
+        let url = format!(
+            "{}{}/logformat/{}/sync",
+            ingestor.domain_name,
+            base_path_without_preceding_slash(),
+            format.name
+        );
+
+        let format = format.clone();
+
+        async move {
+            let res = INTRA_CLUSTER_CLIENT
+                .put(url)
+                .header(header::AUTHORIZATION, &ingestor.token)
+                .header(header::CONTENT_TYPE, "application/json")
+                .json(&format)
+                .send()
+                .await
+                .map_err(|err| {
+                    error!(
+                        "Fatal: failed to forward request to ingestor: {}\n Error: {:?}",
+                        ingestor.domain_name, err
+                    );
+                    LogFormatError::Network(err)
+                })?;
+
+            if !res.status().is_success() {
+                error!(
+                    "failed to forward request to ingestor: {}\nResponse Returned: {:?}",
+                    ingestor.domain_name,
+                    res.text().await
+                );
+            }
+
+            Ok(())
+        }
+    })
+    .await
+}
+
+// forward the delete log format request to all ingestors to keep them in sync
+pub async fn sync_log_format_deletion_with_ingestors(name: &str) -> Result<(), LogFormatError> {
+    let name = name.to_owned();
+
+    for_each_live_ingestor(move |ingestor| {
+        let url = format!(
+            "{}{}/logformat/{}/sync",
+            ingestor.domain_name,
+            base_path_without_preceding_slash(),
+            name
+        );
+
+        async move {
+            let res = INTRA_CLUSTER_CLIENT
+                .delete(url)
+                .header(header::AUTHORIZATION, &ingestor.token)
+                .send()
+                .await
+                .map_err(|err| {
+                    error!(
+                        "Fatal: failed to forward request to ingestor: {}\n Error: {:?}",
+                        ingestor.domain_name, err
+                    );
+                    LogFormatError::Network(err)
+                })?;
+
+            if !res.status().is_success() {
+                error!(
+                    "failed to forward request to ingestor: {}\nResponse Returned: {:?}",
+                    ingestor.domain_name,
+                    res.text().await
+                );
+            }
+
+            Ok(())
+        }
+    })
+    .await
+}
+
+// forward the ingestion settings set for a stream to all ingestors to keep them in sync
+pub async fn sync_settings_with_ingestors(
+    stream_name: &str,
+    settings: &StreamSettings,
+) -> Result<(), StreamError> {
+    let stream_name = stream_name.to_owned();
+    let settings = settings.clone();
+
+    for_each_live_ingestor(move |ingestor| {
+        let url = format!(
+            "{}{}/logstream/{}/settings/sync",
+            ingestor.domain_name,
+            base_path_without_preceding_slash(),
+            stream_name
+        );
+
+        let settings = settings.clone();
+
+        async move {
+            let res = INTRA_CLUSTER_CLIENT
+                .put(url)
+                .header(header::AUTHORIZATION, &ingestor.token)
+                .header(header::CONTENT_TYPE, "application/json")
+                .json(&settings)
+                .send()
+                .await
+                .map_err(|err| {
+                    error!(
+                        "Fatal: failed to forward request to ingestor: {}\n Error: {:?}",
+                        ingestor.domain_name, err
+                    );
+                    StreamError::Network(err)
+                })?;
+
+            if !res.status().is_success() {
+                error!(
+                    "failed to forward request to ingestor: {}\nResponse Returned: {:?}",
+                    ingestor.domain_name,
+                    res.text().await
+                );
+            }
+
+            Ok(())
+        }
+    })
+    .await
+}
+
 pub fn fetch_daily_stats(
     date: &str,
     stream_meta_list: &[ObjectStoreFormat],
@@ -769,6 +904,8 @@ pub async fn fetch_stats_from_ingestors(
     let mut deleted_ingestion_size = 0u64;
     let mut deleted_storage_size = 0u64;
     let mut deleted_count = 0u64;
+    let mut duplicates_dropped = 0u64;
+    let mut wire_size = 0u64;
     for ob in obs {
         let stream_metadata: ObjectStoreFormat =
             serde_json::from_slice(&ob).expect("stream.json is valid json");
@@ -782,20 +919,26 @@ pub async fn fetch_stats_from_ingestors(
         deleted_count += stream_metadata.stats.deleted_stats.events;
         deleted_ingestion_size += stream_metadata.stats.deleted_stats.ingestion;
         deleted_storage_size += stream_metadata.stats.deleted_stats.storage;
+        duplicates_dropped += stream_metadata.stats.duplicates_dropped;
+        wire_size += stream_metadata.stats.wire_size;
     }
 
+    let mut ingestion_stats = IngestionStats::new(
+        count,
+        ingestion_size,
+        lifetime_count,
+        lifetime_ingestion_size,
+        deleted_count,
+        deleted_ingestion_size,
+        "json",
+    );
+    ingestion_stats.duplicates_dropped = duplicates_dropped;
+    ingestion_stats.wire_size = wire_size;
+
     let qs = QueriedStats::new(
         "",
         Utc::now(),
-        IngestionStats::new(
-            count,
-            ingestion_size,
-            lifetime_count,
-            lifetime_ingestion_size,
-            deleted_count,
-            deleted_ingestion_size,
-            "json",
-        ),
+        ingestion_stats,
         StorageStats::new(
             storage_size,
             lifetime_storage_size,
@@ -88,11 +88,16 @@ impl ClusterInfo {
 pub struct IngestionStats {
     pub count: u64,
     pub size: u64,
+    /// Size of the request bodies as received, before decompression
+    #[serde(default)]
+    pub wire_size: u64,
     pub format: String,
     pub lifetime_count: u64,
     pub lifetime_size: u64,
     pub deleted_count: u64,
     pub deleted_size: u64,
+    #[serde(default)]
+    pub duplicates_dropped: u64,
 }
 
 impl IngestionStats {
@@ -108,11 +113,13 @@ impl IngestionStats {
         Self {
             count,
             size,
+            wire_size: 0,
             format: format.to_string(),
             lifetime_count,
             lifetime_size,
             deleted_count,
             deleted_size,
+            duplicates_dropped: 0,
         }
     }
 }
@@ -155,11 +162,13 @@ pub fn merge_queried_stats(stats: Vec<QueriedStats>) -> Result<QueriedStats, Pri
                 count: acc.count + x.count,
 
                 size: acc.size + x.size,
+                wire_size: acc.wire_size + x.wire_size,
                 format: x.format.clone(),
                 lifetime_count: acc.lifetime_count + x.lifetime_count,
                 lifetime_size: acc.lifetime_size + x.lifetime_size,
                 deleted_count: acc.deleted_count + x.deleted_count,
                 deleted_size: acc.deleted_size + x.deleted_size,
+                duplicates_dropped: acc.duplicates_dropped + x.duplicates_dropped,
             });
 
     let cumulative_storage =
@@ -0,0 +1,385 @@
+/*
+ * Parseable Server (C) 2022 - 2025 Parseable, Inc.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ */
+
+//! Elasticsearch `_bulk` API compatibility, such that shippers speaking the
+//! ES bulk protocol (Filebeat, Fluent Bit `es` output, Logstash) can ingest as is
+
+use std::collections::HashMap;
+use std::time::Instant;
+
+use actix_web::http::StatusCode;
+use actix_web::web::{Bytes, Path};
+use actix_web::{HttpRequest, HttpResponse, ResponseError};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use tracing::warn;
+use ulid::Ulid;
+
+use crate::event::format::LogSource;
+use crate::handlers::http::ingest::PostError;
+use crate::handlers::http::modal::utils::ingest_utils::{
+    flatten_and_push_logs, get_custom_fields_from_header, setup_stream_for_ingestion,
+};
+use crate::handlers::{STREAM_NAME_HEADER_KEY, TelemetryType};
+use crate::parseable::PARSEABLE;
+use crate::rbac::{self, Users, role::Action};
+use crate::utils::actix::extract_session_key_from_req;
+
+/// Version reported to clients, shippers refuse to talk to clusters older than themselves
+const ES_VERSION: &str = "8.11.0";
+const ES_PRODUCT_HEADER: (&str, &str) = ("X-Elastic-Product", "Elasticsearch");
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum BulkAction {
+    Index(ActionMetadata),
+    Create(ActionMetadata),
+    Update(ActionMetadata),
+    Delete(ActionMetadata),
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ActionMetadata {
+    #[serde(rename = "_index")]
+    index: Option<String>,
+    #[serde(rename = "_id")]
+    id: Option<String>,
+}
+
+/// A single action of the bulk request along with its outcome
+#[derive(Debug)]
+struct BulkItem {
+    action: &'static str,
+    index: Option<String>,
+    id: String,
+    // the document to be ingested, taken out when pushed to the stream
+    document: Option<Value>,
+    error: Option<(StatusCode, String)>,
+}
+
+impl BulkItem {
+    fn into_response(self) -> Value {
+        let mut item = json!({
+            "_index": self.index,
+            "_id": self.id,
+        });
+        match self.error {
+            None => {
+                item["_version"] = json!(1);
+                item["result"] = json!("created");
+                item["_shards"] = json!({"total": 1, "successful": 1, "failed": 0});
+                item["status"] = json!(StatusCode::CREATED.as_u16());
+            }
+            Some((status, reason)) => {
+                let error_type = match status {
+                    StatusCode::BAD_REQUEST => "illegal_argument_exception",
+                    StatusCode::FORBIDDEN => "security_exception",
+                    StatusCode::NOT_FOUND => "index_not_found_exception",
+                    _ => "exception",
+                };
+                item["status"] = json!(status.as_u16());
+                item["error"] = json!({"type": error_type, "reason": reason});
+            }
+        }
+
+        json!({ self.action: item })
+    }
+}
+
+// Handler for GET /api/v1/elastic
+// returns the cluster info that ES clients check on startup
+pub async fn info() -> HttpResponse {
+    HttpResponse::Ok()
+        .insert_header(ES_PRODUCT_HEADER)
+        .json(json!({
+            "name": "parseable",
+            "cluster_name": "parseable",
+            "version": {
+                "number": ES_VERSION,
+                "build_flavor": "default",
+                "lucene_version": "9.8.0",
+                "minimum_wire_compatibility_version": "7.17.0",
+                "minimum_index_compatibility_version": "7.0.0"
+            },
+            "tagline": "You Know, for Search"
+        }))
+}
+
+// Handler for POST /api/v1/elastic/_bulk
+// ingests documents into the stream named by the `_index` of each action
+pub async fn bulk(req: HttpRequest, body: Bytes) -> Result<HttpResponse, PostError> {
+    let default_index = req
+        .headers()
+        .get(STREAM_NAME_HEADER_KEY)
+        .and_then(|h| h.to_str().ok())
+        .map(str::to_owned);
+
+    ingest_bulk(&req, default_index, &body).await
+}
+
+// Handler for POST /api/v1/elastic/{logstream}/_bulk
+// same as `bulk`, with the stream used for actions that don't specify an `_index`
+pub async fn bulk_with_index(
+    req: HttpRequest,
+    stream_name: Path<String>,
+    body: Bytes,
+) -> Result<HttpResponse, PostError> {
+    ingest_bulk(&req, Some(stream_name.into_inner()), &body).await
+}
+
+async fn ingest_bulk(
+    req: &HttpRequest,
+    default_index: Option<String>,
+    body: &[u8],
+) -> Result<HttpResponse, PostError> {
+    let start = Instant::now();
+    let mut items = parse_bulk(body, default_index.as_deref())?;
+
+    // group documents by the stream they are meant for, preserving their order
+    let mut streams: HashMap<String, Vec<usize>> = HashMap::new();
+    for (i, item) in items.iter().enumerate() {
+        if let (Some(index), None) = (&item.index, &item.error) {
+            streams.entry(index.clone()).or_default().push(i);
+        }
+    }
+
+    let p_custom_fields = get_custom_fields_from_header(req);
+    for (stream_name, positions) in streams {
+        let result = match authorize_stream(req, &stream_name) {
+            Ok(()) => push_documents(&stream_name, &mut items, &positions, &p_custom_fields).await,
+            Err(err) => Err(err),
+        };
+        if let Err((status, reason)) = result {
+            for &i in &positions {
+                items[i].error = Some((status, reason.clone()));
+            }
+        }
+    }
+
+    let errors = items.iter().any(|item| item.error.is_some());
+    let items: Vec<Value> = items.into_iter().map(BulkItem::into_response).collect();
+
+    Ok(HttpResponse::Ok()
+        .insert_header(ES_PRODUCT_HEADER)
+        .json(json!({
+            "took": start.elapsed().as_millis() as u64,
+            "errors": errors,
+            "items": items,
+        })))
+}
+
+/// Parses the NDJSON body of a bulk request, i.e. action lines, each followed by the
+/// document for `index` and `create` actions. Only a malformed action line fails
+/// the whole request, other failures are reported per item.
+fn parse_bulk(body: &[u8], default_index: Option<&str>) -> Result<Vec<BulkItem>, PostError> {
+    let mut lines = body
+        .split(|b| *b == b'\n')
+        .map(<[u8]>::trim_ascii)
+        .filter(|line| !line.is_empty());
+
+    let mut items = vec![];
+    while let Some(line) = lines.next() {
+        let action: BulkAction = serde_json::from_slice(line).map_err(|e| {
+            PostError::Invalid(anyhow::anyhow!("Malformed action/metadata line, {e}"))
+        })?;
+
+        let (action, metadata, has_source, supported) = match action {
+            BulkAction::Index(metadata) => ("index", metadata, true, true),
+            BulkAction::Create(metadata) => ("create", metadata, true, true),
+            BulkAction::Update(metadata) => ("update", metadata, true, false),
+            BulkAction::Delete(metadata) => ("delete", metadata, false, false),
+        };
+        let mut item = BulkItem {
+            action,
+            index: metadata.index.or_else(|| default_index.map(str::to_owned)),
+            id: metadata.id.unwrap_or_else(|| Ulid::new().to_string()),
+            document: None,
+            error: None,
+        };
+
+        let source = if has_source { lines.next() } else { None };
+        item.error = if !supported {
+            Some((
+                StatusCode::BAD_REQUEST,
+                format!("{action} is not supported, documents are append only"),
+            ))
+        } else if item.index.is_none() {
+            Some((StatusCode::BAD_REQUEST, "index is missing".to_owned()))
+        } else {
+            match source.map(serde_json::from_slice::<Value>) {
+                Some(Ok(document @ Value::Object(_))) => {
+                    item.document = Some(document);
+                    None
+                }
+                Some(Ok(_)) => Some((
+                    StatusCode::BAD_REQUEST,
+                    "document should be a json object".to_owned(),
+                )),
+                Some(Err(e)) => Some((StatusCode::BAD_REQUEST, format!("failed to parse, {e}"))),
+                None => Some((StatusCode::BAD_REQUEST, "document is missing".to_owned())),
+            }
+        };
+
+        items.push(item);
+    }
+
+    Ok(items)
+}
+
+fn authorize_stream(req: &HttpRequest, stream_name: &str) -> Result<(), (StatusCode, String)> {
+    let key =
+        extract_session_key_from_req(req).map_err(|e| (StatusCode::UNAUTHORIZED, e.to_string()))?;
+    match Users.authorize(key, Action::Ingest, Some(stream_name), None) {
+        rbac::Response::Authorized => Ok(()),
+        _ => Err((
+            StatusCode::FORBIDDEN,
+            format!("not authorized to ingest into {stream_name}"),
+        )),
+    }
+}
+
+// Pushes the documents at the given positions into the stream, documents are pushed
+// together and only retried one by one to find out which failed, if that is safe to do
+async fn push_documents(
+    stream_name: &str,
+    items: &mut [BulkItem],
+    positions: &[usize],
+    p_custom_fields: &HashMap<String, String>,
+) -> Result<(), (StatusCode, String)> {
+    let into_item_error = |err: PostError| (err.status_code(), err.to_string());
+
+    setup_stream_for_ingestion(stream_name, LogSource::Json, TelemetryType::Logs)
+        .await
+        .map_err(into_item_error)?;
+
+    let documents: Vec<Value> = positions
+        .iter()
+        .filter_map(|&i| items[i].document.take())
+        .collect();
+    let Err(err) = push(
+        stream_name,
+        Value::Array(documents.clone()),
+        p_custom_fields,
+    )
+    .await
+    else {
+        return Ok(());
+    };
+
+    // partitioned streams push each document as its own event, they may have been partially ingested
+    let stream = PARSEABLE
+        .get_stream(stream_name)
+        .map_err(|e| into_item_error(e.into()))?;
+    if positions.len() == 1
+        || stream.get_time_partition().is_some()
+        || stream.get_custom_partition().is_some()
+    {
+        return Err(into_item_error(err));
+    }
+
+    warn!("Bulk ingestion into {stream_name} failed, retrying documents one by one: {err}");
+    for (&i, document) in positions.iter().zip(documents) {
+        if let Err(err) = push(stream_name, document, p_custom_fields).await {
+            items[i].error = Some(into_item_error(err));
+        }
+    }
+
+    Ok(())
+}
+
+async fn push(
+    stream_name: &str,
+    json: Value,
+    p_custom_fields: &HashMap<String, String>,
+) -> Result<(), PostError> {
+    flatten_and_push_logs(
+        json,
+        stream_name,
+        &LogSource::Json,
+        p_custom_fields,
+        None,
+        TelemetryType::Logs,
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_bulk_actions() {
+        let body = br#"
+{"index":{"_index":"app","_id":"1"}}
+{"message":"hello"}
+{"create":{}}
+{"message":"world"}
+{"delete":{"_index":"app","_id":"1"}}
+{"update":{"_index":"app","_id":"1"}}
+{"doc":{"message":"hi"}}
+{"index":{"_index":"app"}}
+not json
+"#;
+
+        let items = parse_bulk(body, Some("default")).unwrap();
+
+        assert_eq!(items.len(), 5);
+        assert_eq!(items[0].index.as_deref(), Some("app"));
+        assert_eq!(items[0].id, "1");
+        assert_eq!(items[0].document, Some(json!({"message": "hello"})));
+        assert_eq!(items[1].index.as_deref(), Some("default"));
+        assert!(items[1].error.is_none());
+        assert_eq!(items[2].error.as_ref().unwrap().0, StatusCode::BAD_REQUEST);
+        assert_eq!(items[3].error.as_ref().unwrap().0, StatusCode::BAD_REQUEST);
+        assert!(items[4].document.is_none());
+        assert_eq!(items[4].error.as_ref().unwrap().0, StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn missing_index_is_an_item_error() {
+        let items = parse_bulk(b"{\"index\":{}}\n{\"a\":1}\n", None).unwrap();
+
+        assert_eq!(items[0].error.as_ref().unwrap().1, "index is missing");
+    }
+
+    #[test]
+    fn malformed_action_fails_request() {
+        assert!(parse_bulk(b"{\"upsert\":{}}\n{}\n", None).is_err());
+    }
+
+    #[test]
+    fn item_response_is_es_shaped() {
+        let item = BulkItem {
+            action: "index",
+            index: Some("app".to_owned()),
+            id: "1".to_owned(),
+            document: None,
+            error: Some((StatusCode::BAD_REQUEST, "oops".to_owned())),
+        };
+
+        assert_eq!(
+            item.into_response(),
+            json!({"index": {
+                "_index": "app",
+                "_id": "1",
+                "status": 400,
+                "error": {"type": "illegal_argument_exception", "reason": "oops"},
+            }})
+        );
+    }
+}
@@ -17,25 +17,33 @@
  */
 
 use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 
+use actix_web::guard::GuardContext;
 use actix_web::http::StatusCode;
+use actix_web::http::header::{CONTENT_ENCODING, CONTENT_TYPE};
 use actix_web::web::{self, Json, Path};
-use actix_web::{HttpRequest, HttpResponse, http::header::ContentType};
+use actix_web::{HttpRequest, HttpResponse, ResponseError, http::header::ContentType};
 use arrow_array::RecordBatch;
+use arrow_schema::Field;
 use bytes::Bytes;
 use chrono::Utc;
+use futures::{StreamExt, future, stream};
+use serde_json::{Value, json};
 use tracing::error;
 
 use crate::event::error::EventError;
+use crate::event::format::csv::CsvOptions;
 use crate::event::format::known_schema::{self, KNOWN_SCHEMA_LIST};
 use crate::event::format::{self, EventFormat, LogSource, LogSourceEntry};
 use crate::event::{self, FORMAT_KEY, USER_AGENT_KEY};
 use crate::handlers::http::modal::utils::ingest_utils::validate_stream_for_ingestion;
 use crate::handlers::{
-    CONTENT_TYPE_JSON, CONTENT_TYPE_PROTOBUF, EXTRACT_LOG_KEY, LOG_SOURCE_KEY,
-    STREAM_NAME_HEADER_KEY, TELEMETRY_TYPE_KEY, TelemetryType,
+    CONTENT_TYPE_CSV, CONTENT_TYPE_JSON, CONTENT_TYPE_NDJSON, CONTENT_TYPE_PROTOBUF,
+    CONTENT_TYPE_TSV, CSV_DELIMITER_KEY, CSV_HAS_HEADER_KEY, CSV_QUOTE_KEY, EXTRACT_LOG_KEY,
+    LOG_SOURCE_KEY, STREAM_NAME_HEADER_KEY, TELEMETRY_TYPE_KEY, TelemetryType,
 };
-use crate::metadata::SchemaVersion;
+use crate::metadata::{SchemaVersion, update_wire_stats};
 use crate::metastore::MetastoreError;
 use crate::option::Mode;
 use crate::otel::logs::OTEL_LOG_KNOWN_FIELD_LIST;
@@ -47,26 +55,22 @@ use crate::utils::header_parsing::ParseHeaderError;
 use crate::utils::json::{flatten::JsonFlattenError, strict::StrictValue};
 
 use super::logstream::error::{CreateStreamError, StreamError};
-use super::modal::utils::ingest_utils::{flatten_and_push_logs, get_custom_fields_from_header};
+use super::modal::utils::ingest_utils::{
+    decode_otel_protobuf, flatten_and_push_logs, get_custom_fields_from_header, push_otel_records,
+    read_body, setup_stream_for_ingestion,
+};
 use super::users::dashboards::DashboardError;
 use super::users::filters::FiltersError;
 
 // Handler for POST /api/v1/ingest
 // ingests events by extracting stream name from header
 // creates if stream does not exist
-pub async fn ingest(
-    req: HttpRequest,
-    Json(json): Json<StrictValue>,
-) -> Result<HttpResponse, PostError> {
+pub async fn ingest(req: HttpRequest, payload: web::Payload) -> Result<HttpResponse, PostError> {
     let Some(stream_name) = req.headers().get(STREAM_NAME_HEADER_KEY) else {
         return Err(PostError::Header(ParseHeaderError::MissingStreamName));
     };
 
     let stream_name = stream_name.to_str().unwrap().to_owned();
-    let internal_stream_names = PARSEABLE.streams.list_internal_streams();
-    if internal_stream_names.contains(&stream_name) {
-        return Err(PostError::InternalStream(stream_name));
-    }
 
     let log_source = req
         .headers()
@@ -94,7 +98,8 @@ pub async fn ingest(
 
     let mut p_custom_fields = get_custom_fields_from_header(&req);
 
-    let mut json = json.into_inner();
+    let (body, wire_size) = read_body(&req, payload).await?;
+    let mut json = serde_json::from_slice::<StrictValue>(&body)?.into_inner();
 
     let fields = match &log_source {
         LogSource::Custom(src) => KNOWN_SCHEMA_LIST.extract_from_inline_log(
@@ -102,32 +107,394 @@ pub async fn ingest(
             &mut p_custom_fields,
             src,
             extract_log,
+            &stream_schema(&stream_name),
         )?,
         _ => HashSet::new(),
     };
 
-    let log_source_entry = LogSourceEntry::new(log_source.clone(), fields);
+    setup_stream_for_ingestion(&stream_name, log_source.clone(), telemetry_type).await?;
+    // the fields of known schemas extracted from the events are kept with the log source
+    if !fields.is_empty() {
+        PARSEABLE
+            .add_update_log_source(
+                &stream_name,
+                LogSourceEntry::new(log_source.clone(), fields),
+            )
+            .await?;
+    }
 
-    PARSEABLE
-        .create_stream_if_not_exists(
-            &stream_name,
-            StreamType::UserDefined,
+    flatten_and_push_logs(
+        json,
+        &stream_name,
+        &log_source,
+        &p_custom_fields,
+        None,
+        telemetry_type,
+    )
+    .await?;
+    update_wire_stats(&stream_name, "json", wire_size);
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+// Handler for POST /api/v1/ingest with a CSV/TSV body, selected by
+// `Content-Type: text/csv` (or tsv) or by `x-p-log-source: csv`
+// creates the stream if it does not exist
+pub async fn ingest_csv(
+    req: HttpRequest,
+    payload: web::Payload,
+) -> Result<HttpResponse, PostError> {
+    let options = get_csv_options(&req)?;
+    let (body, wire_size) = read_body(&req, payload).await?;
+
+    let Some(stream_name) = req.headers().get(STREAM_NAME_HEADER_KEY) else {
+        return Err(PostError::Header(ParseHeaderError::MissingStreamName));
+    };
+    let stream_name = stream_name.to_str().unwrap().to_owned();
+    let telemetry_type = req
+        .headers()
+        .get(TELEMETRY_TYPE_KEY)
+        .and_then(|h| h.to_str().ok())
+        .map_or(TelemetryType::default(), TelemetryType::from);
+
+    setup_stream_for_ingestion(&stream_name, LogSource::Csv, telemetry_type).await?;
+
+    // rows are split per partition value and transformed by the stream's settings as json
+    // events, they are decoded straight into a batch otherwise
+    let stream = PARSEABLE.get_stream(&stream_name)?;
+    if stream.get_time_partition().is_some()
+        || stream.get_custom_partition().is_some()
+        || stream.get_settings().transforms_events()
+    {
+        let rows = format::csv::parse(&body, &options)?;
+        push_parsed_rows(&req, rows, LogSource::Csv, wire_size).await?;
+        return Ok(HttpResponse::Ok().finish());
+    }
+
+    let event = format::csv::Event::new(&body, &options, Utc::now())?;
+    if event.rb.num_rows() == 0 {
+        return Ok(HttpResponse::Ok().finish());
+    }
+    let mut p_custom_fields = get_custom_fields_from_header(&req);
+    p_custom_fields.insert(FORMAT_KEY.to_string(), LogSource::Csv.to_string());
+    event
+        .into_event(
+            stream_name.clone(),
+            body.len() as u64,
+            &stream.get_schema_raw(),
+            stream.get_static_schema_flag(),
             None,
-            vec![log_source_entry.clone()],
+            None,
+            stream.get_schema_version(),
+            StreamType::UserDefined,
+            &p_custom_fields,
             telemetry_type,
-        )
-        .await?;
+        )?
+        .process()?;
+    update_wire_stats(&stream_name, "csv", wire_size);
 
-    //if stream exists, fetch the stream log source
-    //return error if the stream log source is otel traces or otel metrics
-    validate_stream_for_ingestion(&stream_name)?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+// Handler for POST /api/v1/ingest with newline delimited logfmt lines,
+// selected by `x-p-log-source: logfmt`
+// malformed lines are skipped and listed in the response
+// creates the stream if it does not exist
+pub async fn ingest_logfmt(
+    req: HttpRequest,
+    payload: web::Payload,
+) -> Result<HttpResponse, PostError> {
+    let (body, wire_size) = read_body(&req, payload).await?;
+    let body = std::str::from_utf8(&body)
+        .map_err(|e| PostError::Invalid(anyhow::anyhow!("Body is not valid utf-8, {e}")))?;
+    let (rows, errors) = format::logfmt::parse(body);
+    let accepted = rows.len();
+
+    push_parsed_rows(&req, rows, LogSource::Logfmt, wire_size).await?;
+
+    Ok(HttpResponse::Ok().json(json!({
+        "accepted": accepted,
+        "rejected": errors.len(),
+        "errors": errors
+            .iter()
+            .take(MAX_REPORTED_ERRORS)
+            .map(|err| err.to_string())
+            .collect::<Vec<_>>(),
+    })))
+}
+
+/// Lines of a newline delimited json body are pushed in batches of about this size
+const NDJSON_BATCH_SIZE: usize = 1024 * 1024;
+/// Rejected lines listed in the response to a newline delimited json or logfmt request,
+/// the count of rejected lines includes those that are not listed
+const MAX_REPORTED_ERRORS: usize = 1000;
+
+// Handler for POST /api/v1/ingest with newline delimited json, selected by
+// `Content-Type: application/x-ndjson`
+// lines are parsed as they are read and pushed in batches, invalid lines are skipped
+// and listed in the response, creates the stream if it does not exist
+pub async fn ingest_ndjson(
+    req: HttpRequest,
+    payload: web::Payload,
+) -> Result<HttpResponse, PostError> {
+    let Some(stream_name) = req.headers().get(STREAM_NAME_HEADER_KEY) else {
+        return Err(PostError::Header(ParseHeaderError::MissingStreamName));
+    };
+
+    let stream_name = stream_name.to_str().unwrap().to_owned();
+
+    let log_source = req
+        .headers()
+        .get(LOG_SOURCE_KEY)
+        .and_then(|h| h.to_str().ok())
+        .map_or(LogSource::default(), LogSource::from);
+    if !matches!(log_source, LogSource::Json | LogSource::Custom(_)) {
+        return Err(PostError::Invalid(anyhow::anyhow!(
+            "Log source {log_source} is not supported for {CONTENT_TYPE_NDJSON} payloads"
+        )));
+    }
+
+    let telemetry_type = req
+        .headers()
+        .get(TELEMETRY_TYPE_KEY)
+        .and_then(|h| h.to_str().ok())
+        .map_or(TelemetryType::default(), TelemetryType::from);
+
+    setup_stream_for_ingestion(&stream_name, log_source.clone(), telemetry_type).await?;
+
+    let stream = PARSEABLE.get_stream(&stream_name)?;
+    let is_partitioned =
+        stream.get_time_partition().is_some() || stream.get_custom_partition().is_some();
+    let mut ndjson = NdjsonIngestion {
+        stream_name,
+        log_source,
+        telemetry_type,
+        extract_log: req
+            .headers()
+            .get(EXTRACT_LOG_KEY)
+            .and_then(|h| h.to_str().ok())
+            .map(str::to_owned),
+        p_custom_fields: get_custom_fields_from_header(&req),
+        is_partitioned,
+        known_fields: HashSet::new(),
+        accepted: 0,
+        rejected: 0,
+        errors: vec![],
+    };
+
+    // compressed bodies are decompressed whole, within the payload limit, as for other
+    // formats. Otherwise only the current batch and partial line are held in memory
+    let is_compressed = req.headers().contains_key(CONTENT_ENCODING);
+    let mut wire_size = 0;
+    let mut chunks = if is_compressed {
+        let (body, size) = read_body(&req, payload).await?;
+        wire_size = size;
+        stream::once(future::ready(Ok(body))).boxed_local()
+    } else {
+        payload
+            .map(|chunk| {
+                chunk.map_err(|e| PostError::Invalid(anyhow::anyhow!("Failed to read body, {e}")))
+            })
+            .boxed_local()
+    };
+
+    let mut splitter = format::ndjson::LineSplitter::new(PARSEABLE.options.max_event_payload_size);
+    let mut lines = vec![];
+    let mut batch = vec![];
+    let mut batch_size = 0;
+    while let Some(chunk) = chunks.next().await {
+        let chunk = chunk?;
+        if !is_compressed {
+            wire_size += chunk.len() as u64;
+        }
+        batch_size += chunk.len();
+        splitter.push(&chunk, &mut lines);
+        ndjson.collect(lines.drain(..), &mut batch);
+        if batch_size >= NDJSON_BATCH_SIZE {
+            ndjson.push(std::mem::take(&mut batch)).await?;
+            batch_size = 0;
+        }
+    }
+    splitter.finish(&mut lines);
+    ndjson.collect(lines.drain(..), &mut batch);
+    ndjson.push(batch).await?;
+    update_wire_stats(&ndjson.stream_name, "json", wire_size);
+
+    Ok(HttpResponse::Ok().json(json!({
+        "accepted": ndjson.accepted,
+        "rejected": ndjson.rejected,
+        "errors": ndjson.errors,
+    })))
+}
+
+// State of a newline delimited json request, across the batches it is pushed in
+struct NdjsonIngestion {
+    stream_name: String,
+    log_source: LogSource,
+    telemetry_type: TelemetryType,
+    extract_log: Option<String>,
+    p_custom_fields: HashMap<String, String>,
+    is_partitioned: bool,
+    // fields of a custom log source already recorded in the stream's log source entry
+    known_fields: HashSet<String>,
+    accepted: usize,
+    rejected: usize,
+    errors: Vec<Value>,
+}
+
+impl NdjsonIngestion {
+    // Adds the valid lines to the batch and records the invalid ones
+    fn collect(
+        &mut self,
+        lines: impl Iterator<Item = format::ndjson::Line>,
+        batch: &mut Vec<(usize, Value)>,
+    ) {
+        for (line_number, line) in lines {
+            match line {
+                Ok(value) => batch.push((line_number, value)),
+                Err(err) => self.reject(line_number, err),
+            }
+        }
+    }
+
+    fn reject(&mut self, line_number: usize, err: impl ToString) {
+        self.rejected += 1;
+        if self.errors.len() < MAX_REPORTED_ERRORS {
+            self.errors
+                .push(json!({"line": line_number, "error": err.to_string()}));
+        }
+    }
+
+    // Pushes the batch at once, when that fails on account of its content, e.g. a line
+    // doesn't fit the schema of the stream, the lines are pushed one by one instead.
+    // Rows of partitioned streams are pushed as events of their own regardless, such
+    // that a failing batch may have been pushed in part, these are pushed one by one.
+    async fn push(&mut self, batch: Vec<(usize, Value)>) -> Result<(), PostError> {
+        if batch.is_empty() {
+            return Ok(());
+        }
 
+        let (line_numbers, rows): (Vec<_>, Vec<_>) = batch.into_iter().unzip();
+        let mut json = Value::Array(rows);
+        let p_custom_fields = self.extract_known_fields(&mut json).await?;
+        if !self.is_partitioned {
+            match self.push_rows(json.clone(), &p_custom_fields).await {
+                Ok(()) => {
+                    self.accepted += line_numbers.len();
+                    return Ok(());
+                }
+                Err(err) if !is_invalid_content(&err) => return Err(err),
+                Err(_) => {}
+            }
+        }
+
+        let Value::Array(rows) = json else {
+            unreachable!("batch is an array of rows")
+        };
+        for (line_number, row) in line_numbers.into_iter().zip(rows) {
+            match self.push_rows(row, &p_custom_fields).await {
+                Ok(()) => self.accepted += 1,
+                Err(err) if is_invalid_content(&err) => self.reject(line_number, err),
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(())
+    }
+
+    // Extracts the fields of a custom log source as done by `ingest`, recording any
+    // newly seen fields in the log source entry of the stream
+    async fn extract_known_fields(
+        &mut self,
+        json: &mut Value,
+    ) -> Result<HashMap<String, String>, PostError> {
+        let mut p_custom_fields = self.p_custom_fields.clone();
+        let LogSource::Custom(src) = &self.log_source else {
+            return Ok(p_custom_fields);
+        };
+
+        let fields = KNOWN_SCHEMA_LIST.extract_from_inline_log(
+            json,
+            &mut p_custom_fields,
+            src,
+            self.extract_log.as_deref(),
+            &stream_schema(&self.stream_name),
+        )?;
+        if !fields.is_subset(&self.known_fields) {
+            self.known_fields.extend(fields.iter().cloned());
+            PARSEABLE
+                .add_update_log_source(
+                    &self.stream_name,
+                    LogSourceEntry::new(self.log_source.clone(), fields),
+                )
+                .await?;
+        }
+
+        Ok(p_custom_fields)
+    }
+
+    async fn push_rows(
+        &self,
+        json: Value,
+        p_custom_fields: &HashMap<String, String>,
+    ) -> Result<(), PostError> {
+        flatten_and_push_logs(
+            json,
+            &self.stream_name,
+            &self.log_source,
+            p_custom_fields,
+            None,
+            self.telemetry_type,
+        )
+        .await
+    }
+}
+
+// Schema of the stream, which is yet to be created if it doesn't exist
+fn stream_schema(stream_name: &str) -> HashMap<String, Arc<Field>> {
     PARSEABLE
-        .add_update_log_source(&stream_name, log_source_entry)
-        .await?;
+        .get_stream(stream_name)
+        .map(|stream| stream.get_schema_raw())
+        .unwrap_or_default()
+}
+
+// Errors due to the rows pushed rather than the state of the server, such that
+// pushing the rows one by one singles out those at fault
+fn is_invalid_content(err: &PostError) -> bool {
+    matches!(err, PostError::JsonFlattenError(_)) || err.status_code() == StatusCode::BAD_REQUEST
+}
+
+// Common flow for text payloads that the handler has already parsed into json objects,
+// mirrors the checks done for json payloads in `ingest`
+async fn push_parsed_rows(
+    req: &HttpRequest,
+    rows: Vec<Value>,
+    log_source: LogSource,
+    wire_size: u64,
+) -> Result<(), PostError> {
+    let Some(stream_name) = req.headers().get(STREAM_NAME_HEADER_KEY) else {
+        return Err(PostError::Header(ParseHeaderError::MissingStreamName));
+    };
+
+    let stream_name = stream_name.to_str().unwrap().to_owned();
+
+    let telemetry_type = req
+        .headers()
+        .get(TELEMETRY_TYPE_KEY)
+        .and_then(|h| h.to_str().ok())
+        .map_or(TelemetryType::default(), TelemetryType::from);
+
+    if rows.is_empty() {
+        return Ok(());
+    }
+
+    let mut p_custom_fields = get_custom_fields_from_header(req);
+    p_custom_fields.insert(FORMAT_KEY.to_string(), log_source.to_string());
+
+    setup_stream_for_ingestion(&stream_name, log_source.clone(), telemetry_type).await?;
 
     flatten_and_push_logs(
-        json,
+        Value::Array(rows),
         &stream_name,
         &log_source,
         &p_custom_fields,
@@ -135,8 +502,99 @@ pub async fn ingest(
         telemetry_type,
     )
     .await?;
+    update_wire_stats(&stream_name, "json", wire_size);
 
-    Ok(HttpResponse::Ok().finish())
+    Ok(())
+}
+
+/// Route guard for [`ingest_csv`], matches on the Content-Type or the log source header
+pub fn is_csv_request(ctx: &GuardContext) -> bool {
+    let headers = ctx.head().headers();
+    let is_csv_content = headers
+        .get(CONTENT_TYPE)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.split(';').next())
+        .is_some_and(|content_type| {
+            let content_type = content_type.trim();
+            content_type.eq_ignore_ascii_case(CONTENT_TYPE_CSV)
+                || content_type.eq_ignore_ascii_case(CONTENT_TYPE_TSV)
+        });
+    let is_csv_source = headers
+        .get(LOG_SOURCE_KEY)
+        .and_then(|h| h.to_str().ok())
+        .is_some_and(|log_source| LogSource::from(log_source) == LogSource::Csv);
+
+    is_csv_content || is_csv_source
+}
+
+/// Route guard for [`ingest_ndjson`], matches on the Content-Type
+pub fn is_ndjson_request(ctx: &GuardContext) -> bool {
+    ctx.head()
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.split(';').next())
+        .is_some_and(|content_type| {
+            content_type
+                .trim()
+                .eq_ignore_ascii_case(CONTENT_TYPE_NDJSON)
+        })
+}
+
+/// Route guard for [`ingest_logfmt`], matches on the log source header
+pub fn is_logfmt_request(ctx: &GuardContext) -> bool {
+    ctx.head()
+        .headers()
+        .get(LOG_SOURCE_KEY)
+        .and_then(|h| h.to_str().ok())
+        .is_some_and(|log_source| LogSource::from(log_source) == LogSource::Logfmt)
+}
+
+// Reads the delimiter, quote and header options of a CSV payload from the request headers,
+// the delimiter defaults to a tab for `text/tab-separated-values` and to a comma otherwise
+fn get_csv_options(req: &HttpRequest) -> Result<CsvOptions, PostError> {
+    let headers = req.headers();
+    let is_tsv = headers
+        .get(CONTENT_TYPE)
+        .and_then(|h| h.to_str().ok())
+        .is_some_and(|h| h.trim_start().starts_with(CONTENT_TYPE_TSV));
+    let mut options = if is_tsv {
+        CsvOptions::tsv()
+    } else {
+        CsvOptions::default()
+    };
+
+    let single_byte = |key: &str| -> Result<Option<u8>, PostError> {
+        let Some(value) = headers.get(key) else {
+            return Ok(None);
+        };
+        match value.as_bytes() {
+            [byte] => Ok(Some(*byte)),
+            b"\\t" | b"tab" => Ok(Some(b'\t')),
+            _ => Err(PostError::Invalid(anyhow::anyhow!(
+                "Header {key} should be a single character"
+            ))),
+        }
+    };
+    if let Some(delimiter) = single_byte(CSV_DELIMITER_KEY)? {
+        options.delimiter = delimiter;
+    }
+    if let Some(quote) = single_byte(CSV_QUOTE_KEY)? {
+        options.quote = quote;
+    }
+    if let Some(has_header) = headers.get(CSV_HAS_HEADER_KEY) {
+        options.has_header = has_header
+            .to_str()
+            .ok()
+            .and_then(|h| h.parse::<bool>().ok())
+            .ok_or_else(|| {
+                PostError::Invalid(anyhow::anyhow!(
+                    "Header {CSV_HAS_HEADER_KEY} should be either true or false"
+                ))
+            })?;
+    }
+
+    Ok(options)
 }
 
 pub async fn ingest_internal_stream(stream_name: String, body: Bytes) -> Result<(), PostError> {
@@ -190,6 +648,17 @@ pub async fn setup_otel_stream(
 
     let stream_name = stream_name.to_str().unwrap().to_owned();
 
+    create_otel_stream_if_not_exists(stream_name, log_source, known_fields, telemetry_type).await
+}
+
+// Creates the stream for OTEL ingestion if it does not exist and validates
+// that an existing stream is compatible with the incoming OTEL format
+pub async fn create_otel_stream_if_not_exists(
+    stream_name: String,
+    log_source: LogSource,
+    known_fields: &[&str],
+    telemetry_type: TelemetryType,
+) -> Result<(String, LogSource, LogSourceEntry, Option<String>), PostError> {
     let log_source_entry = LogSourceEntry::new(
         log_source.clone(),
         known_fields.iter().map(|&s| s.to_string()).collect(),
@@ -216,10 +685,22 @@ pub async fn setup_otel_stream(
                     .find(|&stream_log_source_entry| {
                         stream_log_source_entry.log_source_format != LogSource::OtelTraces
                             && stream_log_source_entry.log_source_format != LogSource::OtelMetrics
+                            && stream_log_source_entry.log_source_format != LogSource::Prometheus
                     })
                     .ok_or(PostError::IncorrectLogFormat(stream_name.clone()))?;
             }
-            LogSource::OtelMetrics | LogSource::OtelTraces => {
+            LogSource::OtelMetrics | LogSource::Prometheus => {
+                // For metrics, allow otel and prometheus metrics to share the stream
+                stream
+                    .get_log_source()
+                    .iter()
+                    .find(|&stream_log_source_entry| {
+                        stream_log_source_entry.log_source_format == LogSource::OtelMetrics
+                            || stream_log_source_entry.log_source_format == LogSource::Prometheus
+                    })
+                    .ok_or(PostError::IncorrectLogFormat(stream_name.clone()))?;
+            }
+            LogSource::OtelTraces => {
                 // For metrics/traces, only allow same type
                 stream
                     .get_log_source()
@@ -245,12 +726,13 @@ pub async fn setup_otel_stream(
 // Common content processing for OTEL ingestion
 async fn process_otel_content(
     req: &HttpRequest,
-    body: web::Bytes,
+    payload: web::Payload,
     stream_name: &str,
     log_source: &LogSource,
     telemetry_type: TelemetryType,
 ) -> Result<(), PostError> {
     let p_custom_fields = get_custom_fields_from_header(req);
+    let (body, wire_size) = read_body(req, payload).await?;
 
     match req
         .headers()
@@ -269,9 +751,15 @@ async fn process_otel_content(
                 )
                 .await?;
             } else if content_type == CONTENT_TYPE_PROTOBUF {
-                return Err(PostError::Invalid(anyhow::anyhow!(
-                    "Protobuf ingestion is not supported in Parseable OSS"
-                )));
+                push_otel_records(
+                    decode_otel_protobuf(&body, log_source)?,
+                    stream_name,
+                    log_source,
+                    &p_custom_fields,
+                    None,
+                    telemetry_type,
+                )
+                .await?;
             } else {
                 return Err(PostError::Invalid(anyhow::anyhow!(
                     "Unsupported Content-Type: {}. Expected application/json or application/x-protobuf",
@@ -285,6 +773,7 @@ async fn process_otel_content(
             )));
         }
     }
+    update_wire_stats(stream_name, "json", wire_size);
 
     Ok(())
 }
@@ -294,7 +783,7 @@ async fn process_otel_content(
 // creates if stream does not exist
 pub async fn handle_otel_logs_ingestion(
     req: HttpRequest,
-    body: web::Bytes,
+    payload: web::Payload,
 ) -> Result<HttpResponse, PostError> {
     let (stream_name, log_source, ..) = setup_otel_stream(
         &req,
@@ -304,7 +793,14 @@ pub async fn handle_otel_logs_ingestion(
     )
     .await?;
 
-    process_otel_content(&req, body, &stream_name, &log_source, TelemetryType::Logs).await?;
+    process_otel_content(
+        &req,
+        payload,
+        &stream_name,
+        &log_source,
+        TelemetryType::Logs,
+    )
+    .await?;
 
     Ok(HttpResponse::Ok().finish())
 }
@@ -314,7 +810,7 @@ pub async fn handle_otel_logs_ingestion(
 // creates if stream does not exist
 pub async fn handle_otel_metrics_ingestion(
     req: HttpRequest,
-    body: web::Bytes,
+    payload: web::Payload,
 ) -> Result<HttpResponse, PostError> {
     let (stream_name, log_source, ..) = setup_otel_stream(
         &req,
@@ -326,7 +822,7 @@ pub async fn handle_otel_metrics_ingestion(
 
     process_otel_content(
         &req,
-        body,
+        payload,
         &stream_name,
         &log_source,
         TelemetryType::Metrics,
@@ -341,7 +837,7 @@ pub async fn handle_otel_metrics_ingestion(
 // creates if stream does not exist
 pub async fn handle_otel_traces_ingestion(
     req: HttpRequest,
-    body: web::Bytes,
+    payload: web::Payload,
 ) -> Result<HttpResponse, PostError> {
     let (stream_name, log_source, ..) = setup_otel_stream(
         &req,
@@ -351,7 +847,14 @@ pub async fn handle_otel_traces_ingestion(
     )
     .await?;
 
-    process_otel_content(&req, body, &stream_name, &log_source, TelemetryType::Traces).await?;
+    process_otel_content(
+        &req,
+        payload,
+        &stream_name,
+        &log_source,
+        TelemetryType::Traces,
+    )
+    .await?;
 
     Ok(HttpResponse::Ok().finish())
 }
@@ -409,6 +912,7 @@ pub async fn post_event(
                 &mut p_custom_fields,
                 src,
                 extract_log,
+                &stream_schema(&stream_name),
             )?;
         }
         _ => {}
@@ -458,6 +962,12 @@ pub enum PostError {
     StreamNotFound(#[from] StreamNotFound),
     #[error("Could not deserialize into JSON object, {0}")]
     SerdeError(#[from] serde_json::Error),
+    #[error("Could not decode protobuf payload, {0}")]
+    ProtobufDecode(#[from] prost::DecodeError),
+    #[error("Could not decompress snappy payload, {0}")]
+    SnappyDecode(#[from] snap::Error),
+    #[error("Could not parse csv payload, {0}")]
+    CsvDecode(#[from] csv::Error),
     #[error("Header Error: {0}")]
     Header(#[from] ParseHeaderError),
     #[error("Event Error: {0}")]
@@ -487,6 +997,10 @@ pub enum PostError {
     OtelNotSupported,
     #[error("The stream {0} is reserved for internal use and cannot be ingested into")]
     InternalStream(String),
+    #[error("Not authorized to ingest into {0}")]
+    Forbidden(String),
+    #[error("Payload exceeds the limit of {0} bytes")]
+    PayloadTooLarge(usize),
     #[error(r#"Please use "x-p-log-source: {0}" for ingesting otel {1} data"#)]
     IncorrectLogSource(LogSource, String),
     #[error("Ingestion is not allowed in Query mode")]
@@ -516,6 +1030,9 @@ impl actix_web::ResponseError for PostError {
         use PostError::*;
         match self {
             SerdeError(_)
+            | ProtobufDecode(_)
+            | SnappyDecode(_)
+            | CsvDecode(_)
             | Header(_)
             | Invalid(_)
             | InternalStream(_)
@@ -542,6 +1059,10 @@ impl actix_web::ResponseError for PostError {
 
             StreamNotFound(_) => StatusCode::NOT_FOUND,
 
+            Forbidden(_) => StatusCode::FORBIDDEN,
+
+            PayloadTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+
             MetastoreError(e) => e.status_code(),
         }
     }
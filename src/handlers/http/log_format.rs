@@ -0,0 +1,158 @@
+/*
+ * Parseable Server (C) 2022 - 2025 Parseable, Inc.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ */
+
+//! Custom log formats, registered at runtime to be selected with `X-P-Log-Source`
+//! as are the formats built into `formats.json`
+
+use actix_web::http::StatusCode;
+use actix_web::http::header::ContentType;
+use actix_web::web::{self, Json, Path};
+use actix_web::{HttpResponse, Responder};
+use tracing::error;
+
+use crate::event::format::known_schema::{self, KNOWN_SCHEMA_LIST, LogFormat};
+use crate::metastore::MetastoreError;
+use crate::option::Mode;
+use crate::parseable::PARSEABLE;
+
+use super::cluster::{sync_log_format_deletion_with_ingestors, sync_log_format_with_ingestors};
+
+/// Registers the custom log formats stored in the metastore, formats that no longer
+/// compile, e.g. on account of a name now taken by a built-in format, are skipped
+pub async fn load() -> anyhow::Result<()> {
+    for bytes in PARSEABLE.metastore.get_log_formats().await? {
+        let format = match serde_json::from_slice::<LogFormat>(&bytes) {
+            Ok(format) => format,
+            Err(err) => {
+                error!("Unable to load log format file: {err}");
+                continue;
+            }
+        };
+        if let Err(err) = KNOWN_SCHEMA_LIST.set_custom_format(&format) {
+            error!("Unable to load log format {}: {err}", format.name);
+        }
+    }
+
+    Ok(())
+}
+
+// GET /logformat
+pub async fn list() -> Result<impl Responder, LogFormatError> {
+    Ok(web::Json(KNOWN_SCHEMA_LIST.custom_formats()))
+}
+
+// GET /logformat/{name}
+pub async fn get(name: Path<String>) -> Result<impl Responder, LogFormatError> {
+    let name = name.into_inner();
+    let format = KNOWN_SCHEMA_LIST
+        .custom_format(&name)
+        .ok_or(LogFormatError::NotFound(name))?;
+
+    Ok(web::Json(format))
+}
+
+// PUT /logformat/{name}
+// creates the format or replaces the one of the same name
+pub async fn put(
+    name: Path<String>,
+    Json(mut format): Json<LogFormat>,
+) -> Result<impl Responder, LogFormatError> {
+    format.name = name.into_inner();
+    // validate before storing, such that only valid formats are stored and synced
+    KNOWN_SCHEMA_LIST.validate_custom_format(&format)?;
+
+    PARSEABLE.metastore.put_log_format(&format).await?;
+    KNOWN_SCHEMA_LIST.set_custom_format(&format)?;
+    if PARSEABLE.options.mode == Mode::Query {
+        sync_log_format_with_ingestors(&format).await?;
+    }
+
+    Ok(web::Json(format))
+}
+
+// DELETE /logformat/{name}
+pub async fn delete(name: Path<String>) -> Result<impl Responder, LogFormatError> {
+    let name = name.into_inner();
+    let format = KNOWN_SCHEMA_LIST
+        .custom_format(&name)
+        .ok_or_else(|| LogFormatError::NotFound(name.clone()))?;
+
+    PARSEABLE.metastore.delete_log_format(&format).await?;
+    KNOWN_SCHEMA_LIST.remove_custom_format(&name);
+    if PARSEABLE.options.mode == Mode::Query {
+        sync_log_format_deletion_with_ingestors(&name).await?;
+    }
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+// PUT /logformat/{name}/sync
+// registers a format stored by the querier, on ingestors
+pub async fn put_sync(
+    name: Path<String>,
+    Json(mut format): Json<LogFormat>,
+) -> Result<impl Responder, LogFormatError> {
+    format.name = name.into_inner();
+    KNOWN_SCHEMA_LIST.set_custom_format(&format)?;
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+// DELETE /logformat/{name}/sync
+// removes a format deleted by the querier, on ingestors
+pub async fn delete_sync(name: Path<String>) -> Result<impl Responder, LogFormatError> {
+    KNOWN_SCHEMA_LIST.remove_custom_format(&name);
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum LogFormatError {
+    #[error("{0}")]
+    Format(#[from] known_schema::Error),
+    #[error("Log format {0} not found")]
+    NotFound(String),
+    #[error("Network Error: {0}")]
+    Network(#[from] reqwest::Error),
+    #[error(transparent)]
+    Anyhow(#[from] anyhow::Error),
+    #[error(transparent)]
+    MetastoreError(#[from] MetastoreError),
+}
+
+impl actix_web::ResponseError for LogFormatError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            Self::Format(_) => StatusCode::BAD_REQUEST,
+            Self::NotFound(_) => StatusCode::NOT_FOUND,
+            Self::Network(_) | Self::Anyhow(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::MetastoreError(e) => e.status_code(),
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse<actix_web::body::BoxBody> {
+        match self {
+            Self::MetastoreError(e) => HttpResponse::build(e.status_code())
+                .insert_header(ContentType::json())
+                .json(e.to_detail()),
+            _ => HttpResponse::build(self.status_code())
+                .insert_header(ContentType::plaintext())
+                .body(self.to_string()),
+        }
+    }
+}
@@ -17,12 +17,16 @@
  */
 
 use self::error::StreamError;
+use super::cluster::sync_settings_with_ingestors;
 use super::cluster::utils::{IngestionStats, QueriedStats, StorageStats};
 use super::query::update_schema_when_distributed;
-use crate::event::format::override_data_type;
+use crate::event::format::{LogSource, override_data_type};
+use crate::event::processors::Pipeline;
+use crate::event::settings::{self, StreamSettings};
 use crate::hottier::{CURRENT_HOT_TIER_VERSION, HotTierManager, StreamHotTier};
 use crate::metadata::SchemaVersion;
 use crate::metrics::{EVENTS_INGESTED_DATE, EVENTS_INGESTED_SIZE_DATE, EVENTS_STORAGE_SIZE_DATE};
+use crate::option::Mode;
 use crate::parseable::{PARSEABLE, StreamNotFound};
 use crate::rbac::Users;
 use crate::rbac::role::Action;
@@ -30,6 +34,7 @@ use crate::stats::{Stats, event_labels_date, storage_size_labels_date};
 use crate::storage::retention::Retention;
 use crate::storage::{ObjectStoreFormat, StreamInfo, StreamType};
 use crate::utils::actix::extract_session_key_from_req;
+use crate::utils::json::convert_array_to_object;
 use crate::utils::json::flatten::{
     self, convert_to_array, generic_flattening, has_more_than_max_allowed_levels,
 };
@@ -42,6 +47,7 @@ use arrow_json::reader::infer_json_schema_from_iterator;
 use bytes::Bytes;
 use chrono::Utc;
 use itertools::Itertools;
+use serde::Deserialize;
 use serde_json::{Value, json};
 use std::fs;
 use std::sync::Arc;
@@ -236,6 +242,117 @@ pub async fn put_retention(
     ))
 }
 
+pub async fn get_settings(stream_name: Path<String>) -> Result<impl Responder, StreamError> {
+    let stream_name = stream_name.into_inner();
+    // For query mode, if the stream not found in memory map,
+    //check if it exists in the storage
+    //create stream and schema from storage
+    if !PARSEABLE.check_or_load_stream(&stream_name).await {
+        return Err(StreamNotFound(stream_name).into());
+    }
+
+    let settings = PARSEABLE.get_stream(&stream_name)?.get_settings();
+    Ok((web::Json(settings), StatusCode::OK))
+}
+
+// replaces the ingestion settings of the stream, settings left out are disabled
+pub async fn put_settings(
+    stream_name: Path<String>,
+    Json(settings): Json<StreamSettings>,
+) -> Result<impl Responder, StreamError> {
+    let stream_name = stream_name.into_inner();
+    // For query mode, if the stream not found in memory map,
+    //check if it exists in the storage
+    //create stream and schema from storage
+    if !PARSEABLE.check_or_load_stream(&stream_name).await {
+        return Err(StreamNotFound(stream_name).into());
+    }
+
+    let stream = PARSEABLE.get_stream(&stream_name)?;
+    if stream.get_stream_type() == StreamType::Internal {
+        return Err(StreamError::Custom {
+            msg: "Ingestion settings can not be set for internal stream".to_string(),
+            status: StatusCode::BAD_REQUEST,
+        });
+    }
+    settings.validate()?;
+
+    PARSEABLE
+        .storage
+        .get_object_store()
+        .put_settings(&stream_name, &settings)
+        .await?;
+    stream.set_settings(settings.clone());
+
+    if PARSEABLE.options.mode == Mode::Query {
+        sync_settings_with_ingestors(&stream_name, &settings).await?;
+    }
+
+    Ok((
+        format!("set ingestion settings for log stream {stream_name}"),
+        StatusCode::OK,
+    ))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ProcessorsDryRun {
+    /// Processors to try out, those of the stream if not given
+    #[serde(default)]
+    processors: Option<Pipeline>,
+    events: Value,
+}
+
+// applies processors to sample events, flattened as they would be on ingestion,
+// and returns the resulting events without storing anything
+pub async fn test_processors(
+    stream_name: Path<String>,
+    Json(dry_run): Json<ProcessorsDryRun>,
+) -> Result<impl Responder, StreamError> {
+    let stream_name = stream_name.into_inner();
+    if !PARSEABLE.check_or_load_stream(&stream_name).await {
+        return Err(StreamNotFound(stream_name).into());
+    }
+
+    let stream = PARSEABLE.get_stream(&stream_name)?;
+    let processors = match dry_run.processors {
+        Some(processors) => {
+            processors.validate().map_err(settings::Error::from)?;
+            processors
+        }
+        None => stream.get_settings().processors,
+    };
+
+    let data = convert_array_to_object(
+        dry_run.events,
+        stream.get_time_partition().as_ref(),
+        stream.get_time_partition_limit(),
+        stream.get_custom_partition().as_ref(),
+        stream.get_schema_version(),
+        &LogSource::Json,
+    )
+    .map_err(|err| StreamError::Custom {
+        msg: err.to_string(),
+        status: StatusCode::BAD_REQUEST,
+    })?;
+    let events: Vec<Value> = data
+        .into_iter()
+        .flat_map(|json| match json {
+            Value::Array(events) => events,
+            event => vec![event],
+        })
+        .collect();
+    let received = events.len();
+    let events: Vec<Value> = events
+        .into_iter()
+        .filter_map(|event| processors.apply(event))
+        .collect();
+
+    Ok(web::Json(json!({
+        "events": events,
+        "dropped": received - events.len(),
+    })))
+}
+
 pub async fn get_stats_date(stream_name: &str, date: &str) -> Result<Stats, StreamError> {
     let event_labels = event_labels_date(stream_name, "json", date);
     let storage_size_labels = storage_size_labels_date(stream_name, date);
@@ -298,7 +415,7 @@ pub async fn get_stats(
     let time = Utc::now();
 
     let stats = {
-        let ingestion_stats = IngestionStats::new(
+        let mut ingestion_stats = IngestionStats::new(
             stats.current_stats.events,
             stats.current_stats.ingestion,
             stats.lifetime_stats.events,
@@ -307,6 +424,8 @@ pub async fn get_stats(
             stats.deleted_stats.ingestion,
             "json",
         );
+        ingestion_stats.duplicates_dropped = stats.duplicates_dropped;
+        ingestion_stats.wire_size = stats.wire_size;
         let storage_stats = StorageStats::new(
             stats.current_stats.storage,
             stats.lifetime_stats.storage,
@@ -498,6 +617,7 @@ pub mod error {
     use actix_web::http::header::ContentType;
 
     use crate::{
+        event::settings,
         hottier::HotTierError,
         metastore::MetastoreError,
         parseable::StreamNotFound,
@@ -554,6 +674,8 @@ pub mod error {
         InvalidAlertMessage(String, String),
         #[error("failed to set retention configuration due to err: {0}")]
         InvalidRetentionConfig(serde_json::Error),
+        #[error("{0}")]
+        InvalidSettings(#[from] settings::Error),
         #[error("{msg}")]
         Custom { msg: String, status: StatusCode },
         #[error("Error: {0}")]
@@ -601,6 +723,7 @@ pub mod error {
                 StreamError::InvalidAlert(_) => StatusCode::BAD_REQUEST,
                 StreamError::InvalidAlertMessage(_, _) => StatusCode::BAD_REQUEST,
                 StreamError::InvalidRetentionConfig(_) => StatusCode::BAD_REQUEST,
+                StreamError::InvalidSettings(_) => StatusCode::BAD_REQUEST,
                 StreamError::SerdeError(_) => StatusCode::BAD_REQUEST,
                 StreamError::Anyhow(_) => StatusCode::INTERNAL_SERVER_ERROR,
                 StreamError::Network(err) => err
@@ -0,0 +1,494 @@
+/*
+ * Parseable Server (C) 2022 - 2025 Parseable, Inc.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ */
+
+//! Grafana Loki push API compatibility, such that Promtail, the Grafana Agent and
+//! other Loki clients can ship logs as is, in either snappy compressed protobuf or json
+
+use std::collections::HashMap;
+
+use actix_web::web::Bytes;
+use actix_web::{HttpRequest, HttpResponse};
+use chrono::{DateTime, Utc};
+use prost::Message;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+
+use crate::event::FORMAT_KEY;
+use crate::event::format::LogSource;
+use crate::handlers::http::ingest::PostError;
+use crate::handlers::http::modal::utils::ingest_utils::{
+    authorize_stream_ingestion, decompress_snappy, get_custom_fields_from_header,
+    push_timestamped_logs, setup_stream_for_ingestion,
+};
+use crate::handlers::{
+    CONTENT_TYPE_JSON, CONTENT_TYPE_PROTOBUF, LOKI_STREAM_LABEL_KEY, STREAM_NAME_HEADER_KEY,
+    TelemetryType,
+};
+use crate::parseable::PARSEABLE;
+use crate::utils::header_parsing::ParseHeaderError;
+
+/// Columns of the rows of an entry, that labels can't be named as
+const RESERVED_COLUMNS: [&str; 2] = ["timestamp_ns", "line"];
+
+/// Messages of the Loki push protocol, as defined in `pkg/push/push.proto`
+mod proto {
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct PushRequest {
+        #[prost(message, repeated, tag = "1")]
+        pub streams: Vec<StreamAdapter>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct StreamAdapter {
+        /// Labels of the stream in the prometheus format, i.e. `{job="app", env="prod"}`
+        #[prost(string, tag = "1")]
+        pub labels: String,
+        #[prost(message, repeated, tag = "2")]
+        pub entries: Vec<EntryAdapter>,
+        #[prost(uint64, tag = "3")]
+        pub hash: u64,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct EntryAdapter {
+        #[prost(message, optional, tag = "1")]
+        pub timestamp: Option<Timestamp>,
+        #[prost(string, tag = "2")]
+        pub line: String,
+        #[prost(message, repeated, tag = "3")]
+        pub structured_metadata: Vec<LabelPairAdapter>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct LabelPairAdapter {
+        #[prost(string, tag = "1")]
+        pub name: String,
+        #[prost(string, tag = "2")]
+        pub value: String,
+    }
+
+    /// Same as `google.protobuf.Timestamp`
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Timestamp {
+        #[prost(int64, tag = "1")]
+        pub seconds: i64,
+        #[prost(int32, tag = "2")]
+        pub nanos: i32,
+    }
+}
+
+/// Json form of the push request, entries are `[<unix epoch in ns>, <line>, <structured metadata>?]`
+#[derive(Debug, Deserialize)]
+struct JsonPushRequest {
+    streams: Vec<JsonStream>,
+}
+
+#[derive(Debug, Deserialize)]
+struct JsonStream {
+    #[serde(default)]
+    stream: HashMap<String, String>,
+    #[serde(default)]
+    values: Vec<Vec<Value>>,
+}
+
+/// A stream of the push request, in either encoding
+#[derive(Debug, PartialEq)]
+struct LokiStream {
+    labels: Vec<(String, String)>,
+    entries: Vec<LokiEntry>,
+}
+
+#[derive(Debug, PartialEq)]
+struct LokiEntry {
+    timestamp: DateTime<Utc>,
+    line: String,
+    structured_metadata: Vec<(String, String)>,
+}
+
+impl LokiStream {
+    fn label(&self, name: &str) -> Option<&str> {
+        self.labels
+            .iter()
+            .find(|(label, _)| label == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// One row per entry along with the time of the entry, which is its event time. The
+    /// stream labels and structured metadata of the entry are columns, the log line is
+    /// `line` and the time of the entry in nanoseconds is `timestamp_ns`, a string such
+    /// that it isn't truncated. Labels named as either of these are prefixed with `label_`.
+    fn into_rows(self) -> impl Iterator<Item = (DateTime<Utc>, Value)> {
+        let labels = self.labels;
+        self.entries.into_iter().map(move |entry| {
+            let mut row: Map<String, Value> = labels
+                .iter()
+                .chain(entry.structured_metadata.iter())
+                .map(|(name, value)| {
+                    let name = if RESERVED_COLUMNS.contains(&name.as_str()) {
+                        format!("label_{name}")
+                    } else {
+                        name.clone()
+                    };
+                    (name, Value::String(value.clone()))
+                })
+                .collect();
+            // in range, as checked when decoding the entry
+            let timestamp_ns = entry.timestamp.timestamp_nanos_opt().unwrap_or_default();
+            row.insert(
+                "timestamp_ns".to_owned(),
+                Value::String(timestamp_ns.to_string()),
+            );
+            row.insert("line".to_owned(), Value::String(entry.line));
+            (entry.timestamp, Value::Object(row))
+        })
+    }
+}
+
+// Handler for POST /loki/api/v1/push
+// ingests entries into the stream named by the `x-p-stream` header, or else
+// by the value of the stream label named by the `x-p-loki-stream-label` header
+pub async fn push(req: HttpRequest, body: Bytes) -> Result<HttpResponse, PostError> {
+    let content_type = req
+        .headers()
+        .get("Content-Type")
+        .and_then(|h| h.to_str().ok())
+        .unwrap_or(CONTENT_TYPE_PROTOBUF);
+    let streams = if content_type.starts_with(CONTENT_TYPE_JSON) {
+        decode_json(&body)?
+    } else if content_type.starts_with(CONTENT_TYPE_PROTOBUF) {
+        decode_protobuf(&decompress_snappy(
+            &body,
+            PARSEABLE.options.max_event_payload_size,
+        )?)?
+    } else {
+        return Err(PostError::Invalid(anyhow::anyhow!(
+            "Unsupported Content-Type: {content_type}. Expected application/json or application/x-protobuf"
+        )));
+    };
+
+    let routed = route_streams(&req, streams)?;
+    for stream_name in routed.keys() {
+        authorize_stream_ingestion(&req, stream_name)?;
+    }
+
+    let mut p_custom_fields = get_custom_fields_from_header(&req);
+    p_custom_fields.insert(FORMAT_KEY.to_string(), LogSource::Loki.to_string());
+    for (stream_name, rows) in routed {
+        setup_stream_for_ingestion(&stream_name, LogSource::Loki, TelemetryType::Logs).await?;
+        push_timestamped_logs(
+            &stream_name,
+            rows,
+            &LogSource::Loki,
+            &p_custom_fields,
+            TelemetryType::Logs,
+        )
+        .await?;
+    }
+
+    // loki acknowledges a successful push with no content
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Decodes the protobuf push request, once decompressed
+fn decode_protobuf(body: &[u8]) -> Result<Vec<LokiStream>, PostError> {
+    let request = proto::PushRequest::decode(body)?;
+
+    request
+        .streams
+        .into_iter()
+        .map(|stream| -> Result<LokiStream, PostError> {
+            let entries = stream
+                .entries
+                .into_iter()
+                .map(|entry| -> Result<LokiEntry, PostError> {
+                    let timestamp = entry
+                        .timestamp
+                        .and_then(|ts| DateTime::from_timestamp(ts.seconds, ts.nanos as u32))
+                        .filter(|ts| ts.timestamp_nanos_opt().is_some())
+                        .ok_or_else(|| {
+                            PostError::Invalid(anyhow::anyhow!("Invalid timestamp of entry"))
+                        })?;
+                    Ok(LokiEntry {
+                        timestamp,
+                        line: entry.line,
+                        structured_metadata: entry
+                            .structured_metadata
+                            .into_iter()
+                            .map(|pair| (pair.name, pair.value))
+                            .collect(),
+                    })
+                })
+                .collect::<Result<_, _>>()?;
+
+            Ok(LokiStream {
+                labels: parse_labels(&stream.labels)?,
+                entries,
+            })
+        })
+        .collect()
+}
+
+fn decode_json(body: &[u8]) -> Result<Vec<LokiStream>, PostError> {
+    let request: JsonPushRequest = serde_json::from_slice(body)?;
+
+    request
+        .streams
+        .into_iter()
+        .map(|stream| -> Result<LokiStream, PostError> {
+            let mut labels: Vec<(String, String)> = stream.stream.into_iter().collect();
+            labels.sort();
+            let entries = stream
+                .values
+                .into_iter()
+                .map(parse_json_entry)
+                .collect::<Result<_, _>>()?;
+
+            Ok(LokiStream { labels, entries })
+        })
+        .collect()
+}
+
+fn parse_json_entry(value: Vec<Value>) -> Result<LokiEntry, PostError> {
+    let invalid = || PostError::Invalid(anyhow::anyhow!("Invalid entry {value:?}"));
+    let (timestamp, line, structured_metadata) = match value.as_slice() {
+        [Value::String(ts), Value::String(line)] => (ts, line, None),
+        [
+            Value::String(ts),
+            Value::String(line),
+            Value::Object(metadata),
+        ] => (ts, line, Some(metadata)),
+        _ => return Err(invalid()),
+    };
+
+    let timestamp = timestamp
+        .parse::<i64>()
+        .map(DateTime::from_timestamp_nanos)
+        .map_err(|_| invalid())?;
+    let structured_metadata = structured_metadata
+        .into_iter()
+        .flatten()
+        .map(|(name, value)| match value {
+            Value::String(value) => (name.clone(), value.clone()),
+            value => (name.clone(), value.to_string()),
+        })
+        .collect();
+
+    Ok(LokiEntry {
+        timestamp,
+        line: line.clone(),
+        structured_metadata,
+    })
+}
+
+/// Parses stream labels in the prometheus format, i.e. `{job="app", env="prod"}`
+fn parse_labels(labels: &str) -> Result<Vec<(String, String)>, PostError> {
+    let invalid = || PostError::Invalid(anyhow::anyhow!("Invalid stream labels {labels}"));
+    let mut rest = labels
+        .trim()
+        .strip_prefix('{')
+        .and_then(|s| s.strip_suffix('}'))
+        .ok_or_else(invalid)?
+        .trim_start();
+
+    let mut pairs = vec![];
+    while !rest.is_empty() {
+        let (name, value) = rest.split_once('=').ok_or_else(invalid)?;
+        let mut chars = value
+            .trim_start()
+            .strip_prefix('"')
+            .ok_or_else(invalid)?
+            .chars();
+
+        let mut value = String::new();
+        loop {
+            match chars.next().ok_or_else(invalid)? {
+                '"' => break,
+                '\\' => match chars.next().ok_or_else(invalid)? {
+                    'n' => value.push('\n'),
+                    't' => value.push('\t'),
+                    c => value.push(c),
+                },
+                c => value.push(c),
+            }
+        }
+        pairs.push((name.trim().to_owned(), value));
+
+        rest = chars.as_str().trim_start();
+        rest = rest.strip_prefix(',').unwrap_or(rest).trim_start();
+    }
+
+    Ok(pairs)
+}
+
+// Groups the rows of all entries by the stream they are meant for
+fn route_streams(
+    req: &HttpRequest,
+    streams: Vec<LokiStream>,
+) -> Result<HashMap<String, Vec<(DateTime<Utc>, Value)>>, PostError> {
+    let header = |key| {
+        req.headers()
+            .get(key)
+            .and_then(|h| h.to_str().ok())
+            .map(str::to_owned)
+    };
+
+    let mut routed: HashMap<String, Vec<(DateTime<Utc>, Value)>> = HashMap::new();
+    if let Some(stream_name) = header(STREAM_NAME_HEADER_KEY) {
+        routed.insert(
+            stream_name,
+            streams
+                .into_iter()
+                .flat_map(LokiStream::into_rows)
+                .collect(),
+        );
+        return Ok(routed);
+    }
+
+    let Some(stream_label) = header(LOKI_STREAM_LABEL_KEY) else {
+        return Err(PostError::Header(ParseHeaderError::MissingStreamName));
+    };
+    for stream in streams {
+        let Some(stream_name) = stream.label(&stream_label).map(str::to_owned) else {
+            return Err(PostError::Invalid(anyhow::anyhow!(
+                "Stream label {stream_label} is missing from a stream of the push request"
+            )));
+        };
+        routed
+            .entry(stream_name)
+            .or_default()
+            .extend(stream.into_rows());
+    }
+
+    Ok(routed)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn parse_prometheus_labels() {
+        assert_eq!(
+            parse_labels(r#"{job="varlogs", filename="/var/log/\"a\".log",env="prod"}"#).unwrap(),
+            vec![
+                ("job".to_owned(), "varlogs".to_owned()),
+                ("filename".to_owned(), "/var/log/\"a\".log".to_owned()),
+                ("env".to_owned(), "prod".to_owned()),
+            ]
+        );
+        assert!(parse_labels("{}").unwrap().is_empty());
+        assert!(parse_labels(r#"{job="varlogs}"#).is_err());
+    }
+
+    #[test]
+    fn decode_snappy_protobuf() {
+        let request = proto::PushRequest {
+            streams: vec![proto::StreamAdapter {
+                labels: r#"{job="app"}"#.to_owned(),
+                entries: vec![proto::EntryAdapter {
+                    timestamp: Some(proto::Timestamp {
+                        seconds: 1_700_000_000,
+                        nanos: 123_456_789,
+                    }),
+                    line: "hello".to_owned(),
+                    structured_metadata: vec![proto::LabelPairAdapter {
+                        name: "trace_id".to_owned(),
+                        value: "abc".to_owned(),
+                    }],
+                }],
+                hash: 0,
+            }],
+        };
+        let body = snap::raw::Encoder::new()
+            .compress_vec(&request.encode_to_vec())
+            .unwrap();
+
+        let rows: Vec<(DateTime<Utc>, Value)> =
+            decode_protobuf(&decompress_snappy(&body, usize::MAX).unwrap())
+                .unwrap()
+                .into_iter()
+                .flat_map(LokiStream::into_rows)
+                .collect();
+
+        assert_eq!(
+            rows,
+            vec![(
+                DateTime::from_timestamp(1_700_000_000, 123_456_789).unwrap(),
+                json!({
+                    "job": "app",
+                    "trace_id": "abc",
+                    "timestamp_ns": "1700000000123456789",
+                    "line": "hello",
+                })
+            )]
+        );
+    }
+
+    #[test]
+    fn decode_json_push_request() {
+        let body = br#"{"streams": [{
+            "stream": {"job": "app"},
+            "values": [
+                ["1700000000000000000", "first"],
+                ["1700000000500000000", "second", {"trace_id": "abc"}]
+            ]
+        }]}"#;
+
+        let streams = decode_json(body).unwrap();
+
+        assert_eq!(streams.len(), 1);
+        assert_eq!(streams[0].label("job"), Some("app"));
+        let rows: Vec<Value> = streams
+            .into_iter()
+            .flat_map(LokiStream::into_rows)
+            .map(|(_, row)| row)
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                json!({"job": "app", "timestamp_ns": "1700000000000000000", "line": "first"}),
+                json!({
+                    "job": "app",
+                    "trace_id": "abc",
+                    "timestamp_ns": "1700000000500000000",
+                    "line": "second",
+                }),
+            ]
+        );
+        assert!(decode_json(br#"{"streams": [{"values": [["now", "line"]]}]}"#).is_err());
+    }
+
+    #[test]
+    fn labels_named_as_columns_are_prefixed() {
+        let stream = LokiStream {
+            labels: vec![("line".to_owned(), "label".to_owned())],
+            entries: vec![LokiEntry {
+                timestamp: DateTime::from_timestamp(1_700_000_000, 0).unwrap(),
+                line: "hello".to_owned(),
+                structured_metadata: vec![],
+            }],
+        };
+
+        let (_, row) = stream.into_rows().next().unwrap();
+
+        assert_eq!(row["line"], "hello");
+        assert_eq!(row["label_line"], "label");
+    }
+}
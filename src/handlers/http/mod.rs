@@ -33,20 +33,25 @@ pub mod alerts;
 pub mod cluster;
 pub mod correlation;
 pub mod demo_data;
+pub mod elastic;
 pub mod health_check;
 pub mod ingest;
 mod kinesis;
 pub mod llm;
+pub mod log_format;
 pub mod logstream;
+pub mod loki;
 pub mod middleware;
 pub mod modal;
 pub mod oidc;
 pub mod prism_home;
 pub mod prism_logstream;
+pub mod prometheus;
 pub mod query;
 pub mod rbac;
 pub mod resource_check;
 pub mod role;
+pub mod splunk;
 pub mod targets;
 pub mod users;
 pub const API_BASE_PATH: &str = "api";
@@ -28,6 +28,7 @@ use tracing::warn;
 
 use crate::{
     catalog::remove_manifest_from_snapshot,
+    event::settings::StreamSettings,
     handlers::http::logstream::error::StreamError,
     parseable::{PARSEABLE, StreamNotFound},
     stats,
@@ -98,3 +99,35 @@ pub async fn put_stream(
 
     Ok(("Log stream created", StatusCode::OK))
 }
+
+// PUT "/logstream/{logstream}/settings/sync"
+// sets the ingestion settings stored by the querier, on ingestors
+pub async fn put_settings(
+    stream_name: Path<String>,
+    Json(settings): Json<StreamSettings>,
+) -> Result<impl Responder, StreamError> {
+    let stream_name = stream_name.into_inner();
+    // if the stream not found in memory map,
+    //check if it exists in the storage
+    //create stream and schema from storage
+    if !PARSEABLE.streams.contains(&stream_name)
+        && !PARSEABLE
+            .create_stream_and_schema_from_storage(&stream_name)
+            .await
+            .unwrap_or(false)
+    {
+        return Err(StreamNotFound(stream_name.clone()).into());
+    }
+
+    PARSEABLE
+        .storage()
+        .get_object_store()
+        .put_settings(&stream_name, &settings)
+        .await?;
+    PARSEABLE.get_stream(&stream_name)?.set_settings(settings);
+
+    Ok((
+        format!("set ingestion settings for log stream {stream_name}"),
+        StatusCode::OK,
+    ))
+}
@@ -37,10 +37,11 @@ use crate::{
     handlers::{
         airplane,
         http::{
-            base_path, ingest, logstream,
+            base_path, ingest, log_format, logstream,
             middleware::{DisAllowRootUser, RouteExt},
             resource_check, role,
         },
+        otel_grpc,
     },
     migration,
     parseable::PARSEABLE,
@@ -70,18 +71,31 @@ impl ParseableServer for IngestServer {
                     .service(Server::get_ingest_factory().wrap(from_fn(
                         resource_check::check_resource_utilization_middleware,
                     )))
+                    .service(Server::get_ingest_elastic_factory().wrap(from_fn(
+                        resource_check::check_resource_utilization_middleware,
+                    )))
+                    .service(Server::get_ingest_prometheus_factory().wrap(from_fn(
+                        resource_check::check_resource_utilization_middleware,
+                    )))
                     .service(Self::logstream_api())
                     .service(Server::get_about_factory())
                     .service(Self::analytics_factory())
                     .service(Server::get_liveness_factory())
                     .service(Self::get_user_webscope())
                     .service(Self::get_user_role_webscope())
+                    .service(Self::get_log_format_webscope())
                     .service(Server::get_metrics_webscope())
                     .service(Server::get_readiness_factory())
                     .service(Server::get_demo_data_webscope()),
             )
             .service(Server::get_ingest_otel_factory().wrap(from_fn(
                 resource_check::check_resource_utilization_middleware,
+            )))
+            .service(Server::get_ingest_loki_factory().wrap(from_fn(
+                resource_check::check_resource_utilization_middleware,
+            )))
+            .service(Server::get_ingest_splunk_factory().wrap(from_fn(
+                resource_check::check_resource_utilization_middleware,
             )));
     }
 
@@ -118,6 +132,10 @@ impl ParseableServer for IngestServer {
 
         migration::run_migration(&PARSEABLE).await?;
 
+        if let Err(err) = log_format::load().await {
+            tracing::error!("Failed to load log formats: {err}");
+        }
+
         // local sync on init
         let startup_sync_handle = tokio::spawn(async {
             if let Err(e) = sync_start().await {
@@ -130,6 +148,12 @@ impl ParseableServer for IngestServer {
         thread::spawn(|| sync::handler(cancel_rx));
 
         tokio::spawn(airplane::server());
+        if let Some(otel_grpc) = otel_grpc::server()? {
+            tokio::spawn(otel_grpc);
+        }
+        // stream metadata is loaded by now, which the listeners create streams against
+        crate::syslog::init();
+        crate::fluent::init();
 
         // Ingestors shouldn't have to deal with OpenId auth flow
         let result = self.start(shutdown_rx, prometheus.clone(), None).await;
@@ -176,6 +200,23 @@ impl IngestServer {
                     .route(web::put().to(ingestor_role::put).authorize(Action::PutRole)),
             )
     }
+    // get the log format webscope, to sync the formats registered on the querier
+    pub fn get_log_format_webscope() -> Scope {
+        web::scope("/logformat").service(
+            web::resource("/{name}/sync")
+                .route(
+                    web::put()
+                        .to(log_format::put_sync)
+                        .authorize(Action::PutLogFormat),
+                )
+                .route(
+                    web::delete()
+                        .to(log_format::delete_sync)
+                        .authorize(Action::DeleteLogFormat),
+                ),
+        )
+    }
+
     // get the user webscope
     pub fn get_user_webscope() -> Scope {
         web::scope("/user")
@@ -272,6 +313,14 @@ impl IngestServer {
                             .authorize_for_resource(Action::GetStats),
                     ),
                 )
+                .service(
+                    // PUT "/logstream/{logstream}/settings/sync" ==> Sync ingestion settings set on the querier
+                    web::resource("/settings/sync").route(
+                        web::put()
+                            .to(ingestor_logstream::put_settings)
+                            .authorize_for_resource(Action::PutStreamSettings),
+                    ),
+                )
                 .service(
                     web::scope("/retention").service(
                         web::resource("/cleanup").route(
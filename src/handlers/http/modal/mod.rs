@@ -38,6 +38,7 @@ use crate::{
     alerts::{ALERTS, get_alert_manager, target::TARGETS},
     cli::Options,
     correlation::CORRELATIONS,
+    handlers::http::log_format,
     hottier::{HotTierManager, StreamHotTier},
     metastore::metastore_traits::MetastoreObject,
     oidc::{Claims, DiscoveredClient},
@@ -191,6 +192,13 @@ pub trait ParseableServer {
 }
 
 pub async fn load_on_init() -> anyhow::Result<()> {
+    if let Err(err) = log_format::load()
+        .await
+        .context("Failed to load log formats")
+    {
+        error!("{err}");
+    }
+
     // Run all loading operations concurrently
     let (correlations_result, filters_result, dashboards_result, alerts_result, targets_result) =
         future::join5(
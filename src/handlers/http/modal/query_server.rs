@@ -55,6 +55,7 @@ impl ParseableServer for QueryServer {
             .service(
                 web::scope(&base_path())
                     .service(Server::get_correlation_webscope())
+                    .service(Server::get_log_format_webscope())
                     .service(Server::get_query_factory().wrap(from_fn(
                         resource_check::check_resource_utilization_middleware,
                     )))
@@ -329,6 +330,31 @@ impl QueryServer {
                                     .authorize_for_resource(Action::GetRetention),
                             ),
                     )
+                    .service(
+                        web::resource("/settings")
+                            // PUT "/logstream/{logstream}/settings" ==> Set ingestion settings for given logstream
+                            .route(
+                                web::put()
+                                    .to(logstream::put_settings)
+                                    .authorize_for_resource(Action::PutStreamSettings),
+                            )
+                            // GET "/logstream/{logstream}/settings" ==> Get ingestion settings for given logstream
+                            .route(
+                                web::get()
+                                    .to(logstream::get_settings)
+                                    .authorize_for_resource(Action::GetStreamSettings),
+                            ),
+                    )
+                    .service(
+                        // POST "/logstream/{logstream}/processors/test" ==> Dry run processors on sample events
+                        web::resource("/processors/test")
+                            .route(
+                                web::post()
+                                    .to(logstream::test_processors)
+                                    .authorize_for_resource(Action::GetStreamSettings),
+                            )
+                            .app_data(web::JsonConfig::default().limit(max_event_payload_size())),
+                    )
                     .service(
                         web::resource("/hottier")
                             // PUT "/logstream/{logstream}/hottier" ==> Set hottier for given logstream
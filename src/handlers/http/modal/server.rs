@@ -42,6 +42,7 @@ use crate::sync::sync_start;
 
 use actix_web::Resource;
 use actix_web::Scope;
+use actix_web::guard;
 use actix_web::middleware::from_fn;
 use actix_web::web;
 use actix_web::web::resource;
@@ -53,9 +54,9 @@ use tokio::sync::oneshot;
 
 use crate::{
     handlers::http::{
-        self, ingest, llm, logstream,
+        self, elastic, ingest, llm, logstream, loki,
         middleware::{DisAllowRootUser, RouteExt},
-        oidc, role,
+        oidc, prometheus, role, splunk,
     },
     parseable::PARSEABLE,
     rbac::role::Action,
@@ -76,12 +77,19 @@ impl ParseableServer for Server {
             .service(
                 web::scope(&base_path())
                     .service(Self::get_correlation_webscope())
+                    .service(Self::get_log_format_webscope())
                     .service(Self::get_query_factory().wrap(from_fn(
                         resource_check::check_resource_utilization_middleware,
                     )))
                     .service(Self::get_ingest_factory().wrap(from_fn(
                         resource_check::check_resource_utilization_middleware,
                     )))
+                    .service(Self::get_ingest_elastic_factory().wrap(from_fn(
+                        resource_check::check_resource_utilization_middleware,
+                    )))
+                    .service(Self::get_ingest_prometheus_factory().wrap(from_fn(
+                        resource_check::check_resource_utilization_middleware,
+                    )))
                     .service(Self::get_liveness_factory())
                     .service(Self::get_readiness_factory())
                     .service(Self::get_about_factory())
@@ -111,6 +119,12 @@ impl ParseableServer for Server {
             .service(Self::get_ingest_otel_factory().wrap(from_fn(
                 resource_check::check_resource_utilization_middleware,
             )))
+            .service(Self::get_ingest_loki_factory().wrap(from_fn(
+                resource_check::check_resource_utilization_middleware,
+            )))
+            .service(Self::get_ingest_splunk_factory().wrap(from_fn(
+                resource_check::check_resource_utilization_middleware,
+            )))
             .service(Self::get_generated());
     }
 
@@ -162,6 +176,12 @@ impl ParseableServer for Server {
 
         tokio::spawn(handlers::livetail::server());
         tokio::spawn(handlers::airplane::server());
+        if let Some(otel_grpc) = handlers::otel_grpc::server()? {
+            tokio::spawn(otel_grpc);
+        }
+        // stream metadata is loaded by now, which the listeners create streams against
+        crate::syslog::init();
+        crate::fluent::init();
 
         let result = self
             .start(shutdown_rx, prometheus.clone(), PARSEABLE.options.openid())
@@ -252,6 +272,35 @@ impl Server {
             )
     }
 
+    pub fn get_log_format_webscope() -> Scope {
+        web::scope("/logformat")
+            .service(
+                web::resource("").route(
+                    web::get()
+                        .to(http::log_format::list)
+                        .authorize(Action::GetLogFormat),
+                ),
+            )
+            .service(
+                web::resource("/{name}")
+                    .route(
+                        web::get()
+                            .to(http::log_format::get)
+                            .authorize(Action::GetLogFormat),
+                    )
+                    .route(
+                        web::put()
+                            .to(http::log_format::put)
+                            .authorize(Action::PutLogFormat),
+                    )
+                    .route(
+                        web::delete()
+                            .to(http::log_format::delete)
+                            .authorize(Action::DeleteLogFormat),
+                    ),
+            )
+    }
+
     pub fn get_alerts_webscope() -> Scope {
         web::scope("/alerts")
             .service(
@@ -504,6 +553,31 @@ impl Server {
                                     .authorize_for_resource(Action::GetRetention),
                             ),
                     )
+                    .service(
+                        web::resource("/settings")
+                            // PUT "/logstream/{logstream}/settings" ==> Set ingestion settings for given logstream
+                            .route(
+                                web::put()
+                                    .to(logstream::put_settings)
+                                    .authorize_for_resource(Action::PutStreamSettings),
+                            )
+                            // GET "/logstream/{logstream}/settings" ==> Get ingestion settings for given logstream
+                            .route(
+                                web::get()
+                                    .to(logstream::get_settings)
+                                    .authorize_for_resource(Action::GetStreamSettings),
+                            ),
+                    )
+                    .service(
+                        // POST "/logstream/{logstream}/processors/test" ==> Dry run processors on sample events
+                        web::resource("/processors/test")
+                            .route(
+                                web::post()
+                                    .to(logstream::test_processors)
+                                    .authorize_for_resource(Action::GetStreamSettings),
+                            )
+                            .app_data(web::JsonConfig::default().limit(max_event_payload_size())),
+                    )
                     .service(
                         web::resource("/hottier")
                             // PUT "/logstream/{logstream}/hottier" ==> Set hottier for given logstream
@@ -529,12 +603,128 @@ impl Server {
     // get the factory for the ingest route
     pub fn get_ingest_factory() -> Resource {
         web::resource("/ingest")
+            .route(
+                web::post()
+                    .guard(guard::fn_guard(ingest::is_csv_request))
+                    .to(ingest::ingest_csv)
+                    .authorize_for_resource(Action::Ingest),
+            )
+            .route(
+                web::post()
+                    .guard(guard::fn_guard(ingest::is_logfmt_request))
+                    .to(ingest::ingest_logfmt)
+                    .authorize_for_resource(Action::Ingest),
+            )
+            .route(
+                web::post()
+                    .guard(guard::fn_guard(ingest::is_ndjson_request))
+                    .to(ingest::ingest_ndjson)
+                    .authorize_for_resource(Action::Ingest),
+            )
             .route(
                 web::post()
                     .to(ingest::ingest)
                     .authorize_for_resource(Action::Ingest),
             )
             .app_data(web::JsonConfig::default().limit(max_event_payload_size()))
+            .app_data(web::PayloadConfig::default().limit(max_event_payload_size()))
+    }
+
+    // get the elasticsearch `_bulk` compatible ingestion factory
+    pub fn get_ingest_elastic_factory() -> Scope {
+        web::scope("/elastic")
+            .service(
+                // GET "/elastic" ==> Cluster info, checked by ES clients on startup
+                web::resource("").route(web::get().to(elastic::info).authorize(Action::Ingest)),
+            )
+            .service(
+                // POST "/elastic/_bulk" ==> Ingest into the streams named by `_index`
+                web::resource("/_bulk")
+                    .route(
+                        web::post()
+                            .to(elastic::bulk)
+                            .authorize_for_resource(Action::Ingest),
+                    )
+                    .route(
+                        web::put()
+                            .to(elastic::bulk)
+                            .authorize_for_resource(Action::Ingest),
+                    )
+                    .app_data(web::PayloadConfig::default().limit(max_event_payload_size())),
+            )
+            .service(
+                // POST "/elastic/{logstream}/_bulk" ==> Same as above, defaulting to the given stream
+                web::resource("/{logstream}/_bulk")
+                    .route(
+                        web::post()
+                            .to(elastic::bulk_with_index)
+                            .authorize_for_resource(Action::Ingest),
+                    )
+                    .route(
+                        web::put()
+                            .to(elastic::bulk_with_index)
+                            .authorize_for_resource(Action::Ingest),
+                    )
+                    .app_data(web::PayloadConfig::default().limit(max_event_payload_size())),
+            )
+    }
+
+    // get the prometheus remote_write receiver factory
+    pub fn get_ingest_prometheus_factory() -> Resource {
+        web::resource("/prom/write")
+            .route(
+                web::post()
+                    .to(prometheus::remote_write)
+                    .authorize_for_resource(Action::Ingest),
+            )
+            .app_data(web::PayloadConfig::default().limit(max_event_payload_size()))
+    }
+
+    // /loki/api/v1/push endpoint to be used by Loki clients, e.g. Promtail
+    pub fn get_ingest_loki_factory() -> Resource {
+        web::resource("/loki/api/v1/push")
+            .route(
+                web::post()
+                    .to(loki::push)
+                    .authorize_for_resource(Action::Ingest),
+            )
+            .app_data(web::PayloadConfig::default().limit(max_event_payload_size()))
+    }
+
+    // /services/collector endpoints compatible with the Splunk HTTP Event Collector
+    pub fn get_ingest_splunk_factory() -> Scope {
+        web::scope("/services/collector")
+            .service(
+                // POST "/services/collector/event" ==> Ingest concatenated json events
+                web::resource(["", "/event"])
+                    .route(
+                        web::post()
+                            .to(splunk::event)
+                            .authorize_for_resource(Action::Ingest),
+                    )
+                    .app_data(web::PayloadConfig::default().limit(max_event_payload_size())),
+            )
+            .service(
+                // POST "/services/collector/raw" ==> Ingest each line of the body as an event
+                web::resource("/raw")
+                    .route(
+                        web::post()
+                            .to(splunk::raw)
+                            .authorize_for_resource(Action::Ingest),
+                    )
+                    .app_data(web::PayloadConfig::default().limit(max_event_payload_size())),
+            )
+            .service(
+                // POST "/services/collector/ack" ==> Status of the acks handed out to clients
+                web::resource("/ack").route(web::post().to(splunk::ack).authorize(Action::Ingest)),
+            )
+            .service(
+                // GET "/services/collector/health" ==> Health check for HEC clients
+                web::resource("/health").route(web::get().to(splunk::health)),
+            )
+            // HEC credentials are only accepted on these routes, the token being the
+            // `username:password` of a user rather than one issued by Splunk
+            .wrap(from_fn(splunk::splunk_auth_middleware))
     }
 
     // /v1/logs endpoint to be used for OTEL log ingestion only
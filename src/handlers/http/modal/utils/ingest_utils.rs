@@ -16,35 +16,65 @@
  *
  */
 
+use std::io::Read;
+
 use actix_web::HttpRequest;
-use actix_web::http::header::USER_AGENT;
-use chrono::Utc;
+use actix_web::dev::{Decompress, Payload};
+use actix_web::http::header::{CONTENT_ENCODING, ContentEncoding, USER_AGENT};
+use actix_web::web::{self, Bytes, BytesMut};
+use anyhow::anyhow;
+use chrono::{DateTime, Utc};
+use flate2::read::{MultiGzDecoder, ZlibDecoder};
+use futures::StreamExt;
 use opentelemetry_proto::tonic::{
-    logs::v1::LogsData, metrics::v1::MetricsData, trace::v1::TracesData,
+    collector::{
+        logs::v1::ExportLogsServiceRequest, metrics::v1::ExportMetricsServiceRequest,
+        trace::v1::ExportTraceServiceRequest,
+    },
+    logs::v1::LogsData,
+    metrics::v1::MetricsData,
+    trace::v1::TracesData,
 };
+use prost::Message as _;
 use serde_json::Value;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use tracing::warn;
 
 use crate::{
     event::{
         FORMAT_KEY, SOURCE_IP_KEY, USER_AGENT_KEY,
-        format::{EventFormat, LogSource, json},
+        format::{EventFormat, LogSource, LogSourceEntry, json},
     },
     handlers::{
-        EXTRACT_LOG_KEY, LOG_SOURCE_KEY, STREAM_NAME_HEADER_KEY, TelemetryType,
+        CSV_DELIMITER_KEY, CSV_HAS_HEADER_KEY, CSV_QUOTE_KEY, EXTRACT_LOG_KEY, LOG_SOURCE_KEY,
+        STREAM_NAME_HEADER_KEY, TelemetryType,
         http::{
             ingest::PostError,
             kinesis::{Message, flatten_kinesis_logs},
         },
     },
-    otel::{logs::flatten_otel_logs, metrics::flatten_otel_metrics, traces::flatten_otel_traces},
+    otel::{
+        logs::{flatten_otel_logs, flatten_otel_protobuf},
+        metrics::{flatten_otel_metrics, flatten_otel_metrics_protobuf},
+        traces::{flatten_otel_traces, flatten_otel_traces_protobuf},
+    },
     parseable::PARSEABLE,
+    rbac::{self, Users, role::Action},
     storage::StreamType,
-    utils::json::{convert_array_to_object, flatten::convert_to_array},
+    utils::{
+        actix::extract_session_key_from_req,
+        json::{convert_array_to_object, flatten::convert_to_array},
+    },
 };
 
-const IGNORE_HEADERS: [&str; 3] = [STREAM_NAME_HEADER_KEY, LOG_SOURCE_KEY, EXTRACT_LOG_KEY];
+const IGNORE_HEADERS: [&str; 6] = [
+    STREAM_NAME_HEADER_KEY,
+    LOG_SOURCE_KEY,
+    EXTRACT_LOG_KEY,
+    CSV_DELIMITER_KEY,
+    CSV_QUOTE_KEY,
+    CSV_HAS_HEADER_KEY,
+];
 const MAX_CUSTOM_FIELDS: usize = 10;
 const MAX_FIELD_VALUE_LENGTH: usize = 100;
 
@@ -136,6 +166,92 @@ pub async fn flatten_and_push_logs(
     Ok(())
 }
 
+/// Decodes an OTLP `Export*ServiceRequest` protobuf body according to the log source
+/// and returns the flattened records, ready to be pushed with [`push_otel_records`]
+pub fn decode_otel_protobuf(body: &[u8], log_source: &LogSource) -> Result<Vec<Value>, PostError> {
+    let records = match log_source {
+        LogSource::OtelLogs => flatten_otel_protobuf(&ExportLogsServiceRequest::decode(body)?),
+        LogSource::OtelMetrics => {
+            flatten_otel_metrics_protobuf(&ExportMetricsServiceRequest::decode(body)?)
+        }
+        LogSource::OtelTraces => {
+            flatten_otel_traces_protobuf(&ExportTraceServiceRequest::decode(body)?)
+        }
+        _ => {
+            return Err(PostError::Invalid(anyhow!(
+                "Protobuf ingestion is only supported for otel logs, metrics and traces"
+            )));
+        }
+    };
+
+    Ok(records)
+}
+
+/// Pushes already flattened otel records into the stream,
+/// applying the same checks as [`flatten_and_push_logs`]
+pub async fn push_otel_records(
+    records: Vec<Value>,
+    stream_name: &str,
+    log_source: &LogSource,
+    p_custom_fields: &HashMap<String, String>,
+    time_partition: Option<String>,
+    telemetry_type: TelemetryType,
+) -> Result<(), PostError> {
+    // Verify the dataset fields count
+    verify_dataset_fields_count(stream_name)?;
+
+    for record in records {
+        push_logs(
+            stream_name,
+            record,
+            log_source,
+            p_custom_fields,
+            time_partition.clone(),
+            telemetry_type,
+        )
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Pushes rows along with their time of occurrence, which becomes their `p_timestamp`
+/// in place of the time at ingestion. Rows that occurred within the same millisecond
+/// are pushed as one event.
+pub async fn push_timestamped_logs(
+    stream_name: &str,
+    rows: Vec<(DateTime<Utc>, Value)>,
+    log_source: &LogSource,
+    p_custom_fields: &HashMap<String, String>,
+    telemetry_type: TelemetryType,
+) -> Result<(), PostError> {
+    // Verify the dataset fields count
+    verify_dataset_fields_count(stream_name)?;
+
+    let mut events: BTreeMap<i64, Vec<Value>> = BTreeMap::new();
+    for (timestamp, row) in rows {
+        events
+            .entry(timestamp.timestamp_millis())
+            .or_default()
+            .push(row);
+    }
+    for (timestamp, rows) in events {
+        let p_timestamp = DateTime::from_timestamp_millis(timestamp).unwrap_or_else(Utc::now);
+        push_logs_at(
+            stream_name,
+            Value::Array(rows),
+            log_source,
+            p_custom_fields,
+            None,
+            telemetry_type,
+            p_timestamp,
+        )
+        .await?;
+    }
+
+    Ok(())
+}
+
 pub async fn push_logs(
     stream_name: &str,
     json: Value,
@@ -143,6 +259,27 @@ pub async fn push_logs(
     p_custom_fields: &HashMap<String, String>,
     time_partition: Option<String>,
     telemetry_type: TelemetryType,
+) -> Result<(), PostError> {
+    push_logs_at(
+        stream_name,
+        json,
+        log_source,
+        p_custom_fields,
+        time_partition,
+        telemetry_type,
+        Utc::now(),
+    )
+    .await
+}
+
+async fn push_logs_at(
+    stream_name: &str,
+    json: Value,
+    log_source: &LogSource,
+    p_custom_fields: &HashMap<String, String>,
+    time_partition: Option<String>,
+    telemetry_type: TelemetryType,
+    p_timestamp: DateTime<Utc>,
 ) -> Result<(), PostError> {
     let stream = PARSEABLE.get_stream(stream_name)?;
     let time_partition_limit = PARSEABLE
@@ -151,7 +288,11 @@ pub async fn push_logs(
     let static_schema_flag = stream.get_static_schema_flag();
     let custom_partition = stream.get_custom_partition();
     let schema_version = stream.get_schema_version();
-    let p_timestamp = Utc::now();
+    let settings = stream.get_settings();
+    // the fields added from the request are redacted as well, GeoIP still sees the source IP
+    let redacted_custom_fields = settings
+        .redaction
+        .apply_custom_fields(stream_name, p_custom_fields);
 
     let data = convert_array_to_object(
         json,
@@ -163,22 +304,26 @@ pub async fn push_logs(
     )?;
 
     for json in data {
+        // the stream's settings run on the flattened events, before the schema is inferred
+        let Some(json) = settings.apply(stream_name, json, &stream.rate_limiter, p_custom_fields)
+        else {
+            continue;
+        };
         let origin_size = serde_json::to_vec(&json).unwrap().len() as u64; // string length need not be the same as byte length
         let schema = PARSEABLE.get_stream(stream_name)?.get_schema_raw();
-        json::Event { json, p_timestamp }
-            .into_event(
-                stream_name.to_owned(),
-                origin_size,
-                &schema,
-                static_schema_flag,
-                custom_partition.as_ref(),
-                time_partition.as_ref(),
-                schema_version,
-                StreamType::UserDefined,
-                p_custom_fields,
-                telemetry_type,
-            )?
-            .process()?;
+        let event = json::Event { json, p_timestamp }.into_event(
+            stream_name.to_owned(),
+            origin_size,
+            &schema,
+            static_schema_flag,
+            custom_partition.as_ref(),
+            time_partition.as_ref(),
+            schema_version,
+            StreamType::UserDefined,
+            &redacted_custom_fields,
+            telemetry_type,
+        )?;
+        event.process()?;
     }
     Ok(())
 }
@@ -193,12 +338,29 @@ pub fn get_custom_fields_from_header(req: &HttpRequest) -> HashMap<String, Strin
     let conn = req.connection_info().clone();
 
     let source_ip = conn.realip_remote_addr().unwrap_or_default();
+
+    collect_custom_fields(
+        user_agent,
+        source_ip,
+        req.headers()
+            .iter()
+            .map(|(header_name, header_value)| (header_name.as_str(), header_value.to_str().ok())),
+    )
+}
+
+/// Builds the `p_` custom fields from the user agent, source ip and `x-p-` prefixed headers,
+/// shared between HTTP headers and gRPC metadata
+pub fn collect_custom_fields<'a>(
+    user_agent: &str,
+    source_ip: &str,
+    headers: impl Iterator<Item = (&'a str, Option<&'a str>)>,
+) -> HashMap<String, String> {
     let mut p_custom_fields = HashMap::new();
     p_custom_fields.insert(USER_AGENT_KEY.to_string(), user_agent.to_string());
     p_custom_fields.insert(SOURCE_IP_KEY.to_string(), source_ip.to_string());
 
     // Iterate through headers and add custom fields
-    for (header_name, header_value) in req.headers().iter() {
+    for (header_name, header_value) in headers {
         // Check if we've reached the maximum number of custom fields
         if p_custom_fields.len() >= MAX_CUSTOM_FIELDS {
             warn!(
@@ -208,10 +370,9 @@ pub fn get_custom_fields_from_header(req: &HttpRequest) -> HashMap<String, Strin
             break;
         }
 
-        let header_name = header_name.as_str();
         if header_name.starts_with("x-p-")
             && !IGNORE_HEADERS.contains(&header_name)
-            && let Ok(value) = header_value.to_str()
+            && let Some(value) = header_value
         {
             let key = header_name.trim_start_matches("x-p-");
             if !key.is_empty() {
@@ -235,7 +396,7 @@ pub fn get_custom_fields_from_header(req: &HttpRequest) -> HashMap<String, Strin
         }
 
         if header_name == LOG_SOURCE_KEY
-            && let Ok(value) = header_value.to_str()
+            && let Some(value) = header_value
         {
             p_custom_fields.insert(FORMAT_KEY.to_string(), value.to_string());
         }
@@ -244,7 +405,7 @@ pub fn get_custom_fields_from_header(req: &HttpRequest) -> HashMap<String, Strin
     p_custom_fields
 }
 
-fn verify_dataset_fields_count(stream_name: &str) -> Result<(), PostError> {
+pub fn verify_dataset_fields_count(stream_name: &str) -> Result<(), PostError> {
     let fields_count = PARSEABLE
         .get_stream(stream_name)?
         .get_schema()
@@ -276,6 +437,149 @@ fn verify_dataset_fields_count(stream_name: &str) -> Result<(), PostError> {
     Ok(())
 }
 
+/// Reads the body of an ingestion request, decoding it as per its `Content-Encoding`, one of
+/// `gzip`, `deflate`, `br`, `zstd` or `snappy`, where the latter is either of the block or
+/// framed format.
+/// The payload limit applies to both the body as received and once decompressed, returns
+/// the decompressed body along with its size as received.
+pub async fn read_body(
+    req: &HttpRequest,
+    mut payload: web::Payload,
+) -> Result<(Bytes, u64), PostError> {
+    let max_size = PARSEABLE.options.max_event_payload_size;
+    let mut body = BytesMut::new();
+    while let Some(chunk) = payload.next().await {
+        let chunk = chunk.map_err(|e| PostError::Invalid(anyhow!("Failed to read body, {e}")))?;
+        if body.len() + chunk.len() > max_size {
+            return Err(PostError::PayloadTooLarge(max_size));
+        }
+        body.extend_from_slice(&chunk);
+    }
+    let wire_size = body.len() as u64;
+
+    let encoding = req
+        .headers()
+        .get(CONTENT_ENCODING)
+        .and_then(|h| h.to_str().ok())
+        .map(|h| h.trim().to_lowercase());
+    let body = match encoding.as_deref() {
+        None | Some("" | "identity") => body.freeze(),
+        Some("br") => decompress_brotli(body.freeze(), max_size).await?.into(),
+        Some(encoding) => decompress(encoding, &body, max_size)?.into(),
+    };
+
+    Ok((body, wire_size))
+}
+
+/// Decompresses the body, failing as soon as it grows larger than `max_size`
+pub fn decompress(encoding: &str, body: &[u8], max_size: usize) -> Result<Vec<u8>, PostError> {
+    // stream identifier chunk that starts the snappy framed format
+    const SNAPPY_STREAM_IDENTIFIER: &[u8] = b"\xff\x06\x00\x00sNaPpY";
+
+    let reader: Box<dyn Read + '_> = match encoding {
+        "gzip" | "x-gzip" => Box::new(MultiGzDecoder::new(body)),
+        "deflate" => Box::new(ZlibDecoder::new(body)),
+        "zstd" => {
+            Box::new(zstd::stream::read::Decoder::new(body).map_err(|e| {
+                PostError::Invalid(anyhow!("Failed to decompress zstd payload, {e}"))
+            })?)
+        }
+        "snappy" | "x-snappy-framed" if body.starts_with(SNAPPY_STREAM_IDENTIFIER) => {
+            Box::new(snap::read::FrameDecoder::new(body))
+        }
+        "snappy" => return decompress_snappy(body, max_size),
+        encoding => {
+            return Err(PostError::Invalid(anyhow!(
+                "Unsupported Content-Encoding: {encoding}. Expected gzip, deflate, br, zstd or snappy"
+            )));
+        }
+    };
+
+    let mut decompressed = vec![];
+    reader
+        .take(max_size as u64 + 1)
+        .read_to_end(&mut decompressed)
+        .map_err(|e| PostError::Invalid(anyhow!("Failed to decompress {encoding} payload, {e}")))?;
+    if decompressed.len() > max_size {
+        return Err(PostError::PayloadTooLarge(max_size));
+    }
+
+    Ok(decompressed)
+}
+
+/// Decompresses a brotli payload with the decoder actix applies to the bodies it decodes
+/// itself, failing as soon as it grows larger than `max_size`
+async fn decompress_brotli(body: Bytes, max_size: usize) -> Result<Vec<u8>, PostError> {
+    let mut decoder = Decompress::new(Payload::from(body), ContentEncoding::Brotli);
+    let mut decompressed = vec![];
+    while let Some(chunk) = decoder.next().await {
+        let chunk = chunk
+            .map_err(|e| PostError::Invalid(anyhow!("Failed to decompress br payload, {e}")))?;
+        if decompressed.len() + chunk.len() > max_size {
+            return Err(PostError::PayloadTooLarge(max_size));
+        }
+        decompressed.extend_from_slice(&chunk);
+    }
+
+    Ok(decompressed)
+}
+
+/// Decompresses a block format snappy payload, as sent by Prometheus and Loki clients,
+/// failing early when the decompressed payload would be larger than `max_size`
+pub fn decompress_snappy(body: &[u8], max_size: usize) -> Result<Vec<u8>, PostError> {
+    if snap::raw::decompress_len(body)? > max_size {
+        return Err(PostError::PayloadTooLarge(max_size));
+    }
+
+    Ok(snap::raw::Decoder::new().decompress_vec(body)?)
+}
+
+/// Checks that the user can ingest into the stream, for requests that name the
+/// streams in their body and as such can't be authorized by the route
+pub fn authorize_stream_ingestion(req: &HttpRequest, stream_name: &str) -> Result<(), PostError> {
+    let key = extract_session_key_from_req(req)
+        .map_err(|_| PostError::Forbidden(stream_name.to_owned()))?;
+    match Users.authorize(key, Action::Ingest, Some(stream_name), None) {
+        rbac::Response::Authorized => Ok(()),
+        _ => Err(PostError::Forbidden(stream_name.to_owned())),
+    }
+}
+
+/// Creates the stream if it doesn't exist yet, and checks that it can be ingested into
+/// with the given log source, which is then recorded as one of the sources of the stream
+pub async fn setup_stream_for_ingestion(
+    stream_name: &str,
+    log_source: LogSource,
+    telemetry_type: TelemetryType,
+) -> Result<(), PostError> {
+    if PARSEABLE
+        .streams
+        .list_internal_streams()
+        .contains(&stream_name.to_owned())
+    {
+        return Err(PostError::InternalStream(stream_name.to_owned()));
+    }
+
+    let log_source_entry = LogSourceEntry::new(log_source, HashSet::new());
+    PARSEABLE
+        .create_stream_if_not_exists(
+            stream_name,
+            StreamType::UserDefined,
+            None,
+            vec![log_source_entry.clone()],
+            telemetry_type,
+        )
+        .await?;
+
+    validate_stream_for_ingestion(stream_name)?;
+
+    PARSEABLE
+        .add_update_log_source(stream_name, log_source_entry)
+        .await?;
+
+    Ok(())
+}
+
 pub fn validate_stream_for_ingestion(stream_name: &str) -> Result<(), PostError> {
     let stream = PARSEABLE.get_stream(stream_name)?;
 
@@ -286,6 +590,7 @@ pub fn validate_stream_for_ingestion(stream_name: &str) -> Result<(), PostError>
         .find(|&stream_log_source_entry| {
             stream_log_source_entry.log_source_format != LogSource::OtelTraces
                 && stream_log_source_entry.log_source_format != LogSource::OtelMetrics
+                && stream_log_source_entry.log_source_format != LogSource::Prometheus
         })
         .ok_or(PostError::IncorrectLogFormat(stream_name.to_string()))?;
 
@@ -303,6 +608,10 @@ pub fn validate_stream_for_ingestion(stream_name: &str) -> Result<(), PostError>
 mod tests {
     use super::*;
     use actix_web::test::TestRequest;
+    use opentelemetry_proto::tonic::{
+        common::v1::{AnyValue, any_value},
+        logs::v1::{LogRecord, ResourceLogs, ScopeLogs},
+    };
 
     #[test]
     fn test_get_custom_fields_from_header_with_custom_fields() {
@@ -322,12 +631,14 @@ mod tests {
         let req = TestRequest::default()
             .insert_header((USER_AGENT, "TestUserAgent"))
             .insert_header((STREAM_NAME_HEADER_KEY, "teststream"))
+            .insert_header((CSV_DELIMITER_KEY, ";"))
             .to_http_request();
 
         let custom_fields = get_custom_fields_from_header(&req);
 
         assert_eq!(custom_fields.get(USER_AGENT_KEY).unwrap(), "TestUserAgent");
         assert!(!custom_fields.contains_key(STREAM_NAME_HEADER_KEY));
+        assert!(!custom_fields.contains_key("csv-delimiter"));
     }
 
     #[test]
@@ -355,4 +666,81 @@ mod tests {
         assert_eq!(custom_fields.get(USER_AGENT_KEY).unwrap(), "");
         assert_eq!(custom_fields.get(SOURCE_IP_KEY).unwrap(), "");
     }
+
+    #[test]
+    fn test_decode_otel_protobuf_logs() {
+        let request = ExportLogsServiceRequest {
+            resource_logs: vec![ResourceLogs {
+                scope_logs: vec![ScopeLogs {
+                    log_records: vec![LogRecord {
+                        time_unix_nano: 1_700_000_000_000_000_000,
+                        severity_number: 9,
+                        body: Some(AnyValue {
+                            value: Some(any_value::Value::StringValue("hello".to_string())),
+                        }),
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }],
+        };
+
+        let records = decode_otel_protobuf(&request.encode_to_vec(), &LogSource::OtelLogs).unwrap();
+
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0]["body"], "hello");
+        assert_eq!(records[0]["severity_text"], "INFO");
+    }
+
+    #[test]
+    fn test_decode_otel_protobuf_rejects_invalid_body() {
+        assert!(matches!(
+            decode_otel_protobuf(b"\xff\xff\xff", &LogSource::OtelTraces),
+            Err(PostError::ProtobufDecode(_))
+        ));
+    }
+
+    #[test]
+    fn test_decode_otel_protobuf_rejects_non_otel_source() {
+        assert!(matches!(
+            decode_otel_protobuf(&[], &LogSource::Json),
+            Err(PostError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn decompress_within_payload_limit() {
+        use std::io::Write;
+
+        let body = br#"[{"level":"info","message":"started"}]"#.repeat(64);
+
+        let mut gzip = flate2::write::GzEncoder::new(vec![], flate2::Compression::default());
+        gzip.write_all(&body).unwrap();
+        let gzip = gzip.finish().unwrap();
+        let mut deflate = flate2::write::ZlibEncoder::new(vec![], flate2::Compression::default());
+        deflate.write_all(&body).unwrap();
+        let deflate = deflate.finish().unwrap();
+        let zstd = zstd::encode_all(body.as_slice(), 0).unwrap();
+        let mut framed = snap::write::FrameEncoder::new(vec![]);
+        framed.write_all(&body).unwrap();
+        let framed = framed.into_inner().unwrap();
+        let block = snap::raw::Encoder::new().compress_vec(&body).unwrap();
+
+        for (encoding, compressed) in [
+            ("gzip", &gzip),
+            ("deflate", &deflate),
+            ("zstd", &zstd),
+            ("snappy", &framed),
+            ("snappy", &block),
+        ] {
+            assert_eq!(decompress(encoding, compressed, body.len()).unwrap(), body);
+            // a body that only outgrows the limit once decompressed is rejected
+            assert!(matches!(
+                decompress(encoding, compressed, body.len() - 1),
+                Err(PostError::PayloadTooLarge(_))
+            ));
+        }
+        assert!(decompress("compress", &body, usize::MAX).is_err());
+    }
 }
@@ -0,0 +1,359 @@
+/*
+ * Parseable Server (C) 2022 - 2025 Parseable, Inc.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ */
+
+//! Prometheus remote_write receiver, samples are stored in metrics streams with
+//! the same column conventions as that of OTEL metrics, see `otel/metrics.rs`
+
+use std::collections::HashMap;
+
+use actix_web::web::Bytes;
+use actix_web::{HttpRequest, HttpResponse};
+use prost::Message;
+use serde_json::{Map, Number, Value};
+
+use crate::event::FORMAT_KEY;
+use crate::event::format::LogSource;
+use crate::handlers::http::ingest::{PostError, create_otel_stream_if_not_exists};
+use crate::handlers::http::modal::utils::ingest_utils::{
+    decompress_snappy, flatten_and_push_logs, get_custom_fields_from_header,
+};
+use crate::handlers::{STREAM_NAME_HEADER_KEY, TelemetryType};
+use crate::metrics::increment_metrics_collected_by_date;
+use crate::otel::otel_utils::convert_epoch_nano_to_timestamp;
+use crate::parseable::PARSEABLE;
+use crate::utils::header_parsing::ParseHeaderError;
+
+pub const PROMETHEUS_KNOWN_FIELD_LIST: [&str; 6] = [
+    "metric_name",
+    "metric_type",
+    "metric_description",
+    "metric_unit",
+    "data_point_value",
+    "time_unix_nano",
+];
+
+/// Bit pattern of the NaN Prometheus uses to mark a series as stale
+const STALE_NAN: u64 = 0x7ff0000000000002;
+
+/// Messages of the remote_write 1.0 protocol, as defined in `prompb/remote.proto`
+/// and `prompb/types.proto`. Native histograms and exemplars are not decoded.
+mod proto {
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct WriteRequest {
+        #[prost(message, repeated, tag = "1")]
+        pub timeseries: Vec<TimeSeries>,
+        #[prost(message, repeated, tag = "3")]
+        pub metadata: Vec<MetricMetadata>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct TimeSeries {
+        /// Labels of the series, the metric name being that of `__name__`
+        #[prost(message, repeated, tag = "1")]
+        pub labels: Vec<Label>,
+        #[prost(message, repeated, tag = "2")]
+        pub samples: Vec<Sample>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Label {
+        #[prost(string, tag = "1")]
+        pub name: String,
+        #[prost(string, tag = "2")]
+        pub value: String,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Sample {
+        #[prost(double, tag = "1")]
+        pub value: f64,
+        /// Milliseconds since epoch
+        #[prost(int64, tag = "2")]
+        pub timestamp: i64,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct MetricMetadata {
+        #[prost(enumeration = "MetricType", tag = "1")]
+        pub r#type: i32,
+        #[prost(string, tag = "2")]
+        pub metric_family_name: String,
+        #[prost(string, tag = "4")]
+        pub help: String,
+        #[prost(string, tag = "5")]
+        pub unit: String,
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+    #[repr(i32)]
+    pub enum MetricType {
+        Unknown = 0,
+        Counter = 1,
+        Gauge = 2,
+        Histogram = 3,
+        Gaugehistogram = 4,
+        Summary = 5,
+        Info = 6,
+        Stateset = 7,
+    }
+}
+
+// Handler for POST /api/v1/prom/write
+// ingests the samples of a remote_write request into the metrics stream named by the header
+pub async fn remote_write(req: HttpRequest, body: Bytes) -> Result<HttpResponse, PostError> {
+    let Some(stream_name) = req
+        .headers()
+        .get(STREAM_NAME_HEADER_KEY)
+        .and_then(|h| h.to_str().ok())
+        .map(str::to_owned)
+    else {
+        return Err(PostError::Header(ParseHeaderError::MissingStreamName));
+    };
+    if PARSEABLE
+        .streams
+        .list_internal_streams()
+        .contains(&stream_name)
+    {
+        return Err(PostError::InternalStream(stream_name));
+    }
+
+    let body = decompress_snappy(&body, PARSEABLE.options.max_event_payload_size)?;
+    let request = proto::WriteRequest::decode(body.as_slice())?;
+    let rows = flatten_write_request(&request);
+
+    create_otel_stream_if_not_exists(
+        stream_name.clone(),
+        LogSource::Prometheus,
+        &PROMETHEUS_KNOWN_FIELD_LIST,
+        TelemetryType::Metrics,
+    )
+    .await?;
+
+    if !rows.is_empty() {
+        let mut p_custom_fields = get_custom_fields_from_header(&req);
+        p_custom_fields.insert(FORMAT_KEY.to_string(), LogSource::Prometheus.to_string());
+        flatten_and_push_logs(
+            Value::Array(rows),
+            &stream_name,
+            &LogSource::Prometheus,
+            &p_custom_fields,
+            None,
+            TelemetryType::Metrics,
+        )
+        .await?;
+    }
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// One row per sample, with the labels of the series as columns along with the
+/// metric name, value and time of the sample, as well as the type, description
+/// and unit of the metric if its metadata is part of the request
+fn flatten_write_request(request: &proto::WriteRequest) -> Vec<Value> {
+    let metadata: HashMap<&str, &proto::MetricMetadata> = request
+        .metadata
+        .iter()
+        .map(|metadata| (metadata.metric_family_name.as_str(), metadata))
+        .collect();
+
+    let mut rows = vec![];
+    for series in &request.timeseries {
+        let mut series_json = Map::new();
+        for label in &series.labels {
+            let name = match label.name.as_str() {
+                "__name__" => "metric_name",
+                name => name,
+            };
+            series_json.insert(name.to_owned(), Value::String(label.value.clone()));
+        }
+
+        let family = series_json
+            .get("metric_name")
+            .and_then(Value::as_str)
+            .and_then(|name| metric_family(&metadata, name));
+        if let Some(family) = family {
+            insert_metadata(&mut series_json, family);
+        }
+
+        for sample in &series.samples {
+            if sample.value.to_bits() == STALE_NAN {
+                continue;
+            }
+            let mut sample_json = series_json.clone();
+            sample_json.insert(
+                "data_point_value".to_string(),
+                Number::from_f64(sample.value)
+                    .map(Value::Number)
+                    .unwrap_or(Value::Null),
+            );
+            sample_json.insert(
+                "time_unix_nano".to_string(),
+                Value::String(convert_epoch_nano_to_timestamp(
+                    sample.timestamp.saturating_mul(1_000_000),
+                )),
+            );
+            rows.push(Value::Object(sample_json));
+        }
+    }
+
+    let date = chrono::Utc::now().date_naive().to_string();
+    increment_metrics_collected_by_date(rows.len() as u64, &date);
+
+    rows
+}
+
+// Metadata is sent per metric family, such that the series of histograms and
+// summaries, e.g. `http_duration_seconds_bucket`, are found by their family name
+fn metric_family<'a>(
+    metadata: &HashMap<&str, &'a proto::MetricMetadata>,
+    metric_name: &str,
+) -> Option<&'a proto::MetricMetadata> {
+    metadata.get(metric_name).copied().or_else(|| {
+        ["_bucket", "_sum", "_count", "_total"]
+            .iter()
+            .find_map(|suffix| metric_name.strip_suffix(suffix))
+            .and_then(|family| metadata.get(family).copied())
+    })
+}
+
+fn insert_metadata(series_json: &mut Map<String, Value>, metadata: &proto::MetricMetadata) {
+    use proto::MetricType;
+
+    // named after the otel data types the prometheus types map to
+    let metric_type = match MetricType::try_from(metadata.r#type) {
+        Ok(MetricType::Counter) => Some("sum"),
+        Ok(MetricType::Gauge) => Some("gauge"),
+        Ok(MetricType::Histogram | MetricType::Gaugehistogram) => Some("histogram"),
+        Ok(MetricType::Summary) => Some("summary"),
+        _ => None,
+    };
+    if let Some(metric_type) = metric_type {
+        series_json.insert(
+            "metric_type".to_string(),
+            Value::String(metric_type.to_owned()),
+        );
+    }
+    if !metadata.help.is_empty() {
+        series_json.insert(
+            "metric_description".to_string(),
+            Value::String(metadata.help.clone()),
+        );
+    }
+    if !metadata.unit.is_empty() {
+        series_json.insert(
+            "metric_unit".to_string(),
+            Value::String(metadata.unit.clone()),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn series(labels: &[(&str, &str)], samples: &[(f64, i64)]) -> proto::TimeSeries {
+        proto::TimeSeries {
+            labels: labels
+                .iter()
+                .map(|(name, value)| proto::Label {
+                    name: name.to_string(),
+                    value: value.to_string(),
+                })
+                .collect(),
+            samples: samples
+                .iter()
+                .map(|&(value, timestamp)| proto::Sample { value, timestamp })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn flatten_samples_as_rows() {
+        let request = proto::WriteRequest {
+            timeseries: vec![
+                series(
+                    &[("__name__", "http_requests_total"), ("code", "200")],
+                    &[
+                        (10.0, 1_700_000_000_000),
+                        (f64::from_bits(STALE_NAN), 1_700_000_015_000),
+                    ],
+                ),
+                series(
+                    &[("__name__", "http_duration_seconds_bucket"), ("le", "0.5")],
+                    &[(3.0, 1_700_000_000_500)],
+                ),
+            ],
+            metadata: vec![
+                proto::MetricMetadata {
+                    r#type: proto::MetricType::Counter as i32,
+                    metric_family_name: "http_requests".to_owned(),
+                    help: "Requests served".to_owned(),
+                    unit: String::new(),
+                },
+                proto::MetricMetadata {
+                    r#type: proto::MetricType::Histogram as i32,
+                    metric_family_name: "http_duration_seconds".to_owned(),
+                    help: String::new(),
+                    unit: "seconds".to_owned(),
+                },
+            ],
+        };
+
+        assert_eq!(
+            flatten_write_request(&request),
+            vec![
+                json!({
+                    "metric_name": "http_requests_total",
+                    "code": "200",
+                    "metric_type": "sum",
+                    "metric_description": "Requests served",
+                    "data_point_value": 10.0,
+                    "time_unix_nano": "2023-11-14T22:13:20.000000000Z",
+                }),
+                json!({
+                    "metric_name": "http_duration_seconds_bucket",
+                    "le": "0.5",
+                    "metric_type": "histogram",
+                    "metric_unit": "seconds",
+                    "data_point_value": 3.0,
+                    "time_unix_nano": "2023-11-14T22:13:20.500000000Z",
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn decode_snappy_protobuf() {
+        let request = proto::WriteRequest {
+            timeseries: vec![series(&[("__name__", "up")], &[(1.0, 0)])],
+            metadata: vec![],
+        };
+        let body = snap::raw::Encoder::new()
+            .compress_vec(&request.encode_to_vec())
+            .unwrap();
+
+        let decoded =
+            proto::WriteRequest::decode(decompress_snappy(&body, usize::MAX).unwrap().as_slice())
+                .unwrap();
+
+        assert_eq!(decoded, request);
+        assert!(decompress_snappy(&body, 4).is_err());
+    }
+}
@@ -34,7 +34,7 @@ use tracing::{info, trace, warn};
 use crate::analytics::{SYS_INFO, refresh_sys_info};
 use crate::parseable::PARSEABLE;
 
-static RESOURCE_CHECK_ENABLED: LazyLock<Arc<AtomicBool>> =
+pub static RESOURCE_CHECK_ENABLED: LazyLock<Arc<AtomicBool>> =
     LazyLock::new(|| Arc::new(AtomicBool::new(false)));
 
 /// Spawn a background task to monitor system resources
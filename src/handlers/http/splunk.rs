@@ -0,0 +1,503 @@
+/*
+ * Parseable Server (C) 2022 - 2025 Parseable, Inc.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ */
+
+//! Splunk HTTP Event Collector (HEC) compatibility, such that appliances and
+//! shippers that can only forward to Splunk are able to ingest as is.
+//!
+//! Unlike Splunk, there are no HEC tokens to issue: clients authenticate as a Parseable
+//! user, with `Authorization: Splunk <username>:<password>`, the credentials given either
+//! as is or base64 encoded like those of basic auth. Opaque tokens, such as the GUIDs
+//! generated by Splunk, are rejected.
+
+use std::collections::{BTreeSet, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{LazyLock, Mutex};
+
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::{StatusCode, header};
+use actix_web::middleware::Next;
+use actix_web::web::{Bytes, Json, Query};
+use actix_web::{Error, HttpRequest, HttpResponse, ResponseError};
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use chrono::{DateTime, SecondsFormat};
+use serde::Deserialize;
+use serde_json::{Map, Value, json};
+
+use crate::event::FORMAT_KEY;
+use crate::event::format::LogSource;
+use crate::handlers::STREAM_NAME_HEADER_KEY;
+use crate::handlers::TelemetryType;
+use crate::handlers::http::ingest::PostError;
+use crate::handlers::http::modal::utils::ingest_utils::{
+    authorize_stream_ingestion, flatten_and_push_logs, get_custom_fields_from_header,
+    setup_stream_for_ingestion,
+};
+
+/// Header naming the channel of the client, acks are only returned to clients that send it
+const CHANNEL_HEADER_KEY: &str = "x-splunk-request-channel";
+
+/// Acks still pending per channel, past which the oldest acks of the channel are forgotten
+const MAX_PENDING_ACKS: usize = 10_000;
+
+static NEXT_ACK_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Acks handed out per channel and not queried yet. Acks are handed out once the events
+/// are in staging, so any ack issued to the channel is acknowledged when queried.
+static PENDING_ACKS: LazyLock<Mutex<HashMap<String, BTreeSet<u64>>>> =
+    LazyLock::new(Default::default);
+
+#[derive(Debug, thiserror::Error)]
+pub enum HecError {
+    #[error("No data")]
+    NoData,
+    #[error("Invalid data format")]
+    InvalidDataFormat(usize),
+    #[error("Event field is required")]
+    EventRequired(usize),
+    #[error("Event field cannot be blank")]
+    EventBlank(usize),
+    #[error("Incorrect index")]
+    IncorrectIndex(usize),
+    #[error("Data channel is missing")]
+    ChannelMissing,
+    #[error(
+        "Invalid authorization, the HEC token must be the credentials of a user as username:password, either as is or base64 encoded"
+    )]
+    InvalidToken,
+    #[error("{0}")]
+    Post(#[from] PostError),
+}
+
+impl HecError {
+    // status codes as documented for the HEC REST API
+    fn code(&self) -> u8 {
+        match self {
+            HecError::InvalidToken => 3,
+            HecError::NoData => 5,
+            HecError::InvalidDataFormat(_) => 6,
+            HecError::IncorrectIndex(_) => 7,
+            HecError::ChannelMissing => 10,
+            HecError::EventRequired(_) => 12,
+            HecError::EventBlank(_) => 13,
+            HecError::Post(PostError::Forbidden(_)) => 4,
+            HecError::Post(err) if err.status_code().is_client_error() => 6,
+            HecError::Post(_) => 8,
+        }
+    }
+}
+
+impl ResponseError for HecError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            HecError::Post(err) => err.status_code(),
+            HecError::InvalidToken => StatusCode::UNAUTHORIZED,
+            _ => StatusCode::BAD_REQUEST,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let mut body = json!({ "text": self.to_string(), "code": self.code() });
+        match self {
+            HecError::InvalidDataFormat(i)
+            | HecError::EventRequired(i)
+            | HecError::EventBlank(i)
+            | HecError::IncorrectIndex(i) => body["invalid-event-number"] = json!(i),
+            HecError::NoData
+            | HecError::ChannelMissing
+            | HecError::InvalidToken
+            | HecError::Post(_) => {}
+        }
+        HttpResponse::build(self.status_code()).json(body)
+    }
+}
+
+/// Metadata of the events, either set per event or for the whole request as query parameters
+#[derive(Debug, Default, Clone, Deserialize)]
+pub struct HecMetadata {
+    index: Option<String>,
+    host: Option<String>,
+    source: Option<String>,
+    sourcetype: Option<String>,
+    time: Option<Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct HecEvent {
+    #[serde(flatten)]
+    metadata: HecMetadata,
+    event: Option<Value>,
+    /// Indexed fields, added as columns as is
+    #[serde(default)]
+    fields: Map<String, Value>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AckRequest {
+    acks: Vec<u64>,
+}
+
+impl HecMetadata {
+    // event metadata takes precedence over that of the request
+    fn or(self, defaults: &HecMetadata) -> Self {
+        Self {
+            index: self.index.or_else(|| defaults.index.clone()),
+            host: self.host.or_else(|| defaults.host.clone()),
+            source: self.source.or_else(|| defaults.source.clone()),
+            sourcetype: self.sourcetype.or_else(|| defaults.sourcetype.clone()),
+            time: self.time.or_else(|| defaults.time.clone()),
+        }
+    }
+
+    /// Adds `host`, `source`, `sourcetype` and `time` as columns of the row, HEC times
+    /// are epoch seconds with an optional fraction, given as either number or string
+    fn add_columns(&self, row: &mut Map<String, Value>) -> Option<()> {
+        for (name, value) in [
+            ("host", &self.host),
+            ("source", &self.source),
+            ("sourcetype", &self.sourcetype),
+        ] {
+            if let Some(value) = value {
+                row.insert(name.to_owned(), Value::String(value.clone()));
+            }
+        }
+
+        let Some(time) = &self.time else {
+            return Some(());
+        };
+        let seconds = match time {
+            Value::Number(n) => n.as_f64()?,
+            Value::String(s) => s.parse::<f64>().ok()?,
+            _ => return None,
+        };
+        let time = DateTime::from_timestamp_millis((seconds * 1000.0).round() as i64)?;
+        row.insert(
+            "time".to_owned(),
+            Value::String(time.to_rfc3339_opts(SecondsFormat::AutoSi, true)),
+        );
+
+        Some(())
+    }
+}
+
+// Handler for POST /services/collector/event
+// ingests the concatenated json events of the body, each into the stream named by its `index`
+pub async fn event(
+    req: HttpRequest,
+    defaults: Query<HecMetadata>,
+    body: Bytes,
+) -> Result<HttpResponse, HecError> {
+    let defaults = with_default_index(&req, defaults.into_inner());
+    let streams = parse_events(&body, &defaults)?;
+
+    push_streams(&req, streams).await
+}
+
+/// Parses the concatenated json events of the body into rows, grouped by the stream they are meant for
+fn parse_events(
+    body: &[u8],
+    defaults: &HecMetadata,
+) -> Result<HashMap<String, Vec<Value>>, HecError> {
+    let events = serde_json::Deserializer::from_slice(body).into_iter::<HecEvent>();
+
+    let mut streams: HashMap<String, Vec<Value>> = HashMap::new();
+    for (i, event) in events.enumerate() {
+        let event = event.map_err(|_| HecError::InvalidDataFormat(i))?;
+        let mut row = match event.event {
+            None | Some(Value::Null) => return Err(HecError::EventRequired(i)),
+            Some(Value::String(s)) if s.is_empty() => return Err(HecError::EventBlank(i)),
+            Some(Value::Object(event)) => event,
+            Some(event) => Map::from_iter([("event".to_owned(), event)]),
+        };
+        row.extend(event.fields);
+
+        let metadata = event.metadata.or(defaults);
+        metadata
+            .add_columns(&mut row)
+            .ok_or(HecError::InvalidDataFormat(i))?;
+        let index = metadata.index.ok_or(HecError::IncorrectIndex(i))?;
+        streams.entry(index).or_default().push(Value::Object(row));
+    }
+    if streams.is_empty() {
+        return Err(HecError::NoData);
+    }
+
+    Ok(streams)
+}
+
+// Handler for POST /services/collector/raw
+// ingests each line of the body as an event, with the metadata given as query parameters
+pub async fn raw(
+    req: HttpRequest,
+    defaults: Query<HecMetadata>,
+    body: Bytes,
+) -> Result<HttpResponse, HecError> {
+    let metadata = with_default_index(&req, defaults.into_inner());
+
+    let body = String::from_utf8_lossy(&body);
+    let mut rows = vec![];
+    for line in body.lines().filter(|line| !line.trim().is_empty()) {
+        let mut row = Map::from_iter([("event".to_owned(), Value::String(line.to_owned()))]);
+        metadata
+            .add_columns(&mut row)
+            .ok_or(HecError::InvalidDataFormat(0))?;
+        rows.push(Value::Object(row));
+    }
+    if rows.is_empty() {
+        return Err(HecError::NoData);
+    }
+    let index = metadata.index.ok_or(HecError::IncorrectIndex(0))?;
+
+    push_streams(&req, HashMap::from([(index, rows)])).await
+}
+
+// Handler for POST /services/collector/ack
+// acks issued to the channel of the client are acknowledged, once only, any other is not
+pub async fn ack(req: HttpRequest, acks: Json<AckRequest>) -> Result<HttpResponse, HecError> {
+    let channel = req
+        .headers()
+        .get(CHANNEL_HEADER_KEY)
+        .and_then(|h| h.to_str().ok())
+        .ok_or(HecError::ChannelMissing)?;
+
+    let acks: Map<String, Value> = query_acks(channel, &acks.acks)
+        .into_iter()
+        .map(|(id, acked)| (id.to_string(), Value::Bool(acked)))
+        .collect();
+
+    Ok(HttpResponse::Ok().json(json!({ "acks": acks })))
+}
+
+// Handler for GET /services/collector/health
+pub async fn health() -> HttpResponse {
+    HttpResponse::Ok().json(json!({ "text": "HEC is healthy", "code": 17 }))
+}
+
+// the `x-p-stream` header is used as index of events that don't name one
+fn with_default_index(req: &HttpRequest, defaults: HecMetadata) -> HecMetadata {
+    let index = req
+        .headers()
+        .get(STREAM_NAME_HEADER_KEY)
+        .and_then(|h| h.to_str().ok())
+        .map(str::to_owned);
+
+    HecMetadata {
+        index: defaults.index.or(index),
+        ..defaults
+    }
+}
+
+async fn push_streams(
+    req: &HttpRequest,
+    streams: HashMap<String, Vec<Value>>,
+) -> Result<HttpResponse, HecError> {
+    for stream_name in streams.keys() {
+        authorize_stream_ingestion(req, stream_name)?;
+    }
+
+    let mut p_custom_fields = get_custom_fields_from_header(req);
+    p_custom_fields.insert(FORMAT_KEY.to_string(), LogSource::Splunk.to_string());
+    for (stream_name, rows) in streams {
+        setup_stream_for_ingestion(&stream_name, LogSource::Splunk, TelemetryType::Logs).await?;
+        flatten_and_push_logs(
+            Value::Array(rows),
+            &stream_name,
+            &LogSource::Splunk,
+            &p_custom_fields,
+            None,
+            TelemetryType::Logs,
+        )
+        .await?;
+    }
+
+    let mut response = json!({ "text": "Success", "code": 0 });
+    if let Some(channel) = req
+        .headers()
+        .get(CHANNEL_HEADER_KEY)
+        .and_then(|h| h.to_str().ok())
+    {
+        response["ackId"] = json!(issue_ack(channel));
+    }
+
+    Ok(HttpResponse::Ok().json(response))
+}
+
+fn issue_ack(channel: &str) -> u64 {
+    let id = NEXT_ACK_ID.fetch_add(1, Ordering::Relaxed);
+    let mut pending = PENDING_ACKS.lock().unwrap();
+    let acks = pending.entry(channel.to_owned()).or_default();
+    acks.insert(id);
+    if acks.len() > MAX_PENDING_ACKS {
+        acks.pop_first();
+    }
+
+    id
+}
+
+// an ack is reported only the first time it is queried, as done by HEC
+fn query_acks(channel: &str, ids: &[u64]) -> Vec<(u64, bool)> {
+    let mut pending = PENDING_ACKS.lock().unwrap();
+    let Some(acks) = pending.get_mut(channel) else {
+        return ids.iter().map(|&id| (id, false)).collect();
+    };
+    let acked = ids.iter().map(|&id| (id, acks.remove(&id))).collect();
+    if acks.is_empty() {
+        pending.remove(channel);
+    }
+
+    acked
+}
+
+/// Middleware of the HEC routes, rewriting the `Authorization: Splunk <token>` header that
+/// HEC clients authenticate with as basic auth, before the request is authorized. Tokens
+/// that aren't the credentials of a user are rejected with [`HecError::InvalidToken`].
+pub async fn splunk_auth_middleware(
+    mut req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let basic_auth = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|h| h.to_str().ok())
+        .and_then(splunk_to_basic_auth)
+        .transpose()?;
+    if let Some(basic_auth) = basic_auth
+        && let Ok(value) = header::HeaderValue::from_str(&basic_auth)
+    {
+        req.headers_mut().insert(header::AUTHORIZATION, value);
+    }
+
+    next.call(req).await
+}
+
+/// Converts the credentials of an `Authorization: Splunk <token>` header into those of basic
+/// auth, the token being `username:password`, either as is or base64 encoded like basic auth.
+/// Returns `None` for headers of other schemes.
+fn splunk_to_basic_auth(authorization: &str) -> Option<Result<String, HecError>> {
+    let (scheme, token) = authorization.trim().split_once(' ')?;
+    if !scheme.eq_ignore_ascii_case("splunk") {
+        return None;
+    }
+
+    let token = token.trim();
+    let is_encoded = STANDARD
+        .decode(token)
+        .ok()
+        .and_then(|decoded| String::from_utf8(decoded).ok())
+        .is_some_and(|decoded| decoded.contains(':'));
+    let credentials = if is_encoded {
+        token.to_owned()
+    } else if token.contains(':') {
+        STANDARD.encode(token)
+    } else {
+        return Some(Err(HecError::InvalidToken));
+    };
+
+    Some(Ok(format!("Basic {credentials}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splunk_token_as_basic_auth() {
+        assert_eq!(
+            splunk_to_basic_auth("Splunk admin:admin").unwrap().unwrap(),
+            "Basic YWRtaW46YWRtaW4="
+        );
+        assert_eq!(
+            splunk_to_basic_auth("Splunk YWRtaW46YWRtaW4=")
+                .unwrap()
+                .unwrap(),
+            "Basic YWRtaW46YWRtaW4="
+        );
+        assert!(splunk_to_basic_auth("Basic YWRtaW46YWRtaW4=").is_none());
+        // tokens generated by Splunk aren't credentials
+        assert!(matches!(
+            splunk_to_basic_auth("Splunk 8a5c3c0e-2f1b-4e8a-9d6f-0c1e2b3a4d5f"),
+            Some(Err(HecError::InvalidToken))
+        ));
+    }
+
+    #[test]
+    fn event_metadata_as_columns() {
+        let body = br#"{"time": 1700000000.25, "host": "fw-1", "sourcetype": "syslog", "event": "denied"}
+            {"index": "audit", "time": "1700000001", "event": {"user": "bob"}, "fields": {"dc": "eu"}}"#;
+        let defaults = HecMetadata {
+            index: Some("network".to_owned()),
+            ..Default::default()
+        };
+
+        let streams = parse_events(body, &defaults).unwrap();
+
+        assert_eq!(
+            streams["network"],
+            vec![json!({
+                "event": "denied",
+                "host": "fw-1",
+                "sourcetype": "syslog",
+                "time": "2023-11-14T22:13:20.250Z",
+            })]
+        );
+        assert_eq!(
+            streams["audit"],
+            vec![json!({"user": "bob", "dc": "eu", "time": "2023-11-14T22:13:21Z"})]
+        );
+    }
+
+    #[test]
+    fn events_without_index_or_event_are_rejected() {
+        let defaults = HecMetadata::default();
+
+        assert!(matches!(
+            parse_events(br#"{"event": "a"}"#, &defaults),
+            Err(HecError::IncorrectIndex(0))
+        ));
+        assert!(matches!(
+            parse_events(br#"{"index": "a", "event": "a"} {"index": "a"}"#, &defaults),
+            Err(HecError::EventRequired(1))
+        ));
+        assert!(matches!(
+            parse_events(b"  ", &defaults),
+            Err(HecError::NoData)
+        ));
+    }
+
+    #[test]
+    fn acks_are_reported_once_to_their_channel() {
+        let id = issue_ack("channel-a");
+
+        assert_eq!(query_acks("channel-b", &[id]), vec![(id, false)]);
+        assert_eq!(
+            query_acks("channel-a", &[id, id + 1000]),
+            vec![(id, true), (id + 1000, false)]
+        );
+        assert_eq!(query_acks("channel-a", &[id]), vec![(id, false)]);
+    }
+
+    #[test]
+    fn invalid_time_is_rejected() {
+        let metadata = HecMetadata {
+            time: Some(json!("yesterday")),
+            ..Default::default()
+        };
+
+        assert!(metadata.add_columns(&mut Map::new()).is_none());
+    }
+}
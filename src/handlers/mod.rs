@@ -23,6 +23,7 @@ use serde::{Deserialize, Serialize};
 pub mod airplane;
 pub mod http;
 pub mod livetail;
+pub mod otel_grpc;
 
 pub const STREAM_NAME_HEADER_KEY: &str = "x-p-stream";
 pub const LOG_SOURCE_KEY: &str = "x-p-log-source";
@@ -35,6 +36,10 @@ pub const AUTHORIZATION_KEY: &str = "authorization";
 pub const UPDATE_STREAM_KEY: &str = "x-p-update-stream";
 pub const STREAM_TYPE_KEY: &str = "x-p-stream-type";
 pub const TELEMETRY_TYPE_KEY: &str = "x-p-telemetry-type";
+pub const CSV_DELIMITER_KEY: &str = "x-p-csv-delimiter";
+pub const CSV_QUOTE_KEY: &str = "x-p-csv-quote";
+pub const CSV_HAS_HEADER_KEY: &str = "x-p-csv-has-header";
+pub const LOKI_STREAM_LABEL_KEY: &str = "x-p-loki-stream-label";
 const COOKIE_AGE_DAYS: usize = 7;
 const SESSION_COOKIE_NAME: &str = "session";
 const USER_COOKIE_NAME: &str = "username";
@@ -48,6 +53,9 @@ pub const KINESIS_COMMON_ATTRIBUTES_KEY: &str = "x-amz-firehose-common-attribute
 // constants for content type values
 pub const CONTENT_TYPE_JSON: &str = "application/json";
 pub const CONTENT_TYPE_PROTOBUF: &str = "application/x-protobuf";
+pub const CONTENT_TYPE_CSV: &str = "text/csv";
+pub const CONTENT_TYPE_TSV: &str = "text/tab-separated-values";
+pub const CONTENT_TYPE_NDJSON: &str = "application/x-ndjson";
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
@@ -0,0 +1,310 @@
+/*
+ * Parseable Server (C) 2022 - 2025 Parseable, Inc.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ */
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+
+use futures_util::{Future, TryFutureExt};
+use opentelemetry_proto::tonic::collector::{
+    logs::v1::{
+        ExportLogsServiceRequest, ExportLogsServiceResponse,
+        logs_service_server::{LogsService, LogsServiceServer},
+    },
+    metrics::v1::{
+        ExportMetricsServiceRequest, ExportMetricsServiceResponse,
+        metrics_service_server::{MetricsService, MetricsServiceServer},
+    },
+    trace::v1::{
+        ExportTraceServiceRequest, ExportTraceServiceResponse,
+        trace_service_server::{TraceService, TraceServiceServer},
+    },
+};
+use serde_json::Value;
+use tonic::codec::CompressionEncoding;
+use tonic::metadata::{KeyAndValueRef, MetadataMap};
+use tonic::transport::{Identity, Server, ServerTlsConfig};
+use tonic::{Request, Response, Status};
+use tracing::error;
+
+use crate::event::FORMAT_KEY;
+use crate::event::format::LogSource;
+use crate::handlers::http::ingest::{PostError, create_otel_stream_if_not_exists};
+use crate::handlers::http::modal::utils::ingest_utils::{collect_custom_fields, push_otel_records};
+use crate::handlers::http::resource_check::RESOURCE_CHECK_ENABLED;
+use crate::handlers::livetail::extract_session_key;
+use crate::handlers::{STREAM_NAME_HEADER_KEY, TelemetryType};
+use crate::otel::logs::{OTEL_LOG_KNOWN_FIELD_LIST, flatten_otel_protobuf};
+use crate::otel::metrics::{OTEL_METRICS_KNOWN_FIELD_LIST, flatten_otel_metrics_protobuf};
+use crate::otel::traces::{OTEL_TRACES_KNOWN_FIELD_LIST, flatten_otel_traces_protobuf};
+use crate::parseable::PARSEABLE;
+use crate::rbac::{self, Users};
+
+/// OTLP gRPC receiver implementing the collector `LogsService`, `MetricsService` and `TraceService`
+#[derive(Clone, Debug, Default)]
+pub struct OtelGrpcServiceImpl {}
+
+#[tonic::async_trait]
+impl LogsService for OtelGrpcServiceImpl {
+    async fn export(
+        &self,
+        request: Request<ExportLogsServiceRequest>,
+    ) -> Result<Response<ExportLogsServiceResponse>, Status> {
+        ingest(
+            request,
+            LogSource::OtelLogs,
+            &OTEL_LOG_KNOWN_FIELD_LIST,
+            TelemetryType::Logs,
+            flatten_otel_protobuf,
+        )
+        .await?;
+
+        Ok(Response::new(ExportLogsServiceResponse {
+            partial_success: None,
+        }))
+    }
+}
+
+#[tonic::async_trait]
+impl MetricsService for OtelGrpcServiceImpl {
+    async fn export(
+        &self,
+        request: Request<ExportMetricsServiceRequest>,
+    ) -> Result<Response<ExportMetricsServiceResponse>, Status> {
+        ingest(
+            request,
+            LogSource::OtelMetrics,
+            &OTEL_METRICS_KNOWN_FIELD_LIST,
+            TelemetryType::Metrics,
+            flatten_otel_metrics_protobuf,
+        )
+        .await?;
+
+        Ok(Response::new(ExportMetricsServiceResponse {
+            partial_success: None,
+        }))
+    }
+}
+
+#[tonic::async_trait]
+impl TraceService for OtelGrpcServiceImpl {
+    async fn export(
+        &self,
+        request: Request<ExportTraceServiceRequest>,
+    ) -> Result<Response<ExportTraceServiceResponse>, Status> {
+        ingest(
+            request,
+            LogSource::OtelTraces,
+            &OTEL_TRACES_KNOWN_FIELD_LIST,
+            TelemetryType::Traces,
+            flatten_otel_traces_protobuf,
+        )
+        .await?;
+
+        Ok(Response::new(ExportTraceServiceResponse {
+            partial_success: None,
+        }))
+    }
+}
+
+// Common ingestion flow for all OTLP services,
+// mirrors the checks done by the `/v1/{logs,metrics,traces}` HTTP handlers
+async fn ingest<T>(
+    request: Request<T>,
+    log_source: LogSource,
+    known_fields: &[&str],
+    telemetry_type: TelemetryType,
+    flatten: fn(&T) -> Vec<Value>,
+) -> Result<(), Status> {
+    if !RESOURCE_CHECK_ENABLED.load(std::sync::atomic::Ordering::SeqCst) {
+        return Err(Status::unavailable("Server resources over-utilized"));
+    }
+
+    let stream_name = extract_stream_name(request.metadata())?;
+    authorize_ingest(request.metadata(), &stream_name)?;
+
+    let p_custom_fields = get_custom_fields_from_metadata(&request, &log_source);
+
+    let (stream_name, log_source, ..) =
+        create_otel_stream_if_not_exists(stream_name, log_source, known_fields, telemetry_type)
+            .await
+            .map_err(into_status)?;
+
+    push_otel_records(
+        flatten(request.get_ref()),
+        &stream_name,
+        &log_source,
+        &p_custom_fields,
+        None,
+        telemetry_type,
+    )
+    .await
+    .map_err(into_status)
+}
+
+/// Stream name is passed in the `x-p-stream` metadata key, just like the HTTP header
+fn extract_stream_name(metadata: &MetadataMap) -> Result<String, Status> {
+    metadata
+        .get(STREAM_NAME_HEADER_KEY)
+        .and_then(|value| value.to_str().ok())
+        .filter(|value| !value.is_empty())
+        .map(|value| value.to_owned())
+        .ok_or_else(|| {
+            Status::invalid_argument(format!(
+                "Stream name not found in `{STREAM_NAME_HEADER_KEY}` metadata"
+            ))
+        })
+}
+
+fn authorize_ingest(metadata: &MetadataMap, stream_name: &str) -> Result<(), Status> {
+    let key = extract_session_key(metadata).map_err(|e| *e)?;
+
+    match Users.authorize(key, rbac::role::Action::Ingest, Some(stream_name), None) {
+        rbac::Response::Authorized => Ok(()),
+        rbac::Response::UnAuthorized => Err(Status::permission_denied(
+            "user is not authorized to access this resource",
+        )),
+        rbac::Response::ReloadRequired => Err(Status::unauthenticated("reload required")),
+    }
+}
+
+pub fn get_custom_fields_from_metadata<T>(
+    request: &Request<T>,
+    log_source: &LogSource,
+) -> HashMap<String, String> {
+    let metadata = request.metadata();
+    let user_agent = metadata
+        .get("user-agent")
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    let source_ip = request
+        .remote_addr()
+        .map(|addr| addr.ip().to_string())
+        .unwrap_or_default();
+
+    let mut p_custom_fields = collect_custom_fields(
+        user_agent,
+        &source_ip,
+        metadata.iter().filter_map(|entry| match entry {
+            KeyAndValueRef::Ascii(key, value) => Some((key.as_str(), value.to_str().ok())),
+            KeyAndValueRef::Binary(..) => None,
+        }),
+    );
+    p_custom_fields
+        .entry(FORMAT_KEY.to_string())
+        .or_insert_with(|| log_source.to_string());
+
+    p_custom_fields
+}
+
+fn into_status(err: PostError) -> Status {
+    error!("{err}");
+    match actix_web::ResponseError::status_code(&err) {
+        actix_web::http::StatusCode::BAD_REQUEST => Status::invalid_argument(err.to_string()),
+        actix_web::http::StatusCode::NOT_FOUND => Status::not_found(err.to_string()),
+        _ => Status::internal(err.to_string()),
+    }
+}
+
+/// Builds the OTLP gRPC receiver if `P_OTEL_GRPC_PORT` is set, serving it over TLS when
+/// the server is configured with a certificate. Fails rather than fall back to plaintext
+/// if that certificate can't be used.
+pub fn server() -> anyhow::Result<
+    Option<impl Future<Output = Result<(), Box<dyn std::error::Error + Send>>> + Send>,
+> {
+    let Some(port) = PARSEABLE.options.otel_grpc_port else {
+        return Ok(None);
+    };
+    let mut addr: SocketAddr = PARSEABLE
+        .options
+        .address
+        .parse()
+        .expect("valid socket address");
+    addr.set_port(port);
+
+    let max_decoding_message_size = PARSEABLE.options.max_event_payload_size;
+
+    let logs = LogsServiceServer::new(OtelGrpcServiceImpl::default())
+        .max_decoding_message_size(max_decoding_message_size)
+        .accept_compressed(CompressionEncoding::Gzip)
+        .accept_compressed(CompressionEncoding::Zstd);
+    let metrics = MetricsServiceServer::new(OtelGrpcServiceImpl::default())
+        .max_decoding_message_size(max_decoding_message_size)
+        .accept_compressed(CompressionEncoding::Gzip)
+        .accept_compressed(CompressionEncoding::Zstd);
+    let traces = TraceServiceServer::new(OtelGrpcServiceImpl::default())
+        .max_decoding_message_size(max_decoding_message_size)
+        .accept_compressed(CompressionEncoding::Gzip)
+        .accept_compressed(CompressionEncoding::Zstd);
+
+    let mut server = Server::builder();
+    if let (Some(cert), Some(key)) = (
+        &PARSEABLE.options.tls_cert_path,
+        &PARSEABLE.options.tls_key_path,
+    ) {
+        let identity = Identity::from_pem(std::fs::read(cert)?, std::fs::read(key)?);
+        server = server.tls_config(ServerTlsConfig::new().identity(identity))?;
+    }
+
+    Ok(Some(
+        server
+            .add_service(logs)
+            .add_service(metrics)
+            .add_service(traces)
+            .serve(addr)
+            .map_err(|err| Box::new(err) as Box<dyn std::error::Error + Send>),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use tonic::metadata::MetadataValue;
+
+    use super::*;
+
+    #[test]
+    fn stream_name_is_read_from_metadata() {
+        let mut metadata = MetadataMap::new();
+        metadata.insert(STREAM_NAME_HEADER_KEY, MetadataValue::from_static("otel"));
+
+        assert_eq!(extract_stream_name(&metadata).unwrap(), "otel");
+    }
+
+    #[test]
+    fn missing_stream_name_is_rejected() {
+        let status = extract_stream_name(&MetadataMap::new()).unwrap_err();
+
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    }
+
+    #[test]
+    fn custom_fields_are_read_from_metadata() {
+        let mut request = Request::new(());
+        request
+            .metadata_mut()
+            .insert("user-agent", MetadataValue::from_static("otel-collector"));
+        request
+            .metadata_mut()
+            .insert("x-p-environment", MetadataValue::from_static("dev"));
+
+        let fields = get_custom_fields_from_metadata(&request, &LogSource::OtelLogs);
+
+        assert_eq!(fields.get("p_user_agent").unwrap(), "otel-collector");
+        assert_eq!(fields.get("environment").unwrap(), "dev");
+        assert_eq!(fields.get("p_format").unwrap(), "otel-logs");
+    }
+}
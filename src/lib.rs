@@ -27,6 +27,7 @@ pub mod connectors;
 pub mod correlation;
 pub mod enterprise;
 pub mod event;
+pub mod fluent;
 pub mod handlers;
 pub mod hottier;
 mod livetail;
@@ -47,6 +48,7 @@ mod static_schema;
 mod stats;
 pub mod storage;
 pub mod sync;
+pub mod syslog;
 pub mod users;
 pub mod utils;
 pub mod validator;
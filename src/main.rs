@@ -20,8 +20,8 @@ use std::process::exit;
 #[cfg(feature = "kafka")]
 use parseable::connectors;
 use parseable::{
-    IngestServer, ParseableServer, QueryServer, Server, banner, metrics, option::Mode,
-    parseable::PARSEABLE, rbac, storage,
+    IngestServer, ParseableServer, QueryServer, Server, banner, event::geoip, metrics,
+    option::Mode, parseable::PARSEABLE, rbac, storage,
 };
 use tokio::signal::ctrl_c;
 use tokio::sync::oneshot;
@@ -80,6 +80,11 @@ async fn main() -> anyhow::Result<()> {
     });
 
     let prometheus = metrics::build_metrics_handler();
+    // Load GeoIP databases on nodes that accept ingestion
+    if matches!(PARSEABLE.options.mode, Mode::Ingest | Mode::All) {
+        geoip::init();
+    }
+
     // Start servers
     #[cfg(feature = "kafka")]
     {
@@ -25,11 +25,13 @@ use std::sync::Arc;
 
 use crate::catalog::snapshot::ManifestItem;
 use crate::event::format::LogSourceEntry;
+use crate::event::settings::StreamSettings;
 use crate::handlers::TelemetryType;
 use crate::hottier::StreamHotTier;
 use crate::metrics::{
     EVENTS_INGESTED, EVENTS_INGESTED_DATE, EVENTS_INGESTED_SIZE, EVENTS_INGESTED_SIZE_DATE,
-    EVENTS_STORAGE_SIZE_DATE, LIFETIME_EVENTS_INGESTED, LIFETIME_EVENTS_INGESTED_SIZE,
+    EVENTS_INGESTED_WIRE_SIZE, EVENTS_STORAGE_SIZE_DATE, LIFETIME_EVENTS_INGESTED,
+    LIFETIME_EVENTS_INGESTED_SIZE,
 };
 use crate::storage::StreamType;
 use crate::storage::retention::Retention;
@@ -62,6 +64,14 @@ pub fn update_stats(
         .add(size as i64);
 }
 
+/// Size of the request body as received, which differs from the size in
+/// [`update_stats`] when the body was compressed
+pub fn update_wire_stats(stream_name: &str, origin: &'static str, wire_size: u64) {
+    EVENTS_INGESTED_WIRE_SIZE
+        .with_label_values(&[stream_name, origin])
+        .add(wire_size as i64);
+}
+
 /// In order to support backward compatability with streams created before v1.6.4,
 /// we will consider past versions of stream schema to be v0. Streams created with
 /// v1.6.4+ will be v1.
@@ -92,6 +102,7 @@ pub struct LogStreamMetadata {
     pub stream_type: StreamType,
     pub log_source: Vec<LogSourceEntry>,
     pub telemetry_type: TelemetryType,
+    pub settings: StreamSettings,
 }
 
 impl LogStreamMetadata {
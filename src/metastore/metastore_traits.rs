@@ -114,6 +114,11 @@ pub trait Metastore: std::fmt::Debug + Send + Sync {
     async fn put_correlation(&self, obj: &dyn MetastoreObject) -> Result<(), MetastoreError>;
     async fn delete_correlation(&self, obj: &dyn MetastoreObject) -> Result<(), MetastoreError>;
 
+    /// custom log formats
+    async fn get_log_formats(&self) -> Result<Vec<Bytes>, MetastoreError>;
+    async fn put_log_format(&self, obj: &dyn MetastoreObject) -> Result<(), MetastoreError>;
+    async fn delete_log_format(&self, obj: &dyn MetastoreObject) -> Result<(), MetastoreError>;
+
     /// stream metadata
     /// `get_base` when set to true, will fetch the stream.json present at the base of
     /// the stream (independent of Mode of server)
@@ -48,9 +48,9 @@ use crate::{
     option::Mode,
     parseable::PARSEABLE,
     storage::{
-        ALERTS_ROOT_DIRECTORY, ObjectStorage, ObjectStorageError, PARSEABLE_ROOT_DIRECTORY,
-        SETTINGS_ROOT_DIRECTORY, STREAM_METADATA_FILE_NAME, STREAM_ROOT_DIRECTORY,
-        TARGETS_ROOT_DIRECTORY,
+        ALERTS_ROOT_DIRECTORY, LOG_FORMATS_DIR, ObjectStorage, ObjectStorageError,
+        PARSEABLE_ROOT_DIRECTORY, SETTINGS_ROOT_DIRECTORY, STREAM_METADATA_FILE_NAME,
+        STREAM_ROOT_DIRECTORY, TARGETS_ROOT_DIRECTORY,
         object_storage::{
             alert_json_path, alert_state_json_path, filter_path, manifest_path, mttr_json_path,
             parseable_json_path, schema_path, stream_json_path, to_bytes,
@@ -586,6 +586,40 @@ impl Metastore for ObjectStoreMetastore {
             .await?)
     }
 
+    /// Fetch all custom log formats
+    async fn get_log_formats(&self) -> Result<Vec<Bytes>, MetastoreError> {
+        let base_path = RelativePathBuf::from_iter([SETTINGS_ROOT_DIRECTORY, LOG_FORMATS_DIR]);
+        let format_bytes = self
+            .storage
+            .get_objects(
+                Some(&base_path),
+                Box::new(|file_name| file_name.ends_with(".json")),
+            )
+            .await?;
+
+        Ok(format_bytes)
+    }
+
+    /// Save a custom log format
+    async fn put_log_format(&self, obj: &dyn MetastoreObject) -> Result<(), MetastoreError> {
+        let path = obj.get_object_path();
+
+        Ok(self
+            .storage
+            .put_object(&RelativePathBuf::from(path), to_bytes(obj))
+            .await?)
+    }
+
+    /// Delete a custom log format
+    async fn delete_log_format(&self, obj: &dyn MetastoreObject) -> Result<(), MetastoreError> {
+        let path = obj.get_object_path();
+
+        Ok(self
+            .storage
+            .delete_object(&RelativePathBuf::from(path))
+            .await?)
+    }
+
     /// Fetch an `ObjectStoreFormat` file
     ///
     /// If `get_base` is true, get the one at the base of the stream directory else depends on Mode
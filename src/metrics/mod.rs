@@ -55,6 +55,66 @@ pub static EVENTS_INGESTED_SIZE: Lazy<IntGaugeVec> = Lazy::new(|| {
     .expect("metric can be created")
 });
 
+pub static EVENTS_INGESTED_WIRE_SIZE: Lazy<IntGaugeVec> = Lazy::new(|| {
+    IntGaugeVec::new(
+        Opts::new(
+            "events_ingested_wire_size",
+            "Events ingested size bytes for a stream, as received before decompression",
+        )
+        .namespace(METRICS_NAMESPACE),
+        &["stream", "format"],
+    )
+    .expect("metric can be created")
+});
+
+pub static EVENTS_DEDUPLICATED: Lazy<IntCounterVec> = Lazy::new(|| {
+    IntCounterVec::new(
+        Opts::new(
+            "events_deduplicated",
+            "Duplicate events dropped on ingestion for a stream",
+        )
+        .namespace(METRICS_NAMESPACE),
+        &["stream"],
+    )
+    .expect("metric can be created")
+});
+
+pub static REDACTED_VALUES: Lazy<IntCounterVec> = Lazy::new(|| {
+    IntCounterVec::new(
+        Opts::new(
+            "redacted_values",
+            "Matches of the redaction rules of a stream on ingestion",
+        )
+        .namespace(METRICS_NAMESPACE),
+        &["stream", "detector", "action"],
+    )
+    .expect("metric can be created")
+});
+
+pub static FORMAT_TYPE_MISMATCHES: Lazy<IntCounterVec> = Lazy::new(|| {
+    IntCounterVec::new(
+        Opts::new(
+            "format_type_mismatches",
+            "Values extracted by a known log format that aren't of the type declared for their field",
+        )
+        .namespace(METRICS_NAMESPACE),
+        &["format", "field"],
+    )
+    .expect("metric can be created")
+});
+
+pub static SYSLOG_MESSAGES_DROPPED: Lazy<IntCounterVec> = Lazy::new(|| {
+    IntCounterVec::new(
+        Opts::new(
+            "syslog_messages_dropped",
+            "Syslog messages over the size limit dropped by the listeners of a stream",
+        )
+        .namespace(METRICS_NAMESPACE),
+        &["stream"],
+    )
+    .expect("metric can be created")
+});
+
 pub static STORAGE_SIZE: Lazy<IntGaugeVec> = Lazy::new(|| {
     IntGaugeVec::new(
         Opts::new("storage_size", "Storage size bytes for a stream").namespace(METRICS_NAMESPACE),
@@ -455,6 +515,21 @@ fn custom_metrics(registry: &Registry) {
     registry
         .register(Box::new(EVENTS_INGESTED_SIZE.clone()))
         .expect("metric can be registered");
+    registry
+        .register(Box::new(EVENTS_INGESTED_WIRE_SIZE.clone()))
+        .expect("metric can be registered");
+    registry
+        .register(Box::new(EVENTS_DEDUPLICATED.clone()))
+        .expect("metric can be registered");
+    registry
+        .register(Box::new(REDACTED_VALUES.clone()))
+        .expect("metric can be registered");
+    registry
+        .register(Box::new(FORMAT_TYPE_MISMATCHES.clone()))
+        .expect("metric can be registered");
+    registry
+        .register(Box::new(SYSLOG_MESSAGES_DROPPED.clone()))
+        .expect("metric can be registered");
     registry
         .register(Box::new(STORAGE_SIZE.clone()))
         .expect("metric can be registered");
@@ -622,6 +697,12 @@ pub async fn fetch_stats_from_storage(stream_name: &str, stats: FullStats) {
     LIFETIME_EVENTS_STORAGE_SIZE
         .with_label_values(&["data", stream_name, "parquet"])
         .set(stats.lifetime_stats.storage as i64);
+    let duplicates_dropped = EVENTS_DEDUPLICATED.with_label_values(&[stream_name]);
+    duplicates_dropped.reset();
+    duplicates_dropped.inc_by(stats.duplicates_dropped);
+    EVENTS_INGESTED_WIRE_SIZE
+        .with_label_values(&[stream_name, "json"])
+        .set(stats.wire_size as i64);
 }
 
 // Helper functions for tracking billing metrics
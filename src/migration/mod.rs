@@ -371,6 +371,7 @@ async fn setup_logstream_metadata(
         stream_type,
         log_source,
         telemetry_type,
+        settings,
         ..
     } = serde_json::from_value(stream_metadata_value).unwrap_or_default();
 
@@ -407,6 +408,7 @@ async fn setup_logstream_metadata(
         stream_type,
         log_source,
         telemetry_type,
+        settings,
     };
 
     Ok(metadata)
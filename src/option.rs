@@ -136,6 +136,10 @@ pub mod validation {
             .ok_or_else(|| "Socket Address for server is invalid".to_string())
     }
 
+    pub fn syslog_listener(s: &str) -> Result<crate::syslog::SyslogListener, String> {
+        s.parse()
+    }
+
     pub fn url(s: &str) -> Result<url::Url, String> {
         url::Url::parse(s).map_err(|_| "Invalid URL provided".to_string())
     }
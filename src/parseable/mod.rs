@@ -32,7 +32,7 @@ use bytes::Bytes;
 use chrono::Utc;
 use clap::{Parser, error::ErrorKind};
 use once_cell::sync::Lazy;
-pub use staging::StagingError;
+pub use staging::{StagingError, writer::FlushAck};
 use streams::StreamRef;
 pub use streams::{Stream, StreamNotFound, Streams};
 use tokio::try_join;
@@ -365,6 +365,7 @@ impl Parseable {
         let schema_version = stream_metadata.schema_version;
         let log_source = stream_metadata.log_source;
         let telemetry_type = stream_metadata.telemetry_type;
+        let settings = stream_metadata.settings;
         let mut metadata = LogStreamMetadata::new(
             created_at,
             time_partition,
@@ -381,6 +382,7 @@ impl Parseable {
         // Set hot tier fields from the stored metadata
         metadata.hot_tier_enabled = hot_tier_enabled;
         metadata.hot_tier.clone_from(&hot_tier);
+        metadata.settings = settings;
 
         let ingestor_id = INGESTOR_META
             .get()
@@ -32,6 +32,7 @@ use arrow_select::concat::concat_batches;
 use chrono::Utc;
 use itertools::Itertools;
 use rand::distributions::{Alphanumeric, DistString};
+use tokio::sync::watch;
 use tracing::error;
 
 use crate::{
@@ -51,6 +52,33 @@ pub struct DiskWriter {
     inner: StreamWriter<BufWriter<File>>,
     path: PathBuf,
     range: TimeRange,
+    /// Whether the file was finished, once it is
+    flushed: watch::Sender<Option<bool>>,
+}
+
+/// Acknowledges the data written to a staging file once the file is finished,
+/// such that the data survives a restart
+#[derive(Debug, Clone)]
+pub struct FlushAck(watch::Receiver<Option<bool>>);
+
+impl FlushAck {
+    /// Acknowledges data that isn't staged on disk, hence not waited for
+    pub fn done() -> Self {
+        Self(watch::channel(Some(true)).1)
+    }
+
+    /// Whether the file was finished, `None` while it is still being written
+    pub fn status(&self) -> Option<bool> {
+        *self.0.borrow()
+    }
+
+    /// Waits for the file to be finished, returns `false` if it couldn't be
+    pub async fn flushed(mut self) -> bool {
+        match self.0.wait_for(Option::is_some).await {
+            Ok(flushed) => flushed.unwrap_or_default(),
+            Err(_) => false,
+        }
+    }
 }
 
 impl DiskWriter {
@@ -69,7 +97,17 @@ impl DiskWriter {
             .open(&path)?;
         let inner = StreamWriter::try_new_buffered(file, schema)?;
 
-        Ok(Self { inner, path, range })
+        Ok(Self {
+            inner,
+            path,
+            range,
+            flushed: watch::channel(None).0,
+        })
+    }
+
+    /// Acknowledgement of the data written so far, and to be
+    pub fn ack(&self) -> FlushAck {
+        FlushAck(self.flushed.subscribe())
     }
 
     pub fn is_current(&self) -> bool {
@@ -87,6 +125,12 @@ impl Drop for DiskWriter {
     fn drop(&mut self) {
         if let Err(err) = self.inner.finish() {
             error!("Couldn't finish arrow file {:?}, error = {err}", self.path);
+            self.flushed.send_replace(Some(false));
+            return;
+        }
+        if let Err(err) = self.inner.get_ref().get_ref().sync_all() {
+            error!("Couldn't sync arrow file {:?}, error = {err}", self.path);
+            self.flushed.send_replace(Some(false));
             return;
         }
 
@@ -106,7 +150,10 @@ impl Drop for DiskWriter {
 
         if let Err(err) = std::fs::rename(&self.path, &arrow_path) {
             error!("Couldn't rename file {:?}, error = {err}", self.path);
+            self.flushed.send_replace(Some(false));
+            return;
         }
+        self.flushed.send_replace(Some(true));
     }
 }
 
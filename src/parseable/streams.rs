@@ -50,7 +50,10 @@ use crate::{
     cli::Options,
     event::{
         DEFAULT_TIMESTAMP_KEY,
+        dedup::{Dedup, DedupIndex},
         format::{LogSource, LogSourceEntry},
+        sampling::RateLimiter,
+        settings::StreamSettings,
     },
     hottier::StreamHotTier,
     metadata::{LogStreamMetadata, SchemaVersion},
@@ -65,7 +68,7 @@ use super::{
     staging::{
         StagingError,
         reader::{MergedRecordReader, MergedReverseRecordReader},
-        writer::{DiskWriter, Writer},
+        writer::{DiskWriter, FlushAck, Writer},
     },
 };
 
@@ -110,6 +113,8 @@ pub struct Stream {
     pub data_path: PathBuf,
     pub options: Arc<Options>,
     pub writer: Mutex<Writer>,
+    pub rate_limiter: Mutex<RateLimiter>,
+    pub dedup_index: Mutex<DedupIndex>,
     pub ingestor_id: Option<String>,
 }
 
@@ -129,11 +134,14 @@ impl Stream {
             data_path,
             options,
             writer: Mutex::new(Writer::default()),
+            rate_limiter: Mutex::new(RateLimiter::default()),
+            dedup_index: Mutex::new(DedupIndex::default()),
             ingestor_id,
         })
     }
 
     // Concatenates record batches and puts them in memory store for each event.
+    // Returns the acknowledgement of the batch being durable in staging.
     pub fn push(
         &self,
         schema_key: &str,
@@ -141,7 +149,7 @@ impl Stream {
         parsed_timestamp: NaiveDateTime,
         custom_partition_values: &HashMap<String, String>,
         stream_type: StreamType,
-    ) -> Result<(), StagingError> {
+    ) -> Result<FlushAck, StagingError> {
         let mut guard = match self.writer.lock() {
             Ok(guard) => guard,
             Err(poisoned) => {
@@ -152,12 +160,14 @@ impl Stream {
                 poisoned.into_inner()
             }
         };
+        let mut ack = FlushAck::done();
         if self.options.mode != Mode::Query || stream_type == StreamType::Internal {
             let filename =
                 self.filename_by_partition(schema_key, parsed_timestamp, custom_partition_values);
             match guard.disk.get_mut(&filename) {
                 Some(writer) => {
                     writer.write(record)?;
+                    ack = writer.ack();
                 }
                 None => {
                     // entry is not present thus we create it
@@ -172,6 +182,7 @@ impl Stream {
                         .expect("File and RecordBatch both are checked");
 
                     writer.write(record)?;
+                    ack = writer.ack();
                     guard.disk.insert(filename, writer);
                 }
             };
@@ -179,7 +190,7 @@ impl Stream {
 
         guard.mem.push(schema_key, record);
 
-        Ok(())
+        Ok(ack)
     }
 
     pub fn filename_by_partition(
@@ -847,6 +858,10 @@ impl Stream {
         self.metadata.read().expect(LOCK_EXPECT).retention.clone()
     }
 
+    pub fn get_settings(&self) -> StreamSettings {
+        self.metadata.read().expect(LOCK_EXPECT).settings.clone()
+    }
+
     pub fn get_schema_version(&self) -> SchemaVersion {
         self.metadata.read().expect(LOCK_EXPECT).schema_version
     }
@@ -874,6 +889,63 @@ impl Stream {
         self.metadata.write().expect(LOCK_EXPECT).retention = Some(retention);
     }
 
+    /// Replaces the ingestion settings, if the dedup option changed, keys seen so far
+    /// are forgotten as they may no longer apply
+    pub fn set_settings(&self, settings: StreamSettings) {
+        let mut metadata = self.metadata.write().expect(LOCK_EXPECT);
+        if metadata.settings.dedup != settings.dedup {
+            *self.dedup_index.lock().expect(LOCK_EXPECT) = DedupIndex::default();
+        }
+        metadata.settings = settings;
+    }
+
+    /// Drops the rows of `rb` that duplicate events seen within the dedup window of the stream,
+    /// counting them in the stream's stats. Returns the remaining rows and their keys, to be
+    /// [remembered](Self::remember_keys) once the rows are staged.
+    pub fn deduplicate(&self, rb: RecordBatch) -> Result<(RecordBatch, Vec<u128>), StagingError> {
+        let Some(dedup) = self.dedup() else {
+            return Ok((rb, vec![]));
+        };
+        let num_rows = rb.num_rows();
+        let (rb, keys) = self.dedup_index.lock().expect(LOCK_EXPECT).deduplicate(
+            &dedup,
+            rb,
+            Utc::now().timestamp(),
+        )?;
+        let dropped = num_rows - rb.num_rows();
+        if dropped > 0 {
+            metrics::EVENTS_DEDUPLICATED
+                .with_label_values(&[&self.stream_name])
+                .inc_by(dropped as u64);
+        }
+
+        Ok((rb, keys))
+    }
+
+    /// Remembers the keys of rows staged with `ack`, such that their duplicates are dropped
+    /// unless the rows can't be made durable
+    pub fn remember_keys(&self, keys: Vec<u128>, ack: &FlushAck) {
+        if keys.is_empty() {
+            return;
+        }
+        let Some(dedup) = self.dedup() else {
+            return;
+        };
+        self.dedup_index
+            .lock()
+            .expect(LOCK_EXPECT)
+            .insert(&dedup, keys, Utc::now().timestamp());
+    }
+
+    fn dedup(&self) -> Option<Dedup> {
+        self.metadata
+            .read()
+            .expect(LOCK_EXPECT)
+            .settings
+            .dedup
+            .clone()
+    }
+
     pub fn set_first_event_at(&self, first_event_at: &str) {
         self.metadata.write().expect(LOCK_EXPECT).first_event_at = Some(first_event_at.to_owned());
     }
@@ -1120,6 +1192,7 @@ mod tests {
     use arrow_array::{Int32Array, StringArray, TimestampMillisecondArray};
     use arrow_schema::{DataType, Field, TimeUnit};
     use chrono::{NaiveDate, TimeDelta, Utc};
+    use futures_util::FutureExt;
     use temp_dir::TempDir;
     use tokio::time::sleep;
 
@@ -1438,6 +1511,95 @@ mod tests {
         assert_eq!(staging.arrow_files().len(), 0);
     }
 
+    #[tokio::test]
+    async fn push_acknowledged_once_flushed_to_disk() {
+        let temp_dir = TempDir::new().unwrap();
+        let options = Arc::new(Options {
+            local_staging_path: temp_dir.path().to_path_buf(),
+            ..Default::default()
+        });
+        let staging = Stream::new(options, "test_stream", LogStreamMetadata::default(), None);
+        let schema = Schema::new(vec![Field::new("id", DataType::Int32, false)]);
+        let batch =
+            RecordBatch::try_new(Arc::new(schema), vec![Arc::new(Int32Array::from(vec![1]))])
+                .unwrap();
+
+        let ack = staging
+            .push(
+                "abc",
+                &batch,
+                Utc::now().naive_utc(),
+                &HashMap::new(),
+                StreamType::UserDefined,
+            )
+            .unwrap();
+        // the file of the current minute is kept open
+        staging.flush(false);
+        assert!(ack.clone().flushed().now_or_never().is_none());
+
+        staging.flush(true);
+        assert!(ack.flushed().await);
+        assert_eq!(staging.arrow_files().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn retried_events_staged_after_failed_push_or_flush() {
+        let temp_dir = TempDir::new().unwrap();
+        let options = Arc::new(Options {
+            local_staging_path: temp_dir.path().to_path_buf(),
+            ..Default::default()
+        });
+        let mut metadata = LogStreamMetadata::default();
+        metadata.settings.dedup = Some(Dedup {
+            fields: vec!["id".to_owned()],
+            window_secs: 60,
+            max_keys: 100,
+        });
+        let staging = Stream::new(options, "test_stream", metadata, None);
+        let schema = Schema::new(vec![Field::new("id", DataType::Int32, false)]);
+        let batch = RecordBatch::try_new(
+            Arc::new(schema),
+            vec![Arc::new(Int32Array::from(vec![1, 2]))],
+        )
+        .unwrap();
+        let push = |rb: &RecordBatch| {
+            staging.push(
+                "abc",
+                rb,
+                Utc::now().naive_utc(),
+                &HashMap::new(),
+                StreamType::UserDefined,
+            )
+        };
+
+        // the staging directory can't be created
+        std::fs::write(&staging.data_path, "").unwrap();
+        let (rb, _) = staging.deduplicate(batch.clone()).unwrap();
+        assert!(push(&rb).is_err());
+        std::fs::remove_file(&staging.data_path).unwrap();
+
+        let (rb, keys) = staging.deduplicate(batch.clone()).unwrap();
+        assert_eq!(rb.num_rows(), 2);
+        let ack = push(&rb).unwrap();
+        staging.remember_keys(keys, &ack);
+        assert_eq!(staging.deduplicate(batch.clone()).unwrap().0.num_rows(), 0);
+
+        // the staging file can't be renamed once finished
+        for entry in std::fs::read_dir(&staging.data_path).unwrap() {
+            std::fs::remove_file(entry.unwrap().path()).unwrap();
+        }
+        staging.flush(true);
+        assert!(!ack.flushed().await);
+
+        let (rb, keys) = staging.deduplicate(batch).unwrap();
+        assert_eq!(rb.num_rows(), 2);
+        let ack = push(&rb).unwrap();
+        staging.remember_keys(keys, &ack);
+        staging.flush(true);
+        assert!(ack.flushed().await);
+        assert_eq!(staging.arrow_files().len(), 1);
+    }
+
     #[tokio::test]
     async fn miss_current_arrow_file_when_converting_to_parquet() {
         let temp_dir = TempDir::new().unwrap();
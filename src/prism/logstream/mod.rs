@@ -114,7 +114,7 @@ async fn get_stats(stream_name: &str) -> Result<QueriedStats, PrismLogstreamErro
     let time = Utc::now();
 
     let stats = {
-        let ingestion_stats = IngestionStats::new(
+        let mut ingestion_stats = IngestionStats::new(
             stats.current_stats.events,
             stats.current_stats.ingestion,
             stats.lifetime_stats.events,
@@ -123,6 +123,8 @@ async fn get_stats(stream_name: &str) -> Result<QueriedStats, PrismLogstreamErro
             stats.deleted_stats.ingestion,
             "json",
         );
+        ingestion_stats.duplicates_dropped = stats.duplicates_dropped;
+        ingestion_stats.wire_size = stats.wire_size;
         let storage_stats = StorageStats::new(
             stats.current_stats.storage,
             stats.lifetime_stats.storage,
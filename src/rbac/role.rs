@@ -35,6 +35,8 @@ pub enum Action {
     DeleteStream,
     GetRetention,
     PutRetention,
+    GetStreamSettings,
+    PutStreamSettings,
     PutHotTierEnabled,
     GetHotTierEnabled,
     DeleteHotTierEnabled,
@@ -75,6 +77,9 @@ pub enum Action {
     CreateCorrelation,
     DeleteCorrelation,
     PutCorrelation,
+    GetLogFormat,
+    PutLogFormat,
+    DeleteLogFormat,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
@@ -152,7 +157,10 @@ impl RoleBuilder {
                 | Action::GetUserGroup
                 | Action::DeleteUserGroup
                 | Action::ModifyUserGroup
-                | Action::GetAnalytics => Permission::Unit(action),
+                | Action::GetAnalytics
+                | Action::GetLogFormat
+                | Action::PutLogFormat
+                | Action::DeleteLogFormat => Permission::Unit(action),
                 Action::Query
                 | Action::QueryLLM
                 | Action::AddLLM
@@ -166,6 +174,8 @@ impl RoleBuilder {
                 | Action::GetStats
                 | Action::GetRetention
                 | Action::PutRetention
+                | Action::GetStreamSettings
+                | Action::PutStreamSettings
                 | Action::All => Permission::Resource(action, self.resource_type.clone().unwrap()),
             };
             perms.push(perm);
@@ -239,6 +249,8 @@ pub mod model {
                 Action::GetStats,
                 Action::GetRetention,
                 Action::PutRetention,
+                Action::GetStreamSettings,
+                Action::PutStreamSettings,
                 Action::PutHotTierEnabled,
                 Action::GetHotTierEnabled,
                 Action::DeleteHotTierEnabled,
@@ -259,6 +271,9 @@ pub mod model {
                 Action::CreateDashboard,
                 Action::DeleteDashboard,
                 Action::GetUserRoles,
+                Action::GetLogFormat,
+                Action::PutLogFormat,
+                Action::DeleteLogFormat,
             ],
             resource_type: Some(ParseableResourceType::All),
         }
@@ -278,6 +293,7 @@ pub mod model {
                 Action::GetAlert,
                 Action::DeleteAlert,
                 Action::GetRetention,
+                Action::GetStreamSettings,
                 Action::PutHotTierEnabled,
                 Action::GetHotTierEnabled,
                 Action::DeleteHotTierEnabled,
@@ -299,6 +315,7 @@ pub mod model {
                 Action::CreateFilter,
                 Action::DeleteFilter,
                 Action::GetUserRoles,
+                Action::GetLogFormat,
             ],
             resource_type: None,
         }
@@ -329,9 +346,11 @@ pub mod model {
                 Action::CreateDashboard,
                 Action::DeleteDashboard,
                 Action::GetRetention,
+                Action::GetStreamSettings,
                 Action::GetStreamInfo,
                 Action::GetUserRoles,
                 Action::GetAlert,
+                Action::GetLogFormat,
             ],
             resource_type: None,
         }
@@ -26,10 +26,10 @@ use prometheus::{IntCounterVec, IntGaugeVec};
 use tracing::warn;
 
 use crate::metrics::{
-    DELETED_EVENTS_STORAGE_SIZE, EVENTS_DELETED, EVENTS_DELETED_SIZE, EVENTS_INGESTED,
-    EVENTS_INGESTED_DATE, EVENTS_INGESTED_SIZE, EVENTS_INGESTED_SIZE_DATE,
-    EVENTS_STORAGE_SIZE_DATE, LIFETIME_EVENTS_INGESTED, LIFETIME_EVENTS_INGESTED_SIZE,
-    LIFETIME_EVENTS_STORAGE_SIZE, STORAGE_SIZE,
+    DELETED_EVENTS_STORAGE_SIZE, EVENTS_DEDUPLICATED, EVENTS_DELETED, EVENTS_DELETED_SIZE,
+    EVENTS_INGESTED, EVENTS_INGESTED_DATE, EVENTS_INGESTED_SIZE, EVENTS_INGESTED_SIZE_DATE,
+    EVENTS_INGESTED_WIRE_SIZE, EVENTS_STORAGE_SIZE_DATE, LIFETIME_EVENTS_INGESTED,
+    LIFETIME_EVENTS_INGESTED_SIZE, LIFETIME_EVENTS_STORAGE_SIZE, REDACTED_VALUES, STORAGE_SIZE,
 };
 use crate::storage::{ObjectStorage, ObjectStorageError, ObjectStoreFormat};
 
@@ -46,6 +46,13 @@ pub struct FullStats {
     pub lifetime_stats: Stats,
     pub current_stats: Stats,
     pub deleted_stats: Stats,
+    /// Duplicate events dropped on ingestion
+    #[serde(default)]
+    pub duplicates_dropped: u64,
+    /// Size of the ingested request bodies as received, i.e. before decompression,
+    /// which isn't reduced as data is deleted
+    #[serde(default)]
+    pub wire_size: u64,
 }
 
 pub fn get_current_stats(stream_name: &str, format: &'static str) -> Option<FullStats> {
@@ -88,6 +95,14 @@ pub fn get_current_stats(stream_name: &str, format: &'static str) -> Option<Full
         .get_metric_with_label_values(&storage_size_labels)
         .ok()?
         .get() as u64;
+    let duplicates_dropped = EVENTS_DEDUPLICATED
+        .get_metric_with_label_values(&[stream_name])
+        .ok()?
+        .get();
+    let wire_size = EVENTS_INGESTED_WIRE_SIZE
+        .get_metric_with_label_values(&event_labels)
+        .ok()?
+        .get() as u64;
 
     Some(FullStats {
         lifetime_stats: Stats {
@@ -105,6 +120,8 @@ pub fn get_current_stats(stream_name: &str, format: &'static str) -> Option<Full
             ingestion: events_deleted_size,
             storage: deleted_events_storage_size,
         },
+        duplicates_dropped,
+        wire_size,
     })
 }
 
@@ -176,6 +193,7 @@ pub fn delete_stats(stream_name: &str, format: &'static str) -> prometheus::Resu
 
     remove_label_values(&EVENTS_INGESTED, &event_labels);
     remove_label_values(&EVENTS_INGESTED_SIZE, &event_labels);
+    remove_label_values(&EVENTS_INGESTED_WIRE_SIZE, &event_labels);
     remove_label_values(&STORAGE_SIZE, &storage_size_labels);
     remove_label_values(&EVENTS_DELETED, &event_labels);
     remove_label_values(&EVENTS_DELETED_SIZE, &event_labels);
@@ -183,10 +201,14 @@ pub fn delete_stats(stream_name: &str, format: &'static str) -> prometheus::Resu
     remove_label_values(&LIFETIME_EVENTS_INGESTED, &event_labels);
     remove_label_values(&LIFETIME_EVENTS_INGESTED_SIZE, &event_labels);
     remove_label_values(&LIFETIME_EVENTS_STORAGE_SIZE, &storage_size_labels);
+    if let Err(e) = EVENTS_DEDUPLICATED.remove_label_values(&[stream_name]) {
+        warn!("Unable to delete labels- {stream_name:?}\nwith error- {e}");
+    }
 
     delete_with_label_prefix(&EVENTS_INGESTED_DATE, &event_labels);
     delete_with_label_prefix(&EVENTS_INGESTED_SIZE_DATE, &event_labels);
     delete_with_label_prefix(&EVENTS_STORAGE_SIZE_DATE, &storage_size_labels);
+    delete_with_label_prefix(&REDACTED_VALUES, &[stream_name]);
 
     Ok(())
 }
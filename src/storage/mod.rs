@@ -23,7 +23,7 @@ use tokio::task::JoinError;
 
 use crate::{
     catalog::snapshot::Snapshot,
-    event::format::LogSourceEntry,
+    event::{format::LogSourceEntry, settings::StreamSettings},
     handlers::TelemetryType,
     hottier::StreamHotTier,
     metadata::SchemaVersion,
@@ -66,6 +66,7 @@ pub const PARSEABLE_ROOT_DIRECTORY: &str = ".parseable";
 pub const SCHEMA_FILE_NAME: &str = ".schema";
 pub const ALERTS_ROOT_DIRECTORY: &str = ".alerts";
 pub const SETTINGS_ROOT_DIRECTORY: &str = ".settings";
+pub const LOG_FORMATS_DIR: &str = "log_formats";
 pub const TARGETS_ROOT_DIRECTORY: &str = ".targets";
 pub const MANIFEST_FILE: &str = "manifest.json";
 
@@ -131,6 +132,8 @@ pub struct ObjectStoreFormat {
     pub log_source: Vec<LogSourceEntry>,
     #[serde(default)]
     pub telemetry_type: TelemetryType,
+    #[serde(flatten)]
+    pub settings: StreamSettings,
 }
 
 impl MetastoreObject for ObjectStoreFormat {
@@ -274,6 +277,7 @@ impl Default for ObjectStoreFormat {
             hot_tier: None,
             log_source: vec![LogSourceEntry::default()],
             telemetry_type: TelemetryType::Logs,
+            settings: StreamSettings::default(),
         }
     }
 }
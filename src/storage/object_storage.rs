@@ -45,6 +45,7 @@ use ulid::Ulid;
 use crate::catalog::{self, snapshot::Snapshot};
 use crate::event::format::LogSource;
 use crate::event::format::LogSourceEntry;
+use crate::event::settings::StreamSettings;
 use crate::handlers::http::fetch_schema;
 use crate::handlers::http::modal::ingest_server::INGESTOR_EXPECT;
 use crate::handlers::http::modal::ingest_server::INGESTOR_META;
@@ -519,6 +520,27 @@ pub trait ObjectStorage: Debug + Send + Sync + 'static {
             .map_err(|e| ObjectStorageError::MetastoreError(Box::new(e.to_detail())))?)
     }
 
+    async fn put_settings(
+        &self,
+        stream_name: &str,
+        settings: &StreamSettings,
+    ) -> Result<(), ObjectStorageError> {
+        let mut stream_metadata: ObjectStoreFormat = serde_json::from_slice(
+            &PARSEABLE
+                .metastore
+                .get_stream_json(stream_name, false)
+                .await
+                .map_err(|e| ObjectStorageError::MetastoreError(Box::new(e.to_detail())))?,
+        )?;
+        stream_metadata.settings = settings.clone();
+
+        Ok(PARSEABLE
+            .metastore
+            .put_stream_json(&stream_metadata, stream_name)
+            .await
+            .map_err(|e| ObjectStorageError::MetastoreError(Box::new(e.to_detail())))?)
+    }
+
     async fn upsert_stream_metadata(
         &self,
         stream_name: &str,
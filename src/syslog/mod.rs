@@ -0,0 +1,355 @@
+/*
+ * Parseable Server (C) 2022 - 2025 Parseable, Inc.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ */
+
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    net::{IpAddr, SocketAddr},
+    str::FromStr,
+};
+
+use serde_json::{Value, json};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, BufReader},
+    net::{TcpListener, UdpSocket},
+};
+use tracing::{debug, error, info, warn};
+
+use crate::{
+    event::{FORMAT_KEY, format::LogSource},
+    handlers::{
+        TelemetryType,
+        http::{
+            ingest::PostError,
+            modal::utils::ingest_utils::{
+                collect_custom_fields, flatten_and_push_logs, setup_stream_for_ingestion,
+            },
+        },
+    },
+    metrics::SYSLOG_MESSAGES_DROPPED,
+    parseable::PARSEABLE,
+};
+
+pub mod parser;
+
+/// Maximum number of messages pushed to the stream at once
+const MAX_BATCH_SIZE: usize = 1000;
+/// Largest payload of a UDP datagram
+const MAX_DATAGRAM_SIZE: usize = 65535;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyslogProtocol {
+    Tcp,
+    Udp,
+}
+
+impl Display for SyslogProtocol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            SyslogProtocol::Tcp => "tcp",
+            SyslogProtocol::Udp => "udp",
+        })
+    }
+}
+
+/// A syslog listener, given as `<tcp|udp>:<port>=<stream>`, e.g. `udp:514=network`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyslogListener {
+    pub protocol: SyslogProtocol,
+    pub port: u16,
+    pub stream: String,
+}
+
+impl FromStr for SyslogListener {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || format!("Invalid syslog listener {s}, expected <tcp|udp>:<port>=<stream>");
+        let (address, stream) = s.trim().split_once('=').ok_or_else(err)?;
+        let (protocol, port) = address.split_once(':').ok_or_else(err)?;
+        let protocol = match protocol.to_lowercase().as_str() {
+            "tcp" => SyslogProtocol::Tcp,
+            "udp" => SyslogProtocol::Udp,
+            _ => return Err(err()),
+        };
+        let port = port.parse().map_err(|_| err())?;
+        if stream.is_empty() {
+            return Err(err());
+        }
+
+        Ok(Self {
+            protocol,
+            port,
+            stream: stream.to_owned(),
+        })
+    }
+}
+
+impl Display for SyslogListener {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}={}", self.protocol, self.port, self.stream)
+    }
+}
+
+/// Spawns a task for each of the syslog listeners configured with `P_SYSLOG_LISTENERS`
+pub fn init() {
+    for listener in PARSEABLE.options.syslog_listeners.iter().cloned() {
+        tokio::spawn(async move {
+            let result = match listener.protocol {
+                SyslogProtocol::Tcp => serve_tcp(&listener).await,
+                SyslogProtocol::Udp => serve_udp(&listener).await,
+            };
+            if let Err(err) = result {
+                error!("Syslog listener {listener} stopped: {err}");
+            }
+        });
+    }
+}
+
+fn bind_address(port: u16) -> SocketAddr {
+    let mut addr: SocketAddr = PARSEABLE
+        .options
+        .address
+        .parse()
+        .expect("valid socket address");
+    addr.set_port(port);
+    addr
+}
+
+async fn serve_tcp(listener: &SyslogListener) -> std::io::Result<()> {
+    let socket = TcpListener::bind(bind_address(listener.port)).await?;
+    info!("Syslog listener {listener} started");
+
+    loop {
+        let (conn, peer) = socket.accept().await?;
+        let stream = listener.stream.clone();
+        tokio::spawn(async move {
+            if let Err(err) = handle_tcp_connection(conn, peer.ip(), &stream).await {
+                warn!("Syslog connection from {peer} closed: {err}");
+            }
+        });
+    }
+}
+
+// Reads frames off a TCP connection, pushing them to the stream whenever
+// the batch is full or no more data is readily available on the connection
+async fn handle_tcp_connection(
+    conn: impl AsyncRead + Unpin,
+    peer: IpAddr,
+    stream: &str,
+) -> std::io::Result<()> {
+    let mut reader = BufReader::new(conn);
+    let mut batch = Vec::new();
+    let max_size = PARSEABLE.options.max_event_payload_size;
+
+    while let Some(frame) = read_frame(&mut reader, max_size).await? {
+        match frame {
+            Frame::Message(message) if !message.trim_ascii().is_empty() => {
+                batch.push(to_record(&message))
+            }
+            Frame::Message(_) => {}
+            Frame::Oversized => {
+                warn!("Dropped syslog message from {peer} over {max_size} bytes");
+                SYSLOG_MESSAGES_DROPPED.with_label_values(&[stream]).inc();
+            }
+        }
+        if !batch.is_empty() && (batch.len() >= MAX_BATCH_SIZE || reader.buffer().is_empty()) {
+            push(stream, std::mem::take(&mut batch), peer).await;
+        }
+    }
+    if !batch.is_empty() {
+        push(stream, batch, peer).await;
+    }
+
+    Ok(())
+}
+
+/// A message read off a TCP connection
+#[derive(Debug, PartialEq, Eq)]
+enum Frame {
+    Message(Vec<u8>),
+    /// A newline delimited message over the size limit, which is dropped
+    /// rather than split into several messages
+    Oversized,
+}
+
+/// Reads a single message from a TCP connection, messages are either octet counted
+/// (`<len> <message>`) or newline delimited as per RFC 6587. Returns None at EOF.
+async fn read_frame<R: AsyncRead + Unpin>(
+    reader: &mut BufReader<R>,
+    max_size: usize,
+) -> std::io::Result<Option<Frame>> {
+    let starts_with_digit = match reader.fill_buf().await?.first() {
+        Some(byte) => byte.is_ascii_digit(),
+        None => return Ok(None),
+    };
+
+    let mut frame = Vec::new();
+    if starts_with_digit {
+        (&mut *reader).take(12).read_until(b' ', &mut frame).await?;
+        let len = std::str::from_utf8(&frame)
+            .ok()
+            .and_then(|len| len.trim_end().parse::<usize>().ok())
+            .filter(|len| *len <= max_size)
+            .ok_or_else(|| {
+                std::io::Error::new(std::io::ErrorKind::InvalidData, "invalid octet count")
+            })?;
+        frame.resize(len, 0);
+        reader.read_exact(&mut frame).await?;
+    } else {
+        let read = (&mut *reader)
+            .take(max_size as u64)
+            .read_until(b'\n', &mut frame)
+            .await?;
+        if read == max_size && frame.last() != Some(&b'\n') && !reader.fill_buf().await?.is_empty()
+        {
+            skip_line(reader).await?;
+            return Ok(Some(Frame::Oversized));
+        }
+    }
+
+    Ok(Some(Frame::Message(frame)))
+}
+
+// Discards the rest of the current line
+async fn skip_line<R: AsyncRead + Unpin>(reader: &mut BufReader<R>) -> std::io::Result<()> {
+    loop {
+        let buf = reader.fill_buf().await?;
+        if buf.is_empty() {
+            return Ok(());
+        }
+        match buf.iter().position(|byte| *byte == b'\n') {
+            Some(end) => {
+                reader.consume(end + 1);
+                return Ok(());
+            }
+            None => {
+                let len = buf.len();
+                reader.consume(len);
+            }
+        }
+    }
+}
+
+async fn serve_udp(listener: &SyslogListener) -> std::io::Result<()> {
+    let socket = UdpSocket::bind(bind_address(listener.port)).await?;
+    info!("Syslog listener {listener} started");
+
+    let mut buf = vec![0; MAX_DATAGRAM_SIZE];
+    loop {
+        // each datagram is a single message, drain whatever is readily
+        // available and push it in batches grouped by the sender
+        let mut batches: HashMap<IpAddr, Vec<Value>> = HashMap::new();
+        let (len, peer) = socket.recv_from(&mut buf).await?;
+        batches
+            .entry(peer.ip())
+            .or_default()
+            .push(to_record(&buf[..len]));
+        for _ in 1..MAX_BATCH_SIZE {
+            match socket.try_recv_from(&mut buf) {
+                Ok((len, peer)) => batches
+                    .entry(peer.ip())
+                    .or_default()
+                    .push(to_record(&buf[..len])),
+                Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => break,
+                Err(err) => return Err(err),
+            }
+        }
+
+        for (peer, batch) in batches {
+            push(&listener.stream, batch, peer).await;
+        }
+    }
+}
+
+// Messages that fail to parse are kept as is, along with the reason
+fn to_record(frame: &[u8]) -> Value {
+    let line = String::from_utf8_lossy(frame);
+    match parser::parse(&line) {
+        Ok(message) => message.into_json(),
+        Err(err) => {
+            debug!("Failed to parse syslog message: {err}");
+            json!({
+                "message": line.trim_end(),
+                "parse_error": err.to_string(),
+            })
+        }
+    }
+}
+
+async fn push(stream_name: &str, records: Vec<Value>, peer: IpAddr) {
+    let size = records.len();
+    if let Err(err) = try_push(stream_name, records, peer).await {
+        error!("Failed to ingest {size} syslog messages into {stream_name}: {err}");
+    }
+}
+
+async fn try_push(stream_name: &str, records: Vec<Value>, peer: IpAddr) -> Result<(), PostError> {
+    setup_stream_for_ingestion(stream_name, LogSource::Syslog, TelemetryType::Logs).await?;
+
+    let mut p_custom_fields = collect_custom_fields("", &peer.to_string(), std::iter::empty());
+    p_custom_fields.insert(FORMAT_KEY.to_string(), LogSource::Syslog.to_string());
+
+    flatten_and_push_logs(
+        Value::Array(records),
+        stream_name,
+        &LogSource::Syslog,
+        &p_custom_fields,
+        None,
+        TelemetryType::Logs,
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn listener_from_str() {
+        assert_eq!(
+            "udp:5514=network".parse::<SyslogListener>().unwrap(),
+            SyslogListener {
+                protocol: SyslogProtocol::Udp,
+                port: 5514,
+                stream: "network".to_owned(),
+            }
+        );
+        assert!("http:5514=network".parse::<SyslogListener>().is_err());
+        assert!("tcp:5514".parse::<SyslogListener>().is_err());
+    }
+
+    #[tokio::test]
+    async fn oversized_lines_are_dropped() {
+        let mut reader = BufReader::new(&b"short\nthis line is too long\nok\n"[..]);
+
+        let mut frames = vec![];
+        while let Some(frame) = read_frame(&mut reader, 8).await.unwrap() {
+            frames.push(frame);
+        }
+
+        assert_eq!(
+            frames,
+            vec![
+                Frame::Message(b"short\n".to_vec()),
+                Frame::Oversized,
+                Frame::Message(b"ok\n".to_vec()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,409 @@
+/*
+ * Parseable Server (C) 2022 - 2025 Parseable, Inc.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ */
+
+use chrono::{DateTime, Datelike, Duration, FixedOffset, NaiveDateTime, TimeZone, Utc};
+use serde_json::{Map, Value, json};
+
+const FACILITY_NAMES: [&str; 24] = [
+    "kern",
+    "user",
+    "mail",
+    "daemon",
+    "auth",
+    "syslog",
+    "lpr",
+    "news",
+    "uucp",
+    "cron",
+    "authpriv",
+    "ftp",
+    "ntp",
+    "security",
+    "console",
+    "solaris-cron",
+    "local0",
+    "local1",
+    "local2",
+    "local3",
+    "local4",
+    "local5",
+    "local6",
+    "local7",
+];
+
+const SEVERITY_NAMES: [&str; 8] = [
+    "emerg", "alert", "crit", "err", "warning", "notice", "info", "debug",
+];
+
+#[derive(Debug, thiserror::Error, PartialEq)]
+pub enum Error {
+    #[error("Missing or malformed PRI part")]
+    InvalidPriority,
+    #[error("Malformed RFC 5424 header")]
+    InvalidHeader,
+    #[error("Malformed RFC 5424 timestamp: {0}")]
+    InvalidTimestamp(String),
+    #[error("Malformed RFC 5424 structured data")]
+    InvalidStructuredData,
+}
+
+/// A syslog message, as defined by either RFC 5424 or the older BSD format of RFC 3164
+#[derive(Debug, Default, PartialEq)]
+pub struct SyslogMessage {
+    pub facility: u8,
+    pub severity: u8,
+    /// Only set for RFC 5424 messages
+    pub version: Option<u8>,
+    pub timestamp: Option<DateTime<FixedOffset>>,
+    pub hostname: Option<String>,
+    pub appname: Option<String>,
+    pub procid: Option<String>,
+    pub msgid: Option<String>,
+    /// SD-ID along with its list of params, in order of occurrence
+    pub structured_data: Vec<(String, Vec<(String, String)>)>,
+    pub message: Option<String>,
+}
+
+impl SyslogMessage {
+    /// Converts the message into a json object, structured data is kept nested
+    /// and ends up as `structured_data_<sd-id>_<param>` columns once flattened
+    pub fn into_json(self) -> Value {
+        let mut map = Map::new();
+        map.insert("facility".to_owned(), json!(self.facility));
+        map.insert(
+            "facility_name".to_owned(),
+            json!(FACILITY_NAMES[self.facility as usize]),
+        );
+        map.insert("severity".to_owned(), json!(self.severity));
+        map.insert(
+            "severity_name".to_owned(),
+            json!(SEVERITY_NAMES[self.severity as usize]),
+        );
+        if let Some(version) = self.version {
+            map.insert("version".to_owned(), json!(version));
+        }
+        if let Some(timestamp) = self.timestamp {
+            map.insert("timestamp".to_owned(), json!(timestamp.to_rfc3339()));
+        }
+        for (key, value) in [
+            ("hostname", self.hostname),
+            ("appname", self.appname),
+            ("procid", self.procid),
+            ("msgid", self.msgid),
+            ("message", self.message),
+        ] {
+            if let Some(value) = value {
+                map.insert(key.to_owned(), Value::String(value));
+            }
+        }
+        if !self.structured_data.is_empty() {
+            let structured_data = self
+                .structured_data
+                .into_iter()
+                .map(|(id, params)| {
+                    let params = params
+                        .into_iter()
+                        .map(|(name, value)| (name, Value::String(value)))
+                        .collect::<Map<_, _>>();
+                    (id, Value::Object(params))
+                })
+                .collect::<Map<_, _>>();
+            map.insert("structured_data".to_owned(), Value::Object(structured_data));
+        }
+
+        Value::Object(map)
+    }
+}
+
+/// Parses a single syslog message, RFC 5424 is detected by the version following the PRI part,
+/// anything else is parsed leniently as RFC 3164
+pub fn parse(line: &str) -> Result<SyslogMessage, Error> {
+    let line = line.trim_end_matches(['\r', '\n', '\0']);
+    let (facility, severity, rest) = parse_priority(line)?;
+
+    match rest.split_once(' ') {
+        Some((version, rest))
+            if !version.is_empty()
+                && version.len() <= 2
+                && version.bytes().all(|b| b.is_ascii_digit()) =>
+        {
+            let version = version.parse().map_err(|_| Error::InvalidHeader)?;
+            parse_rfc5424(facility, severity, version, rest)
+        }
+        _ => Ok(parse_rfc3164(facility, severity, rest, Utc::now())),
+    }
+}
+
+fn parse_priority(line: &str) -> Result<(u8, u8, &str), Error> {
+    let rest = line.strip_prefix('<').ok_or(Error::InvalidPriority)?;
+    let (priority, rest) = rest.split_once('>').ok_or(Error::InvalidPriority)?;
+    if priority.is_empty() || priority.len() > 3 {
+        return Err(Error::InvalidPriority);
+    }
+    let priority: u8 = priority.parse().map_err(|_| Error::InvalidPriority)?;
+    if priority > 191 {
+        return Err(Error::InvalidPriority);
+    }
+
+    Ok((priority / 8, priority % 8, rest))
+}
+
+// HEADER fields are separated by a single space, "-" stands for a nil value
+fn next_field(rest: &mut &str) -> Result<Option<String>, Error> {
+    let (field, remaining) = rest.split_once(' ').unwrap_or((rest, ""));
+    if field.is_empty() {
+        return Err(Error::InvalidHeader);
+    }
+    *rest = remaining;
+
+    Ok((field != "-").then(|| field.to_owned()))
+}
+
+fn parse_rfc5424(
+    facility: u8,
+    severity: u8,
+    version: u8,
+    mut rest: &str,
+) -> Result<SyslogMessage, Error> {
+    let timestamp = next_field(&mut rest)?
+        .map(|ts| DateTime::parse_from_rfc3339(&ts).map_err(|_| Error::InvalidTimestamp(ts)))
+        .transpose()?;
+    let hostname = next_field(&mut rest)?;
+    let appname = next_field(&mut rest)?;
+    let procid = next_field(&mut rest)?;
+    let msgid = next_field(&mut rest)?;
+
+    let (structured_data, rest) = if let Some(rest) = rest.strip_prefix('-') {
+        (vec![], rest)
+    } else {
+        parse_structured_data(rest)?
+    };
+
+    let message = rest
+        .strip_prefix(' ')
+        .map(|msg| msg.trim_start_matches('\u{feff}'))
+        .filter(|msg| !msg.is_empty())
+        .map(str::to_owned);
+
+    Ok(SyslogMessage {
+        facility,
+        severity,
+        version: Some(version),
+        timestamp,
+        hostname,
+        appname,
+        procid,
+        msgid,
+        structured_data,
+        message,
+    })
+}
+
+type StructuredData = Vec<(String, Vec<(String, String)>)>;
+
+// Parses one or more SD-ELEMENTs, i.e. `[id param="value" ...][id ...]`
+fn parse_structured_data(input: &str) -> Result<(StructuredData, &str), Error> {
+    let mut elements = vec![];
+    let mut rest = input;
+
+    while let Some(element) = rest.strip_prefix('[') {
+        let id_end = element
+            .find([' ', ']'])
+            .ok_or(Error::InvalidStructuredData)?;
+        let id = &element[..id_end];
+        if id.is_empty() {
+            return Err(Error::InvalidStructuredData);
+        }
+        rest = &element[id_end..];
+
+        let mut params = vec![];
+        loop {
+            if let Some(remaining) = rest.strip_prefix(']') {
+                rest = remaining;
+                break;
+            }
+            let param = rest.strip_prefix(' ').ok_or(Error::InvalidStructuredData)?;
+            let (name, value) = param
+                .split_once("=\"")
+                .ok_or(Error::InvalidStructuredData)?;
+            let (value, remaining) = read_param_value(value)?;
+            params.push((name.to_owned(), value));
+            rest = remaining;
+        }
+        elements.push((id.to_owned(), params));
+    }
+
+    if elements.is_empty() {
+        return Err(Error::InvalidStructuredData);
+    }
+
+    Ok((elements, rest))
+}
+
+// Reads a PARAM-VALUE up to the closing quote, unescaping `\"`, `\\` and `\]`
+fn read_param_value(input: &str) -> Result<(String, &str), Error> {
+    let mut value = String::new();
+    let mut chars = input.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Ok((value, &input[i + 1..])),
+            '\\' => match chars.next() {
+                Some((_, c @ ('"' | '\\' | ']'))) => value.push(c),
+                Some((_, c)) => {
+                    value.push('\\');
+                    value.push(c);
+                }
+                None => break,
+            },
+            c => value.push(c),
+        }
+    }
+
+    Err(Error::InvalidStructuredData)
+}
+
+// BSD syslog is loosely defined, `Mmm dd hh:mm:ss HOSTNAME TAG[PID]: MSG` is parsed
+// when possible, otherwise whatever follows the PRI part is kept as the message
+fn parse_rfc3164(facility: u8, severity: u8, rest: &str, now: DateTime<Utc>) -> SyslogMessage {
+    let mut message = SyslogMessage {
+        facility,
+        severity,
+        ..Default::default()
+    };
+
+    let Some(timestamp) = rest.get(..15).and_then(|ts| parse_bsd_timestamp(ts, now)) else {
+        message.message = Some(rest.to_owned()).filter(|msg| !msg.is_empty());
+        return message;
+    };
+    message.timestamp = Some(timestamp);
+
+    let rest = rest[15..].trim_start();
+    let (hostname, rest) = rest.split_once(' ').unwrap_or((rest, ""));
+    message.hostname = Some(hostname.to_owned()).filter(|host| !host.is_empty());
+
+    // TAG is alphanumeric and at most 32 chars, followed by an optional [PID] and a colon
+    let tag_end = rest
+        .find(|c: char| !(c.is_alphanumeric() || "-_./".contains(c)))
+        .unwrap_or(rest.len());
+    let (tag, after_tag) = rest.split_at(tag_end);
+    let (procid, after_pid) = match after_tag
+        .strip_prefix('[')
+        .and_then(|pid| pid.split_once(']'))
+    {
+        Some((pid, remaining)) => (Some(pid.to_owned()), remaining),
+        None => (None, after_tag),
+    };
+    match after_pid.strip_prefix(':') {
+        Some(msg) if !tag.is_empty() && tag.len() <= 32 => {
+            message.appname = Some(tag.to_owned());
+            message.procid = procid;
+            message.message = Some(msg.trim_start().to_owned());
+        }
+        _ => message.message = Some(rest.to_owned()),
+    }
+    message.message = message.message.filter(|msg| !msg.is_empty());
+
+    message
+}
+
+// BSD timestamps carry neither year nor timezone, UTC and the current year are assumed,
+// unless that puts the timestamp in the future, e.g. a December message read in January
+fn parse_bsd_timestamp(timestamp: &str, now: DateTime<Utc>) -> Option<DateTime<FixedOffset>> {
+    let parse = |year: i32| {
+        NaiveDateTime::parse_from_str(&format!("{year} {timestamp}"), "%Y %b %e %H:%M:%S").ok()
+    };
+    let mut parsed = parse(now.year())?;
+    if parsed > now.naive_utc() + Duration::days(1) {
+        parsed = parse(now.year() - 1)?;
+    }
+
+    Some(Utc.from_utc_datetime(&parsed).fixed_offset())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rfc5424_with_structured_data() {
+        let line = r#"<165>1 2003-10-11T22:14:15.003Z mymachine.example.com evntslog - ID47 [exampleSDID@32473 iut="3" eventSource="Appli\"cation"][examplePriority@32473 class="high"] An application event"#;
+
+        let message = parse(line).unwrap();
+
+        assert_eq!(
+            message.into_json(),
+            json!({
+                "facility": 20,
+                "facility_name": "local4",
+                "severity": 5,
+                "severity_name": "notice",
+                "version": 1,
+                "timestamp": "2003-10-11T22:14:15.003+00:00",
+                "hostname": "mymachine.example.com",
+                "appname": "evntslog",
+                "msgid": "ID47",
+                "message": "An application event",
+                "structured_data": {
+                    "exampleSDID@32473": {"iut": "3", "eventSource": "Appli\"cation"},
+                    "examplePriority@32473": {"class": "high"},
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn rfc5424_with_nil_values() {
+        let message = parse("<34>1 - - - - - -").unwrap();
+
+        assert_eq!(message.facility, 4);
+        assert_eq!(message.severity, 2);
+        assert!(message.timestamp.is_none());
+        assert!(message.hostname.is_none());
+        assert!(message.message.is_none());
+    }
+
+    #[test]
+    fn rfc3164() {
+        let now = Utc.with_ymd_and_hms(2025, 1, 2, 0, 0, 0).unwrap();
+
+        let message = parse_rfc3164(
+            4,
+            2,
+            "Dec  1 22:14:15 mymachine su[123]: 'su root' failed for lonvick",
+            now,
+        );
+
+        assert_eq!(
+            message.timestamp.unwrap().to_rfc3339(),
+            "2024-12-01T22:14:15+00:00"
+        );
+        assert_eq!(message.hostname.as_deref(), Some("mymachine"));
+        assert_eq!(message.appname.as_deref(), Some("su"));
+        assert_eq!(message.procid.as_deref(), Some("123"));
+        assert_eq!(
+            message.message.as_deref(),
+            Some("'su root' failed for lonvick")
+        );
+    }
+
+    #[test]
+    fn invalid_priority_is_rejected() {
+        assert_eq!(parse("<192>1 - - - - - -"), Err(Error::InvalidPriority));
+        assert_eq!(parse("no priority"), Err(Error::InvalidPriority));
+    }
+}